@@ -47,18 +47,19 @@ async fn main() -> steward::Result<()> {
                 PoolEntry::Process(server::watch()),
                 PoolEntry::ProcessWithDep {
                     process: client::watch(),
-                    dependency: Box::new(HttpService {
-                        tag: "server".to_string(),
-                        addr: format!(
-                            "http://{host}:{port}",
-                            host = Config::SERVER_HOST(),
-                            port = Config::SERVER_PORT()
+                    dependency: Box::new(
+                        HttpService::new(
+                            "server",
+                            Config::SERVER_HOST(),
+                            Config::SERVER_PORT(),
+                            "/",
+                            false,
+                            HttpMethod::GET,
+                            Duration::from_secs(30),
                         )
-                        .parse()
-                        .unwrap(),
-                        method: HttpMethod::GET,
-                        timeout: Duration::from_secs(30),
-                    }),
+                        .unwrap()
+                        .hint("is the server running? Try `cargo run -p server`"),
+                    ),
                 },
             ])
             .await?;
@@ -0,0 +1,229 @@
+use std::io;
+
+use crate::{cmd::sh_quote, Env, Shell};
+
+/// Defines where and how a [`Cmd`](crate::Cmd) is executed.
+///
+/// Every backend spawns a local process, so commands with any backend can be run as one-off commands
+/// or as processes of a [`ProcessPool`](crate::ProcessPool), e.g. to mix local watchers with remote
+/// or containerized services in one pool:
+///
+/// ```ignore
+/// ProcessPool::run(vec![
+///     process! { tag: "client", cmd: client::watch() },
+///     process! {
+///         tag: "db",
+///         cmd: cmd! { "tail -f /var/log/postgresql/current.log", env: Env::empty(), pwd: Loc::root() }
+///             .backend(Backend::Docker { container: "app-db-1".to_string(), workdir: None, options: vec![] }),
+///     },
+///     process! {
+///         tag: "staging",
+///         cmd: cmd! { "journalctl -fu app", env: Env::empty(), pwd: Loc::root() }
+///             .backend(Backend::Ssh { host: "staging".to_string(), workdir: None, options: vec!["-tt".to_string()] }),
+///     },
+/// ])
+/// .await
+/// ```
+///
+/// Remote backends (`Ssh` and `Docker`) run the command with the [`Shell`](Shell) of the command on the remote side,
+/// so on Windows, a Unix shell must be set explicitly, e.g. `Shell::new("sh")`. The working directory of
+/// the command is not used remotely, the `workdir` of a backend is used instead.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Runs a command with the [`Shell`](Shell) of the command locally.
+    #[default]
+    Local,
+    /// Runs a program directly, without a shell. The command is split into a program and its arguments
+    /// following the `sh` quoting rules, but no expansions (variables, globs etc.) are performed.
+    /// Saves spawning a shell and makes sure that signals are delivered to the program itself.
+    Exec,
+    /// Runs a command on a remote host via `ssh`. The environment of the command is set on the remote host.
+    ///
+    /// Stopping a local `ssh` process doesn't stop the remote command, unless a terminal is allocated
+    /// (pass `-tt` in `options`).
+    Ssh {
+        /// Host to connect to, e.g. `deploy@staging.example.com` or an alias from `~/.ssh/config`.
+        host: String,
+        /// Working directory on the remote host. The default directory of the user is used if it's not set.
+        workdir: Option<String>,
+        /// Additional options of `ssh`.
+        options: Vec<String>,
+    },
+    /// Runs a command in a running container via `docker exec`. The environment of the command is set
+    /// in the container (its values are not exposed in the arguments of `docker`).
+    Docker {
+        /// Name or id of a container.
+        container: String,
+        /// Working directory in the container. The working directory of the container is used if it's not set.
+        workdir: Option<String>,
+        /// Additional options of `docker exec`, e.g. `--user`.
+        options: Vec<String>,
+    },
+}
+
+impl Backend {
+    /// Returns `true` if a command runs on the local machine.
+    pub fn is_local(&self) -> bool {
+        match self {
+            Self::Local | Self::Exec => true,
+            Self::Ssh { .. } | Self::Docker { .. } => false,
+        }
+    }
+
+    /// Program and its arguments that run the command with the environment.
+    pub(crate) fn argv(&self, exe: &str, shell: &Shell, env: &Env) -> io::Result<Vec<String>> {
+        let shell_argv = || {
+            let mut argv = vec![shell.program.to_owned()];
+            argv.extend(shell.args(exe).into_iter().map(str::to_owned));
+            argv
+        };
+        match self {
+            Self::Local => Ok(shell_argv()),
+            Self::Exec => match self::split(exe) {
+                Some(argv) if !argv.is_empty() => Ok(argv),
+                Some(_) => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Unable to exec an empty command",
+                )),
+                None => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unable to exec a command with unterminated quote: {}", exe),
+                )),
+            },
+            Self::Ssh {
+                host,
+                workdir,
+                options,
+            } => {
+                let mut remote = vec![];
+                if let Some(workdir) = workdir {
+                    remote.push(format!("cd {} &&", sh_quote(workdir)));
+                }
+                remote.push("exec".to_string());
+                let env = env.sorted();
+                if !env.is_empty() {
+                    remote.push("env".to_string());
+                    for (key, val) in env {
+                        remote.push(sh_quote(&format!("{}={}", key, val)));
+                    }
+                }
+                remote.extend(shell_argv().iter().map(|x| sh_quote(x)));
+
+                let mut argv = vec!["ssh".to_string()];
+                argv.extend(options.iter().cloned());
+                argv.extend([host.to_owned(), "--".to_string(), remote.join(" ")]);
+                Ok(argv)
+            }
+            Self::Docker {
+                container,
+                workdir,
+                options,
+            } => {
+                let mut argv = vec!["docker".to_string(), "exec".to_string()];
+                argv.extend(options.iter().cloned());
+                if let Some(workdir) = workdir {
+                    argv.extend(["--workdir".to_string(), workdir.to_owned()]);
+                }
+                // Values are taken from the environment of the `docker` process
+                for (key, _) in env.sorted() {
+                    argv.extend(["--env".to_string(), key.to_owned()]);
+                }
+                argv.push(container.to_owned());
+                argv.extend(shell_argv());
+                Ok(argv)
+            }
+        }
+    }
+}
+
+/// Splits a command into words following the `sh` quoting rules. Returns `None` if a quote is not terminated.
+fn split(exe: &str) -> Option<Vec<String>> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut chars = exe.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if let Some(word) = word.take() {
+                    words.push(word);
+                }
+            }
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\' | '$' | '`') => word.push(c),
+                            '\n' => (),
+                            c => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') => (),
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => word.get_or_insert_with(String::new).push('\\'),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if let Some(word) = word {
+        words.push(word);
+    }
+    Some(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_words() {
+        assert_eq!(
+            split(r#"node  'my app.js' --name="a \"b\"" x\ y ''"#).unwrap(),
+            ["node", "my app.js", r#"--name=a "b""#, "x y", ""]
+        );
+        assert_eq!(split("echo 'oops"), None);
+    }
+
+    #[test]
+    fn ssh_argv() {
+        let backend = Backend::Ssh {
+            host: "staging".to_string(),
+            workdir: Some("/srv/my app".to_string()),
+            options: vec!["-tt".to_string()],
+        };
+        let argv = backend
+            .argv(
+                "echo $GREETING",
+                &Shell::new("sh"),
+                &Env::one("GREETING", "it's me"),
+            )
+            .unwrap();
+        assert_eq!(
+            argv,
+            [
+                "ssh",
+                "-tt",
+                "staging",
+                "--",
+                r#"cd '/srv/my app' && exec env 'GREETING=it'\''s me' sh -c 'echo $GREETING'"#
+            ]
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use std::{future::Future, time::Duration};
+
+use tokio::time::{self, Instant};
+
+use crate::{Error, Result};
+
+/// Time budget of a [`Cmd`](crate::Cmd) or a [`Task`](crate::Task), to keep CI pipelines and dev-stack startup
+/// times honest.
+///
+/// - When the soft budget is exceeded, a warning is printed and the run continues.
+/// - When the hard budget is exceeded, the run is stopped and fails with [`Error::BudgetExceeded`](Error::BudgetExceeded).
+///   A command is interrupted (and killed if it doesn't exit within its [`KillTimeout`](crate::KillTimeout)),
+///   a pool is shut down, and a function is cancelled.
+///
+/// ```ignore
+/// client::build()
+///     .budget(Budget::soft(Duration::from_secs(60)).hard(Duration::from_secs(300)))
+///     .run()
+///     .await?;
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Budget {
+    /// Duration after which a warning is printed.
+    pub soft: Option<Duration>,
+    /// Duration after which a run is stopped and fails.
+    pub hard: Option<Duration>,
+}
+
+impl Budget {
+    /// Constructs a budget with the soft limit only. The hard limit can be added via [`Budget::hard`](Budget::hard).
+    pub fn soft(soft: Duration) -> Self {
+        Self {
+            soft: Some(soft),
+            hard: None,
+        }
+    }
+
+    /// Sets the hard limit.
+    pub fn hard(self, hard: Duration) -> Self {
+        Self {
+            hard: Some(hard),
+            ..self
+        }
+    }
+
+    /// Returns `true` if neither limit is set.
+    pub fn is_none(&self) -> bool {
+        self.soft.is_none() && self.hard.is_none()
+    }
+
+    /// Runs a future that stops on its own at the hard limit (e.g. a command with a deadline). Prints a warning
+    /// at the soft limit, and fails if the hard limit is exceeded.
+    pub(crate) async fn watch<T>(
+        &self,
+        name: &str,
+        fut: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let started = Instant::now();
+        let res = self.warn(name, fut).await;
+        match self.hard {
+            Some(hard) if started.elapsed() >= hard => Err(Error::BudgetExceeded {
+                name: name.to_owned(),
+                budget: hard,
+            }),
+            Some(_) | None => res,
+        }
+    }
+
+    /// Runs a future, printing a warning at the soft limit. Drops the future and fails at the hard limit.
+    pub(crate) async fn enforce<T>(
+        &self,
+        name: &str,
+        fut: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let hard = match self.hard {
+            Some(hard) => hard,
+            None => return self.warn(name, fut).await,
+        };
+        match time::timeout(hard, self.warn(name, fut)).await {
+            Ok(res) => res,
+            Err(_) => Err(Error::BudgetExceeded {
+                name: name.to_owned(),
+                budget: hard,
+            }),
+        }
+    }
+
+    async fn warn<T>(&self, name: &str, fut: impl Future<Output = T>) -> T {
+        let soft = match self.soft {
+            Some(soft) => soft,
+            None => return fut.await,
+        };
+        tokio::pin!(fut);
+        tokio::select! {
+            res = &mut fut => return res,
+            _ = time::sleep(soft) => (),
+        }
+        console!(
+            "⚠️  {} exceeded its soft time budget of {}",
+            crate::fmt::style(name).bold(),
+            crate::fmt::duration(soft)
+        );
+        fut.await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn hard_budget() {
+        let budget = Budget::soft(Duration::from_millis(10)).hard(Duration::from_millis(20));
+        let res = budget
+            .enforce("sleep", async {
+                time::sleep(Duration::from_secs(10)).await;
+                Ok(())
+            })
+            .await;
+        assert!(
+            matches!(res, Err(Error::BudgetExceeded { budget, .. }) if budget == Duration::from_millis(20))
+        );
+        let res = budget.enforce("noop", async { Ok(()) }).await;
+        assert!(res.is_ok());
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use tokio::{process::Command, runtime::Handle};
+
+/// Resource limits of a process within a transient cgroup (v2). Linux only, requires `cgroup` feature.
+///
+/// When a process of a [`ProcessPool`](crate::ProcessPool) has limits, the pool creates a transient cgroup
+/// for it right before the start, places the process (and all its descendants) into it,
+/// and removes the cgroup when the process exits.
+///
+/// ```ignore
+/// process! { tag: "client", cmd: cmd! { ... } }.cgroup(CgroupLimits {
+///     memory_max: Some(2 * 1024 * 1024 * 1024),
+///     cpu_max: Some(2.0),
+///     ..Default::default()
+/// })
+/// ```
+///
+/// Note that cgroup v2 doesn't allow enabling controllers in a cgroup that contains processes,
+/// so usually the [`parent`](CgroupLimits::parent) must be set to a cgroup delegated to the current user,
+/// e.g. one created via `systemd-run --user --scope -p Delegate=yes`.
+#[derive(Clone, Debug, Default)]
+pub struct CgroupLimits {
+    /// Memory limit in bytes (`memory.max`).
+    pub memory_max: Option<u64>,
+    /// CPU limit in number of CPUs, e.g. `1.5` (`cpu.max`).
+    pub cpu_max: Option<f64>,
+    /// Parent cgroup, e.g. `/sys/fs/cgroup/user.slice/steward`. By default, the cgroup of the current process is used.
+    pub parent: Option<PathBuf>,
+}
+
+const CPU_PERIOD: u64 = 100_000;
+
+/// A cgroup that gets removed when dropped.
+pub(crate) struct TransientCgroup {
+    path: PathBuf,
+}
+
+impl TransientCgroup {
+    pub(crate) fn create(name: &str, limits: &CgroupLimits) -> io::Result<Self> {
+        let parent = match &limits.parent {
+            Some(parent) => parent.to_owned(),
+            None => current()?,
+        };
+
+        let mut controllers = vec![];
+        if limits.memory_max.is_some() {
+            controllers.push("+memory");
+        }
+        if limits.cpu_max.is_some() {
+            controllers.push("+cpu");
+        }
+        if !controllers.is_empty() {
+            fs::write(parent.join("cgroup.subtree_control"), controllers.join(" ")).map_err(
+                |err| {
+                    io::Error::new(
+                        err.kind(),
+                        format!(
+                            "unable to enable {} controllers in {}: {}",
+                            controllers.join(" "),
+                            parent.display(),
+                            err
+                        ),
+                    )
+                },
+            )?;
+        }
+
+        let path = parent.join(format!(
+            "steward-{}-{}",
+            crate::checkpoint::sanitize(name),
+            std::process::id()
+        ));
+        fs::create_dir_all(&path)?;
+        let cgroup = Self { path };
+
+        if let Some(bytes) = limits.memory_max {
+            fs::write(cgroup.path.join("memory.max"), bytes.to_string())?;
+        }
+        if let Some(cpus) = limits.cpu_max {
+            let quota = (cpus * CPU_PERIOD as f64).round() as u64;
+            fs::write(
+                cgroup.path.join("cpu.max"),
+                format!("{} {}", quota, CPU_PERIOD),
+            )?;
+        }
+
+        Ok(cgroup)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TransientCgroup {
+    fn drop(&mut self) {
+        let path = std::mem::take(&mut self.path);
+        // Removal is retried with sleeps in between, which must not block the runtime
+        match Handle::try_current() {
+            Ok(runtime) => drop(runtime.spawn_blocking(move || self::remove(&path))),
+            Err(_) => self::remove(&path),
+        }
+    }
+}
+
+fn remove(path: &Path) {
+    // Kills leftovers, if any. Available since Linux 5.14.
+    fs::write(path.join("cgroup.kill"), "1").ok();
+    for _ in 0..10 {
+        match fs::remove_dir(path) {
+            Ok(()) => return,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return,
+            Err(_) => thread::sleep(Duration::from_millis(50)),
+        }
+    }
+    console!("⚠️  Failed to remove cgroup {}", path.display());
+}
+
+/// Returns the cgroup of the current process.
+fn current() -> io::Result<PathBuf> {
+    let not_available = || io::Error::new(io::ErrorKind::NotFound, "cgroup v2 is not available");
+
+    // Usually `/sys/fs/cgroup`, or `/sys/fs/cgroup/unified` on systems with hybrid hierarchy
+    let mounts = fs::read_to_string("/proc/self/mounts")?;
+    let root = mounts
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .find_map(|fields| match fields[..] {
+            [_, path, "cgroup2", ..] => Some(PathBuf::from(path)),
+            _ => None,
+        })
+        .ok_or_else(not_available)?;
+
+    let cgroups = fs::read_to_string("/proc/self/cgroup")?;
+    cgroups
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(|path| root.join(path.trim_start_matches('/')))
+        .ok_or_else(not_available)
+}
+
+/// Configures the command to join the cgroup right before `exec`.
+pub(crate) fn join(command: &mut Command, cgroup: &Path) {
+    // Allocated before fork
+    let procs = cgroup.join("cgroup.procs");
+
+    // SAFETY: The closure only performs system calls, all the data is allocated before fork.
+    unsafe {
+        command.pre_exec(move || {
+            // `0` stands for the writing process
+            crate::sandbox::write(procs.as_path(), b"0").map_err(crate::sandbox::to_io_error)
+        });
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    fs,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{Cmd, Location, Result};
+
+/// Progress of a long sequence of steps, persisted under `.steward/checkpoints/<name>` directory of a project.
+///
+/// Every successfully completed step leaves a marker, so after a failure the sequence can be resumed
+/// from the failed step instead of being rerun from the beginning.
+///
+/// ```ignore
+/// let checkpoint = Checkpoint::new(&Loc::root(), "setup", cli.resume)?;
+///
+/// checkpoint.cmd("deps", &deps::install()).await?;
+/// checkpoint.cmd("server", &server::build()).await?;
+/// checkpoint.cmd("client", &client::build()).await?;
+/// checkpoint.func("seed", || db::seed()).await?;
+///
+/// checkpoint.finish()?;
+/// ```
+///
+/// A marker of a command step holds the command, so if the command is changed, the step is rerun.
+pub struct Checkpoint {
+    dir: PathBuf,
+}
+
+impl Checkpoint {
+    /// Constructs a checkpoint of a sequence. If `resume` is `true`, the steps completed in a previous run are skipped.
+    /// Otherwise, the progress of a previous run is discarded.
+    pub fn new<Loc>(root: &Loc, name: &str, resume: bool) -> Result<Self>
+    where
+        Loc: Location,
+    {
+        let dir = root
+            .as_path()
+            .join(".steward")
+            .join("checkpoints")
+            .join(self::sanitize(name));
+        if !resume {
+            self::remove_dir(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// Returns `true` if the step is completed.
+    pub fn is_done(&self, step: &str) -> bool {
+        self.marker(step).exists()
+    }
+
+    /// Runs a command via [`Cmd::run`](Cmd::run), unless the step with the same command is completed.
+    pub async fn cmd<Loc>(&self, step: &str, cmd: &Cmd<Loc>) -> Result<()>
+    where
+        Loc: Location,
+    {
+        let spec = format!("{} [@ {}]\n", cmd.exe(), cmd.workdir().display());
+        match fs::read_to_string(self.marker(step)) {
+            Ok(done) if done == spec => {
+                self.skip(step);
+                Ok(())
+            }
+            Ok(_) | Err(_) => {
+                cmd.run().await?;
+                fs::write(self.marker(step), spec)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Runs an async function, unless the step is completed.
+    pub async fn func<F, Fut>(&self, step: &str, f: F) -> Result<()>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        if self.is_done(step) {
+            self.skip(step);
+            return Ok(());
+        }
+        f().await?;
+        fs::write(self.marker(step), "")?;
+        Ok(())
+    }
+
+    /// Discards the progress once the whole sequence is completed, so the next run starts from the beginning.
+    pub fn finish(self) -> Result<()> {
+        self::remove_dir(&self.dir)?;
+        Ok(())
+    }
+
+    fn marker(&self, step: &str) -> PathBuf {
+        self.dir.join(self::sanitize(step))
+    }
+
+    fn skip(&self, step: &str) {
+        crate::print(format!("Skipping {}: completed in a previous run", step));
+    }
+}
+
+fn remove_dir(dir: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        Ok(()) | Err(_) => Ok(()),
+    }
+}
+
+/// Makes a name safe to use as a file name. Names made only of dots (e.g. `..`) and empty names
+/// are escaped as well, since they would point to the parent directory otherwise.
+pub(crate) fn sanitize(name: &str) -> String {
+    if name.chars().all(|c| c == '.') {
+        return format!("_{}", name.replace('.', "_"));
+    }
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn sanitize() {
+        assert_eq!(super::sanitize("db.seed"), "db.seed");
+        assert_eq!(super::sanitize("web/app"), "web_app");
+        assert_eq!(super::sanitize(".."), "___");
+        assert_eq!(super::sanitize("."), "__");
+        assert_eq!(super::sanitize(""), "_");
+    }
+}
@@ -1,32 +1,382 @@
 use std::{
-    io,
+    fmt, io,
     ops::Deref,
+    path::{Path, PathBuf},
     process::{self, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
     time::Duration,
 };
 
 use once_cell::sync::Lazy;
-use tokio::process::Command;
+use tokio::{
+    signal,
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time,
+};
 
-use crate::{Env, ExitResult, Location, Result, RunningProcess};
+use crate::{
+    env::{self, PATH},
+    executor::{self, Invocation, OutputMode},
+    inputs::Snapshot,
+    Backend, Budget, Env, EnvSource, Error, Location, OutputStream, Result, RetryPolicy,
+    RunningProcess,
+};
 
 /// Struct holds a specification of a command. Can be used for running one-off commands, long running processes etc.
+/// Can be constructed via [`Cmd::new`](Cmd::new) or convenience [`cmd!`](crate::cmd!) macro, and configured via its builder methods.
 #[derive(Clone)]
+#[non_exhaustive]
 pub struct Cmd<Loc> {
     /// Command to run.
     pub exe: String,
     /// Environment of a process.
     pub env: Env,
+    /// Sources of the environment loaded every time a process is spawned. See [`Cmd::env_source`](Cmd::env_source).
+    pub env_sources: Vec<Arc<dyn EnvSource>>,
     /// Working directory of a process.
     pub pwd: Loc,
+    /// Working directory of a process relative to the root of a project. If set, it takes precedence over
+    /// [`Cmd::pwd`](Cmd#structfield.pwd). See [`Cmd::pwd_rel`](Cmd::pwd_rel).
+    pub pwd_rel: Option<PathBuf>,
     /// Message displayed when running a command.
     pub msg: Option<String>,
+    /// Whether to create the working directory (recursively) before spawning a process.
+    pub create_pwd: bool,
+    /// Shell that runs the command. See [`Shell`](Shell).
+    pub shell: Shell,
+    /// Where and how the command is executed. See [`Backend`](crate::Backend).
+    pub backend: Backend,
+    /// Time budget of a one-off run. See [`Budget`](crate::Budget).
+    pub budget: Budget,
+}
+
+/// Shell that runs a [`Cmd`](Cmd). By default, it's `/bin/sh -c` on Unix and `cmd /c` on Windows.
+///
+/// Commands that rely on shell initialization (e.g. `rbenv`, `nvm` or `direnv`) fail under the bare shell.
+/// In this case, a login shell can be used:
+///
+/// ```ignore
+/// cmd! {
+///   "bundle exec rails server",
+///   env: Env::empty(),
+///   pwd: Loc::root(),
+/// }
+/// .shell(Shell::new("bash").flag("-l"))
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shell {
+    /// Shell executable.
+    pub program: String,
+    /// Flags passed to the shell before the command, e.g. `-l` to run a login shell.
+    pub flags: Vec<String>,
+    /// Flag followed by the command, e.g. `-c`.
+    pub cmd_flag: String,
+}
+
+impl Shell {
+    /// Constructs a new Unix-like shell (with `-c` command flag).
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            flags: vec![],
+            cmd_flag: "-c".to_string(),
+        }
+    }
+
+    /// PowerShell running a command via `-Command`, without loading profiles:
+    /// `powershell` (Windows PowerShell) on Windows and `pwsh` (PowerShell 7) elsewhere.
+    pub fn powershell() -> Self {
+        let program = if cfg!(windows) { "powershell" } else { "pwsh" };
+        Self {
+            program: program.to_string(),
+            flags: vec!["-NoProfile".to_string(), "-NonInteractive".to_string()],
+            cmd_flag: "-Command".to_string(),
+        }
+    }
+
+    /// Login shell of the current user (taken from the `SHELL` environment variable, `/bin/sh` otherwise).
+    #[cfg(unix)]
+    pub fn login() -> Self {
+        let program = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+        Self::new(program).flag("-l")
+    }
+
+    /// Adds a flag that is passed to the shell before the command.
+    pub fn flag(mut self, flag: impl Into<String>) -> Self {
+        self.flags.push(flag.into());
+        self
+    }
+
+    /// Quotes a value, so the shell passes it to a program as a single argument verbatim.
+    /// `cmd` (when it's the program of the shell) gets `cmd.exe` quoting, `powershell` and `pwsh` get PowerShell quoting,
+    /// any other shell gets POSIX `sh` quoting. See also [`shquote`](shquote).
+    pub fn quote(&self, value: &str) -> String {
+        if self.is_cmd_exe() {
+            self::cmd_exe_quote(value)
+        } else if self.is("powershell") || self.is("pwsh") {
+            self::powershell_quote(value)
+        } else {
+            self::sh_quote(value)
+        }
+    }
+
+    /// Whether the program of the shell is `cmd.exe`, which doesn't parse its command line with the MSVC rules,
+    /// so a command must be passed to it verbatim.
+    pub(crate) fn is_cmd_exe(&self) -> bool {
+        self.is("cmd")
+    }
+
+    fn is(&self, program: &str) -> bool {
+        Path::new(&self.program)
+            .file_stem()
+            .is_some_and(|stem| stem.eq_ignore_ascii_case(program))
+    }
+
+    /// Arguments of the shell to run the command.
+    pub(crate) fn args<'a>(&'a self, cmd: &'a str) -> Vec<&'a str> {
+        self.flags
+            .iter()
+            .map(String::as_str)
+            .chain([self.cmd_flag.as_str(), cmd])
+            .collect()
+    }
+}
+
+/// Quotes a value for the default [`Shell`](Shell) of the platform, so it can be safely interpolated into a command
+/// (`/bin/sh` quoting on Unix and `cmd.exe` quoting on Windows).
+///
+/// ```ignore
+/// cmd! {
+///   format!("cp {} {}", shquote(&src), shquote(&dest)),
+///   env: Env::empty(),
+///   pwd: Loc::root(),
+/// }
+/// ```
+pub fn shquote(value: impl AsRef<str>) -> String {
+    Shell::default().quote(value.as_ref())
+}
+
+/// Single quotes a value unless it consists of safe characters only.
+/// A single quote within a value is written as `'\''` (closing quote, escaped quote, opening quote).
+pub(crate) fn sh_quote(value: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-.,:/@%+=".contains(c);
+    if !value.is_empty() && value.chars().all(is_safe) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// Double quotes a value following the rules of the MSVC runtime, which most programs use to parse a command line,
+/// then escapes the `cmd.exe` metacharacters (including the quotes themselves) with `^`,
+/// so `cmd.exe` doesn't interpret them or expand variables.
+fn cmd_exe_quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in value.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Backslashes preceding a quote must be doubled, and the quote itself escaped
+                quoted.push_str(&"\\".repeat(backslashes + 1));
+                backslashes = 0;
+            }
+            _ => backslashes = 0,
+        }
+        quoted.push(c);
+    }
+    // Backslashes preceding the closing quote must be escaped
+    quoted.push_str(&"\\".repeat(backslashes));
+    quoted.push('"');
+
+    let mut escaped = String::with_capacity(quoted.len());
+    for c in quoted.chars() {
+        if "()%!^\"<>&|".contains(c) {
+            escaped.push('^');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Single quotes a value, so PowerShell doesn't expand variables or subexpressions within it.
+/// A single quote within a value is doubled.
+fn powershell_quote(value: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-.:/\\".contains(c);
+    if !value.is_empty() && value.chars().all(is_safe) {
+        value.to_string()
+    } else {
+        // PowerShell treats typographic single quotes as regular ones
+        let mut quoted = String::from("'");
+        for c in value.chars() {
+            if matches!(c, '\'' | '‘' | '’' | '‚' | '‛') {
+                quoted.push(c);
+            }
+            quoted.push(c);
+        }
+        quoted.push('\'');
+        quoted
+    }
+}
+
+impl Default for Shell {
+    #[cfg(unix)]
+    fn default() -> Self {
+        Self::new("/bin/sh")
+    }
+
+    #[cfg(windows)]
+    fn default() -> Self {
+        Self {
+            program: "cmd".to_string(),
+            flags: vec![],
+            cmd_flag: "/c".to_string(),
+        }
+    }
 }
 
 impl<Loc> Cmd<Loc>
 where
     Loc: Location,
 {
+    /// Constructs a new command. See also [`cmd!`](crate::cmd!) macro.
+    pub fn new(exe: impl Into<String>, env: Env, pwd: Loc, msg: Option<String>) -> Self {
+        Self {
+            exe: exe.into(),
+            env,
+            env_sources: vec![],
+            pwd,
+            pwd_rel: None,
+            msg,
+            create_pwd: false,
+            shell: Shell::default(),
+            backend: Backend::default(),
+            budget: Budget::default(),
+        }
+    }
+
+    /// Sets a working directory relative to the root of a project, i.e. [`Location::apex`](Location::apex),
+    /// so a command that runs in a subdirectory doesn't require a dedicated location.
+    /// The path is resolved every time a process is spawned, and a process isn't spawned
+    /// if the directory doesn't exist, unless [`Cmd::create_pwd`](Cmd::create_pwd) is set.
+    ///
+    /// ```ignore
+    /// cmd! {
+    ///   "npm run build",
+    ///   env: Env::empty(),
+    ///   pwd: Loc::root(),
+    /// }
+    /// .pwd_rel("client/app")
+    /// ```
+    pub fn pwd_rel(mut self, path: impl Into<PathBuf>) -> Self {
+        self.pwd_rel = Some(path.into());
+        self
+    }
+
+    /// Creates the working directory (recursively) before spawning a process if it doesn't exist,
+    /// e.g. when a command writes to a generated output directory.
+    ///
+    /// ```ignore
+    /// cmd! {
+    ///   "protoc --rust_out . schema.proto",
+    ///   env: Env::empty(),
+    ///   pwd: Loc::generated(),
+    /// }
+    /// .create_pwd(true)
+    /// ```
+    pub fn create_pwd(mut self, create: bool) -> Self {
+        self.create_pwd = create;
+        self
+    }
+
+    /// Adds a source of the environment, which is loaded every time a process is spawned, so the changes
+    /// of e.g. a dotenv file are picked up on restarts. Variables of later sources take precedence over
+    /// the earlier ones, and [`Cmd::env`](Cmd#structfield.env) takes precedence over all the sources.
+    /// See [`EnvSource`](crate::EnvSource).
+    ///
+    /// ```ignore
+    /// cmd! {
+    ///   "rails server",
+    ///   env: Env::one("PORT", port),
+    ///   pwd: Loc::root(),
+    /// }
+    /// .env_source(DotenvFile::new(Loc::root().join(".env")))
+    /// .env_source(DotenvFile::new(Loc::root().join(".env.local")).optional())
+    /// .env_source(ParentEnv)
+    /// ```
+    pub fn env_source(mut self, source: impl EnvSource + 'static) -> Self {
+        self.env_sources.push(Arc::new(source));
+        self
+    }
+
+    /// Sets a shell that runs the command. See [`Shell`](Shell).
+    pub fn shell(mut self, shell: Shell) -> Self {
+        self.shell = shell;
+        self
+    }
+
+    /// Sets where and how the command is executed. See [`Backend`](crate::Backend).
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Sets a time budget of a one-off run. See [`Budget`](crate::Budget).
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Appends an argument to the command, quoted for the shell of the command (see [`Shell::quote`](Shell::quote)).
+    /// Unlike formatting a value into the command, it's safe for values with whitespace or shell metacharacters,
+    /// such as user-supplied paths. Set a custom shell before adding arguments, since they are quoted for the current one.
+    ///
+    /// ```ignore
+    /// cmd! {
+    ///   "rm -rf",
+    ///   env: Env::empty(),
+    ///   pwd: Loc::root(),
+    /// }
+    /// .arg(path.to_string_lossy())
+    /// ```
+    pub fn arg(mut self, value: impl AsRef<str>) -> Self {
+        let arg = self.shell.quote(value.as_ref());
+        self.exe.push(' ');
+        self.exe.push_str(&arg);
+        self
+    }
+
+    /// Appends an argument to the command verbatim, without quoting, e.g. a value that is already quoted
+    /// or that relies on shell syntax, such as a redirect. An escape hatch for when [`Cmd::arg`](Cmd::arg) quoting
+    /// doesn't fit.
+    ///
+    /// ```ignore
+    /// cmd! {
+    ///   "findstr",
+    ///   env: Env::empty(),
+    ///   pwd: Loc::root(),
+    /// }
+    /// .arg(pattern)
+    /// .raw_arg("*.log > matches.txt")
+    /// ```
+    pub fn raw_arg(mut self, value: impl AsRef<str>) -> Self {
+        self.exe.push(' ');
+        self.exe.push_str(value.as_ref());
+        self
+    }
+
+    /// Appends arguments to the command. See [`Cmd::arg`](Cmd::arg).
+    pub fn args<I, S>(self, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        values.into_iter().fold(self, Cmd::arg)
+    }
+
     /// Command to run.
     pub fn exe(&self) -> &str {
         &self.exe
@@ -42,53 +392,148 @@ where
         &self.pwd
     }
 
+    /// Effective working directory of a process: [`Cmd::pwd_rel`](Cmd::pwd_rel) resolved against the root
+    /// of a project, if it's set, or [`Cmd::pwd`](Cmd#structfield.pwd) otherwise.
+    pub fn workdir(&self) -> PathBuf {
+        match &self.pwd_rel {
+            Some(rel) => Loc::apex().join(rel),
+            None => self.pwd.as_path().to_owned(),
+        }
+    }
+
     /// Message displayed when running a command.
     pub fn msg(&self) -> Option<&String> {
         self.msg.as_ref()
     }
+
+    /// Where the command runs, for printing to console: the working directory of a local command
+    /// (see [`Location::display`](Location::display)), or the host or the container of a remote one.
+    pub fn place(&self) -> String {
+        match &self.backend {
+            Backend::Local | Backend::Exec => match &self.pwd_rel {
+                Some(rel) => Path::new(&Loc::apex().display())
+                    .join(rel)
+                    .display()
+                    .to_string(),
+                None => self.pwd.display(),
+            },
+            Backend::Ssh { host, workdir, .. } => {
+                format!("{}:{}", host, workdir.as_deref().unwrap_or("~"))
+            }
+            Backend::Docker {
+                container, workdir, ..
+            } => match workdir {
+                Some(workdir) => format!("{}:{}", container, workdir),
+                None => container.to_owned(),
+            },
+        }
+    }
+
+    /// Looks up an executable on the effective `PATH` of the command, i.e. the `PATH` from
+    /// the environment of the command, if it's set, or from the environment of the current process.
+    /// Relative paths, like `./bin/server`, are resolved against the working directory of the command.
+    ///
+    /// ```ignore
+    /// if client::build().which("rescript").is_none() {
+    ///     client::install().run().await?;
+    /// }
+    /// ```
+    pub fn which(&self, name: &str) -> Option<PathBuf> {
+        if env::is_path(name) {
+            env::executable(self.workdir().join(name))
+        } else {
+            PATH::which(name, self.env.get("PATH").map(String::as_str))
+        }
+    }
 }
 
 /// Amount of time to wait before killing hanged process.
 ///
 /// When constructing a new [`Process`](crate::Process) via [`process!`](crate::process!) macro
 /// without providing a specific timeout, the [`KillTimeout::default`](KillTimeout::default) implementation is used.
-/// By default, the timeout is 10 seconds, but it can be configured by setting `PROCESS_TIMEOUT` environment variable.
+/// By default, the timeout is 10 seconds, but it can be configured by setting `PROCESS_TIMEOUT` environment variable
+/// (in seconds, or with a unit, e.g. `90s` or `2m`), or programmatically via [`set_default_kill_timeout`](set_default_kill_timeout).
+/// A [`ProcessPool`](crate::ProcessPool) resolves the default timeouts of its processes once it starts,
+/// so they can be overridden per pool via [`PoolOptions::default_timeout`](crate::PoolOptions::default_timeout).
 #[derive(Clone, Debug)]
-pub struct KillTimeout(Duration);
+pub struct KillTimeout {
+    duration: Duration,
+    default: bool,
+}
 
 impl KillTimeout {
+    const DEFAULT: Duration = Duration::from_secs(10);
+
     /// Constructs a new timeout.
     pub fn new(duration: Duration) -> Self {
-        Self(duration)
+        Self {
+            duration,
+            default: false,
+        }
     }
 
     /// Returns underlying [`Duration`](std::time::Duration).
     pub fn duration(&self) -> Duration {
-        self.0
+        self.duration
     }
+
+    /// Whether the timeout is the default one, i.e. it wasn't provided explicitly.
+    pub(crate) fn is_default(&self) -> bool {
+        self.default
+    }
+}
+
+static DEFAULT_KILL_TIMEOUT: Lazy<RwLock<Option<Duration>>> = Lazy::new(|| RwLock::new(None));
+
+/// Sets the default [`KillTimeout`](KillTimeout), e.g. from a command line flag, so it doesn't have to be passed
+/// via `PROCESS_TIMEOUT` environment variable. `None` restores the default from the environment variable.
+///
+/// ```ignore
+/// steward::set_default_kill_timeout(Some(Duration::from_secs(cli.timeout)));
+///
+/// ProcessPool::run(vec![server::watch(), client::watch()]).await?;
+/// ```
+pub fn set_default_kill_timeout(timeout: Option<Duration>) {
+    *DEFAULT_KILL_TIMEOUT
+        .write()
+        .unwrap_or_else(|err| err.into_inner()) = timeout;
 }
 
-static DEFAULT_KILL_TIMEOUT: Lazy<Duration> = Lazy::new(|| {
-    let default = Duration::from_secs(10);
+/// Returns the default kill timeout. `PROCESS_TIMEOUT` environment variable is read on every call,
+/// so changes of it at runtime are honored. Fails if the variable is not a valid duration, see [`parse_duration`](crate::parse_duration).
+pub(crate) fn default_kill_timeout() -> Result<Duration> {
+    if let Some(timeout) = *DEFAULT_KILL_TIMEOUT
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+    {
+        return Ok(timeout);
+    }
     match std::env::var("PROCESS_TIMEOUT") {
-        Err(_) => default,
-        Ok(timeout) => match timeout.parse::<u64>() {
-            Ok(x) => Duration::from_secs(x),
-            Err(_) => {
-                eprintln!(
-                    "⚠️  TIMEOUT variable is not a valid int: {}. Using default: {}",
-                    timeout,
-                    default.as_secs()
-                );
-                default
-            }
-        },
+        Err(_) => Ok(KillTimeout::DEFAULT),
+        Ok(timeout) => crate::parse_duration(&timeout),
     }
-});
+}
 
 impl Default for KillTimeout {
+    /// Returns the default timeout. If `PROCESS_TIMEOUT` variable is invalid, a warning is printed
+    /// and 10 seconds are used, while a [`ProcessPool`](crate::ProcessPool) fails to start.
     fn default() -> Self {
-        Self(*DEFAULT_KILL_TIMEOUT)
+        static WARNED: AtomicBool = AtomicBool::new(false);
+
+        let duration = self::default_kill_timeout().unwrap_or_else(|error| {
+            if !WARNED.swap(true, Ordering::Relaxed) {
+                console!(
+                    "⚠️  PROCESS_TIMEOUT variable is invalid: {} Using default: {}",
+                    error,
+                    crate::fmt::duration(Self::DEFAULT)
+                );
+            }
+            Self::DEFAULT
+        });
+        Self {
+            duration,
+            default: true,
+        }
     }
 }
 
@@ -96,32 +541,256 @@ impl Deref for KillTimeout {
     type Target = Duration;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.duration
     }
 }
 
 impl From<Duration> for KillTimeout {
     fn from(value: Duration) -> Self {
-        Self(value)
+        Self::new(value)
+    }
+}
+
+static CONCURRENCY_LIMIT: Lazy<RwLock<Option<Arc<Semaphore>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Limits the number of one-off commands ([`Cmd::run`](Cmd::run), [`Cmd::silent`](Cmd::silent)
+/// and [`Cmd::output`](Cmd::output)) that run concurrently across the whole program.
+/// Commands over the limit wait until one of the running commands finishes. `None` removes the limit.
+///
+/// ```ignore
+/// // Avoid running all the builds at once
+/// steward::set_concurrency_limit(Some(2));
+///
+/// tokio::try_join!(server::build().run(), client::build().run(), docs::build().run())?;
+/// ```
+///
+/// The limit doesn't apply to [`Cmd::spawn`](Cmd::spawn) and processes of a [`ProcessPool`](crate::ProcessPool).
+pub fn set_concurrency_limit(limit: Option<usize>) {
+    let semaphore = limit.map(|limit| Arc::new(Semaphore::new(limit.max(1))));
+    *CONCURRENCY_LIMIT
+        .write()
+        .unwrap_or_else(|err| err.into_inner()) = semaphore;
+}
+
+async fn acquire_concurrency_permit() -> Option<OwnedSemaphorePermit> {
+    let semaphore = CONCURRENCY_LIMIT
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone();
+    match semaphore {
+        None => None,
+        // The semaphore is never closed
+        Some(semaphore) => semaphore.acquire_owned().await.ok(),
     }
 }
 
 /// Options for [`Cmd::spawn`](Cmd::spawn).
+///
+/// ```ignore
+/// let opts = SpawnOptions::default()
+///     .stdin(Stdio::null())
+///     .stdout(Stdio::piped())
+///     .pwd(Loc::tmp().as_path())
+///     .process_group(ProcessGroup::New)
+///     .stop_signal(StopSignal::Terminate);
+///
+/// let server = server::run().spawn(opts)?;
+/// ```
+#[non_exhaustive]
 pub struct SpawnOptions {
+    /// Stdin stream. Inherited by default, so an interactive process can read from the terminal.
+    pub stdin: Stdio,
     /// Stdout stream.
     pub stdout: Stdio,
     /// Stderr stream.
     pub stderr: Stdio,
     /// Amount of time to wait before killing hanged process. See [`KillTimeout`](crate::KillTimeout).
     pub timeout: KillTimeout,
+    /// Working directory of a process, instead of the one of the command.
+    pub pwd: Option<PathBuf>,
+    /// Process group of a process. See [`ProcessGroup`](ProcessGroup).
+    pub process_group: ProcessGroup,
+    /// Signal a process is asked to exit with before it's killed. See [`StopSignal`](StopSignal).
+    pub stop_signal: StopSignal,
+    /// Runs a process as a different user. See [`RunAs`](RunAs).
+    #[cfg(unix)]
+    pub run_as: Option<RunAs>,
+    /// Runs a process in a sandbox. See [`Sandbox`](crate::Sandbox).
+    #[cfg(target_os = "linux")]
+    pub sandbox: Option<crate::Sandbox>,
+    /// Path to a cgroup (v2), e.g. `/sys/fs/cgroup/user.slice/steward/server`, the process is placed into before `exec`.
+    /// To let steward manage a cgroup with resource limits, see [`CgroupLimits`](crate::CgroupLimits).
+    #[cfg(all(target_os = "linux", feature = "cgroup"))]
+    pub cgroup: Option<PathBuf>,
 }
 
 impl Default for SpawnOptions {
     fn default() -> Self {
         Self {
+            stdin: Stdio::inherit(),
             stdout: Stdio::inherit(),
             stderr: Stdio::inherit(),
             timeout: KillTimeout::default(),
+            pwd: None,
+            process_group: ProcessGroup::Inherit,
+            stop_signal: StopSignal::Interrupt,
+            #[cfg(unix)]
+            run_as: None,
+            #[cfg(target_os = "linux")]
+            sandbox: None,
+            #[cfg(all(target_os = "linux", feature = "cgroup"))]
+            cgroup: None,
+        }
+    }
+}
+
+impl SpawnOptions {
+    /// Sets stdin stream.
+    pub fn stdin(mut self, stdin: impl Into<Stdio>) -> Self {
+        self.stdin = stdin.into();
+        self
+    }
+
+    /// Sets stdout stream.
+    pub fn stdout(mut self, stdout: impl Into<Stdio>) -> Self {
+        self.stdout = stdout.into();
+        self
+    }
+
+    /// Sets stderr stream.
+    pub fn stderr(mut self, stderr: impl Into<Stdio>) -> Self {
+        self.stderr = stderr.into();
+        self
+    }
+
+    /// Sets amount of time to wait before killing hanged process.
+    pub fn timeout(mut self, timeout: impl Into<KillTimeout>) -> Self {
+        self.timeout = timeout.into();
+        self
+    }
+
+    /// Overrides working directory of the command.
+    pub fn pwd(mut self, pwd: impl Into<PathBuf>) -> Self {
+        self.pwd = Some(pwd.into());
+        self
+    }
+
+    /// Sets process group of a process.
+    pub fn process_group(mut self, process_group: ProcessGroup) -> Self {
+        self.process_group = process_group;
+        self
+    }
+
+    /// Sets signal a process is asked to exit with before it's killed.
+    pub fn stop_signal(mut self, stop_signal: StopSignal) -> Self {
+        self.stop_signal = stop_signal;
+        self
+    }
+
+    /// Runs a process as a different user.
+    #[cfg(unix)]
+    pub fn run_as(mut self, run_as: RunAs) -> Self {
+        self.run_as = Some(run_as);
+        self
+    }
+
+    /// Runs a process in a sandbox.
+    #[cfg(target_os = "linux")]
+    pub fn sandbox(mut self, sandbox: crate::Sandbox) -> Self {
+        self.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Places a process into an existing cgroup (v2).
+    #[cfg(all(target_os = "linux", feature = "cgroup"))]
+    pub fn cgroup(mut self, cgroup: impl Into<PathBuf>) -> Self {
+        self.cgroup = Some(cgroup.into());
+        self
+    }
+}
+
+/// Process group of a spawned process. See [`SpawnOptions::process_group`](SpawnOptions::process_group).
+///
+/// A process in its own group doesn't receive `Ctrl+C` from the terminal, so it's asked to exit by steward instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProcessGroup {
+    /// A process joins the process group of the current process.
+    #[default]
+    Inherit,
+    /// A process starts a new process group (via `setpgid` on Unix, `CREATE_NEW_PROCESS_GROUP` on Windows).
+    New,
+    /// A process starts a new session without a controlling terminal (via `setsid`). The same as
+    /// [`ProcessGroup::New`](ProcessGroup::New) on Windows.
+    Session,
+}
+
+/// Signal a process is asked to exit with before it's killed after its [`KillTimeout`](KillTimeout).
+/// See [`SpawnOptions::stop_signal`](SpawnOptions::stop_signal).
+///
+/// There are no signals on Windows, so a process is killed right away.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StopSignal {
+    /// `SIGINT`, the same as `Ctrl+C`.
+    #[default]
+    Interrupt,
+    /// `SIGTERM`, which is expected by most servers and containers.
+    Terminate,
+    /// `SIGHUP`.
+    Hangup,
+    /// `SIGQUIT`.
+    Quit,
+}
+
+#[cfg(unix)]
+impl StopSignal {
+    pub(crate) fn to_nix(self) -> nix::sys::signal::Signal {
+        use nix::sys::signal::Signal;
+
+        match self {
+            Self::Interrupt => Signal::SIGINT,
+            Self::Terminate => Signal::SIGTERM,
+            Self::Hangup => Signal::SIGHUP,
+            Self::Quit => Signal::SIGQUIT,
+        }
+    }
+}
+
+/// A user to run a process as. Unix only.
+///
+/// ```ignore
+/// let opts = SpawnOptions::default().run_as(RunAs::Sudo("postgres".to_string()));
+///
+/// cmd! { "pg_ctl reload", env: Env::empty(), pwd: Loc::db() }.spawn(opts)?;
+/// ```
+#[cfg(unix)]
+#[derive(Clone, Debug)]
+pub enum RunAs {
+    /// Switches the user and group of a process (via `setuid` / `setgid`).
+    /// The current process must have sufficient privileges to do so (usually, it must run as root).
+    Ids {
+        /// User id.
+        uid: u32,
+        /// Group id.
+        gid: u32,
+    },
+    /// Wraps a command with `sudo -E -u <user> --`. The environment is preserved if `sudo` policy allows it.
+    Sudo(String),
+}
+
+#[cfg(unix)]
+impl RunAs {
+    /// Looks up the user and its primary group by name and returns [`RunAs::Ids`](RunAs::Ids).
+    pub fn user(name: &str) -> Result<Self> {
+        match nix::unistd::User::from_name(name) {
+            Ok(Some(user)) => Ok(Self::Ids {
+                uid: user.uid.as_raw(),
+                gid: user.gid.as_raw(),
+            }),
+            Ok(None) => Err(Error::IoError(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("User {} does not exist", name),
+            ))),
+            Err(err) => Err(Error::IoError(io::Error::other(err))),
         }
     }
 }
@@ -134,6 +803,17 @@ pub enum Output {
     Interrupted,
 }
 
+/// Output of [`Cmd::run_captured`](Cmd::run_captured).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CmdOutput {
+    /// Bytes collected from stdout.
+    pub stdout: Vec<u8>,
+    /// Bytes collected from stderr.
+    pub stderr: Vec<u8>,
+    /// Whether child process has been interrupted (e.g. user pressed Ctrl + C). The output is collected up to this point.
+    pub interrupted: bool,
+}
+
 impl Output {
     /// Returns bytes from stdout. Be aware that if child process was interrupted
     /// during the command execution (e.g. user pressed Ctrl + C), this function will terminate
@@ -157,85 +837,308 @@ impl<Loc> Cmd<Loc>
 where
     Loc: Location,
 {
-    #[cfg(unix)]
-    pub(crate) const SHELL: &'static str = "/bin/sh";
+    /// Runs one-off command with inherited [`Stdio`](std::process::Stdio). Prints headline (witn [`Cmd::msg`](Cmd::msg), if provided) to stderr.
+    pub async fn run(&self) -> Result<()> {
+        self.run_inner().await?;
+        Ok(())
+    }
 
-    #[cfg(windows)]
-    pub(crate) const SHELL: &'static str = "cmd";
+    /// Runs the command like [`Cmd::run`](Cmd::run), telling whether it was interrupted.
+    async fn run_inner(&self) -> Result<Output> {
+        let _permit = acquire_concurrency_permit().await;
 
-    #[cfg(unix)]
-    pub(crate) fn shelled(cmd: &str) -> Vec<&str> {
-        vec!["-c", cmd]
+        console!("{}", crate::headline!(self));
+
+        #[cfg(feature = "otel")]
+        let span = self.span();
+        let res = self
+            .budget
+            .watch(self.label(), async {
+                executor::execute(&self.invocation(OutputMode::Inherit)?).await
+            })
+            .await;
+        #[cfg(feature = "otel")]
+        span.exit(&crate::Exit::from_cmd_result(&res));
+        #[cfg(feature = "notify")]
+        self.notify_failure(&res);
+        res
     }
 
-    #[cfg(windows)]
-    pub(crate) fn shelled(cmd: &str) -> Vec<&str> {
-        vec!["/c", cmd]
+    /// Runs one-off command like [`Cmd::run`](Cmd::run) and retries it on failure according to the [`RetryPolicy`](crate::RetryPolicy).
+    /// Returns the error of the last attempt if the command never succeeds.
+    ///
+    /// ```ignore
+    /// db::migrate()
+    ///     .run_with_retry(&RetryPolicy::fixed(Duration::from_secs(2)).max_attempts(5))
+    ///     .await?;
+    /// ```
+    pub async fn run_with_retry(&self, policy: &RetryPolicy) -> Result<()> {
+        let mut attempts = policy.start();
+        loop {
+            match self.run().await {
+                Ok(()) => return Ok(()),
+                Err(err) => match attempts.fail() {
+                    None => return Err(err),
+                    Some(delay) => {
+                        crate::print(format!(
+                            "Attempt {} failed. Retrying in {}...",
+                            attempts.failed(),
+                            crate::fmt::duration(delay),
+                        ));
+                        time::sleep(delay).await;
+                    }
+                },
+            }
+        }
     }
 
-    /// Runs one-off command with inherited [`Stdio`](std::process::Stdio). Prints headline (witn [`Cmd::msg`](Cmd::msg), if provided) to stderr.
-    pub async fn run(&self) -> Result<()> {
-        eprintln!("{}", crate::headline!(self));
+    /// Runs one-off command like [`Cmd::run`](Cmd::run), printing its output live, and also captures the output.
+    /// When a command fails, the captured output is available in [`Error::NonZeroExitCode`](crate::Error::NonZeroExitCode).
+    ///
+    /// ```ignore
+    /// let output = server::test().run_captured().await?;
+    /// report::save(&output.stdout)?;
+    /// ```
+    pub async fn run_captured(&self) -> Result<CmdOutput> {
+        let _permit = acquire_concurrency_permit().await;
 
-        let opts = SpawnOptions {
-            stdout: Stdio::inherit(),
-            stderr: Stdio::inherit(),
-            ..Default::default()
-        };
+        console!("{}", crate::headline!(self));
 
-        self.spawn(opts)?.wait().await?;
+        #[cfg(feature = "otel")]
+        let span = self.span();
+        let res = self
+            .budget
+            .watch(self.label(), async {
+                executor::execute_captured(&self.invocation(OutputMode::Tee)?).await
+            })
+            .await;
+        #[cfg(feature = "otel")]
+        span.exit(&crate::Exit::from_cmd_result(&res));
+        #[cfg(feature = "notify")]
+        self.notify_failure(&res);
+        res
+    }
 
-        Ok(())
+    /// Span of a one-off run of the command.
+    #[cfg(feature = "otel")]
+    fn span(&self) -> crate::otel::Span {
+        crate::otel::Span::start(self.label())
+            .str("steward.exe", &self.exe)
+            .str("steward.cwd", self.workdir().display().to_string())
+    }
+
+    /// Shows a desktop notification if the command failed.
+    #[cfg(feature = "notify")]
+    fn notify_failure<T>(&self, res: &Result<T>) {
+        let exit = crate::Exit::from_cmd_result(res);
+        if !exit.is_failure() {
+            return;
+        }
+        let title = match &self.msg {
+            Some(msg) => format!("{} failed", msg),
+            None => "Command failed".to_string(),
+        };
+        crate::desktop::notify(&title, &format!("{} {}", self.exe, exit));
     }
 
     /// Runs one-off command. Doesn't print anything.
     pub async fn silent(&self) -> Result<()> {
-        let opts = SpawnOptions {
-            stdout: Stdio::null(),
-            stderr: Stdio::null(),
-            ..Default::default()
-        };
+        let _permit = acquire_concurrency_permit().await;
 
-        self.spawn(opts)?.wait().await?;
+        self.budget
+            .watch(self.label(), async {
+                executor::execute(&self.invocation(OutputMode::Silent)?).await
+            })
+            .await?;
 
         Ok(())
     }
 
     /// Runs one-off command and returns [`Output`](Output). Doesn't print anything.
     pub async fn output(&self) -> Result<Output> {
+        let _permit = acquire_concurrency_permit().await;
+
+        self.budget
+            .watch(self.label(), async {
+                executor::execute(&self.invocation(OutputMode::Capture)?).await
+            })
+            .await
+    }
+
+    /// Runs one-off command and returns lines of its stdout. Doesn't print anything.
+    ///
+    /// ```ignore
+    /// let files = cmd!("git ls-files", env: Env::empty(), pwd: Loc::root()).output_lines().await?;
+    /// ```
+    pub async fn output_lines(&self) -> Result<Vec<String>> {
+        let output = self.output_captured().await?;
+        match String::from_utf8(output.stdout) {
+            Ok(stdout) => Ok(stdout.lines().map(ToOwned::to_owned).collect()),
+            Err(err) => {
+                let reason = err.utf8_error();
+                Err(self.invalid_output(reason, err.into_bytes(), &output.stderr))
+            }
+        }
+    }
+
+    /// Runs one-off command and deserializes its stdout as JSON. Doesn't print anything.
+    /// If the output can't be deserialized, fails with [`Error::InvalidOutput`](Error::InvalidOutput),
+    /// which includes stderr of the command.
+    ///
+    /// ```ignore
+    /// #[derive(Deserialize)]
+    /// struct Metadata {
+    ///     workspace_root: PathBuf,
+    /// }
+    ///
+    /// let metadata: Metadata = server::metadata().output_json().await?;
+    /// ```
+    #[cfg(feature = "json")]
+    pub async fn output_json<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        let output = self.output_captured().await?;
+        serde_json::from_slice(&output.stdout)
+            .map_err(|err| self.invalid_output(err, output.stdout, &output.stderr))
+    }
+
+    /// Runs one-off command silently, capturing both stdout and stderr. An interrupted command is an error,
+    /// since its output is incomplete.
+    async fn output_captured(&self) -> Result<CmdOutput> {
+        let _permit = acquire_concurrency_permit().await;
+
+        let output = self
+            .budget
+            .watch(self.label(), async {
+                executor::execute_captured(&self.invocation(OutputMode::Capture)?).await
+            })
+            .await?;
+        if output.interrupted {
+            return Err(self.invalid_output("interrupted", output.stdout, &output.stderr));
+        }
+        Ok(output)
+    }
+
+    fn invalid_output(&self, reason: impl fmt::Display, stdout: Vec<u8>, stderr: &[u8]) -> Error {
+        Error::InvalidOutput {
+            cmd: self.label().to_owned(),
+            reason: reason.to_string(),
+            stdout,
+            stderr: String::from_utf8_lossy(stderr).into_owned(),
+        }
+    }
+
+    /// Spawns the command and returns a [`Stream`](futures_core::Stream) of its output lines, tagged with the stream
+    /// they come from, followed by the exit code of the process. Doesn't print anything.
+    ///
+    /// ```ignore
+    /// use tokio_stream::StreamExt;
+    ///
+    /// let mut lines = server::migrate().stream()?;
+    /// while let Some(line) = lines.next().await {
+    ///     match line {
+    ///         OutputLine::Stdout(line) | OutputLine::Stderr(line) => progress.set_message(line),
+    ///         OutputLine::Exit(code) => progress.finish_with_message(format!("Exited with {:?}", code)),
+    ///         OutputLine::Failed(err) => progress.abandon_with_message(err),
+    ///     }
+    /// }
+    /// ```
+    pub fn stream(&self) -> Result<OutputStream> {
         let opts = SpawnOptions {
             stdout: Stdio::piped(),
             stderr: Stdio::piped(),
             ..Default::default()
         };
+        Ok(OutputStream::new(self.spawn(opts)?))
+    }
+
+    /// Runs the command and reruns it whenever files that match the globs change, until interrupted with `Ctrl+C`.
+    /// Changes are detected by polling, and a rerun starts once the files stop changing for a moment,
+    /// so a burst of changes (e.g. a `git checkout`) results in a single rerun.
+    /// Failed runs are reported and don't stop watching.
+    ///
+    /// ```ignore
+    /// client::build().watch([Loc::client().as_path().join("src/**/*.res")]).await?;
+    /// ```
+    pub async fn watch<P: AsRef<Path>>(&self, globs: impl IntoIterator<Item = P>) -> Result<()> {
+        const POLL_INTERVAL: Duration = Duration::from_millis(500);
+        const DEBOUNCE: Duration = Duration::from_millis(200);
 
-        let res = self.spawn(opts)?.wait().await?;
+        let globs = globs
+            .into_iter()
+            .map(|glob| glob.as_ref().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        let mut snapshot = Snapshot::take(&globs)?;
 
-        match res {
-            ExitResult::Output(output) => Ok(Output::Data(output.stdout)),
-            ExitResult::Interrupted | ExitResult::Killed { pid: _ } => Ok(Output::Interrupted),
+        loop {
+            // Every run goes through the same path as `Cmd::run`: concurrency limit, budget, executor and hooks
+            match self.run_inner().await {
+                Ok(Output::Interrupted) => return Ok(()),
+                Ok(Output::Data(_)) => (),
+                Err(Error::NonZeroExitCode { code, output: _ }) => console!(
+                    "❗️ Command exited with non-zero code: {}",
+                    code.map(|x| x.to_string())
+                        .unwrap_or_else(|| "-".to_string())
+                ),
+                Err(err) => console!("❗️ Command failed: {}", err),
+            }
+
+            crate::print("Waiting for changes...");
+            loop {
+                tokio::select! {
+                    _ = signal::ctrl_c() => return Ok(()),
+                    _ = time::sleep(POLL_INTERVAL) => (),
+                }
+                let mut next = Snapshot::take(&globs)?;
+                if next == snapshot {
+                    continue;
+                }
+                loop {
+                    time::sleep(DEBOUNCE).await;
+                    let settled = Snapshot::take(&globs)?;
+                    if settled == next {
+                        break;
+                    }
+                    next = settled;
+                }
+                snapshot = next;
+                break;
+            }
         }
     }
 
     /// A low-level method for spawning a process and getting a handle to it.
-    pub fn spawn(&self, opts: SpawnOptions) -> io::Result<RunningProcess> {
-        let cmd = self;
+    ///
+    /// Before spawning, it validates that the working directory exists and returns
+    /// [`Error::InvalidWorkingDir`](crate::Error::InvalidWorkingDir) otherwise.
+    pub fn spawn(&self, opts: SpawnOptions) -> Result<RunningProcess> {
+        executor::spawn(&self.invocation(OutputMode::Inherit)?, opts)
+    }
 
-        let SpawnOptions {
-            stdout,
-            stderr,
-            timeout,
-        } = opts;
+    /// Spawns a process with the environment of the command layered on top of the `base` environment.
+    pub(crate) fn spawn_with_env(&self, opts: SpawnOptions, base: Env) -> Result<RunningProcess> {
+        self.invocation(OutputMode::Inherit)?.spawn(opts, base)
+    }
 
-        let process = Command::new(Cmd::<Loc>::SHELL)
-            .args(Cmd::<Loc>::shelled(&cmd.exe))
-            .envs(cmd.env.to_owned())
-            .current_dir(cmd.pwd.as_path())
-            .stdout(stdout)
-            .stderr(stderr)
-            .spawn()?;
+    /// Invocation of the command with the environment loaded from its sources.
+    fn invocation(&self, output: OutputMode) -> Result<Invocation> {
+        let mut env = Env::empty();
+        for source in &self.env_sources {
+            env = env.extend(source.load()?);
+        }
+        Ok(Invocation {
+            exe: self.exe.to_owned(),
+            env: env.extend(self.env.to_owned()),
+            pwd: self.workdir(),
+            create_pwd: self.create_pwd,
+            shell: self.shell.to_owned(),
+            backend: self.backend.to_owned(),
+            output,
+            deadline: self.budget.hard,
+        })
+    }
 
-        Ok(RunningProcess { process, timeout })
+    /// Message of the command, or the command itself.
+    fn label(&self) -> &str {
+        self.msg.as_ref().unwrap_or(&self.exe)
     }
 }
 
@@ -279,20 +1182,41 @@ where
 ///   pwd: Loc::root(),
 /// }
 /// ```
+///
+/// Extra variables can be set on top of the environment with `env+:`:
+/// ```ignore
+/// cmd! {
+///   "cargo run",
+///   env: Config::env(),
+///   env+: { "RUST_LOG" => "debug", "PORT" => port },
+///   pwd: Loc::root(),
+/// }
+/// ```
 #[macro_export]
 macro_rules! cmd {
+    {
+        exe: $exe:expr,
+        env: $env:expr,
+        env+: { $($key:expr => $val:expr),* $(,)? },
+        $($rest:tt)*
+    } => {
+        $crate::cmd! { $exe, env: ($env)$(.insert($key, $val))*, $($rest)* }
+    };
+    {
+        $exe:expr,
+        env: $env:expr,
+        env+: { $($key:expr => $val:expr),* $(,)? },
+        $($rest:tt)*
+    } => {
+        $crate::cmd! { $exe, env: ($env)$(.insert($key, $val))*, $($rest)* }
+    };
     {
         $exe:literal,
         env: $env:expr,
         pwd: $pwd:expr,
         msg: $msg:literal$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe.to_string(),
-            env: $env,
-            pwd: $pwd,
-            msg: Some($msg.to_string()),
-        }
+        $crate::Cmd::new($exe.to_string(), $env, $pwd, Some($msg.to_string()))
     };
     {
         exe: $exe:literal,
@@ -300,12 +1224,7 @@ macro_rules! cmd {
         pwd: $pwd:expr,
         msg: $msg:literal$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe.to_string(),
-            env: $env,
-            pwd: $pwd,
-            msg: Some($msg.to_string()),
-        }
+        $crate::Cmd::new($exe.to_string(), $env, $pwd, Some($msg.to_string()))
     };
     {
         $exe:literal,
@@ -313,12 +1232,7 @@ macro_rules! cmd {
         pwd: $pwd:expr,
         msg: Some($msg:expr)$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe.to_string(),
-            env: $env,
-            pwd: $pwd,
-            msg: Some($msg),
-        }
+        $crate::Cmd::new($exe.to_string(), $env, $pwd, Some($msg))
     };
     {
         exe: $exe:literal,
@@ -326,12 +1240,7 @@ macro_rules! cmd {
         pwd: $pwd:expr,
         msg: Some($msg:expr)$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe.to_string(),
-            env: $env,
-            pwd: $pwd,
-            msg: Some($msg),
-        }
+        $crate::Cmd::new($exe.to_string(), $env, $pwd, Some($msg))
     };
     {
         $exe:literal,
@@ -339,12 +1248,7 @@ macro_rules! cmd {
         pwd: $pwd:expr,
         msg: None$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe.to_string(),
-            env: $env,
-            pwd: $pwd,
-            msg: None,
-        }
+        $crate::Cmd::new($exe.to_string(), $env, $pwd, None)
     };
     {
         exe: $exe:literal,
@@ -352,12 +1256,7 @@ macro_rules! cmd {
         pwd: $pwd:expr,
         msg: None$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe.to_string(),
-            env: $env,
-            pwd: $pwd,
-            msg: None,
-        }
+        $crate::Cmd::new($exe.to_string(), $env, $pwd, None)
     };
     {
         $exe:literal,
@@ -365,12 +1264,7 @@ macro_rules! cmd {
         pwd: $pwd:expr,
         msg: $msg:expr$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe.to_string(),
-            env: $env,
-            pwd: $pwd,
-            msg: Some($msg),
-        }
+        $crate::Cmd::new($exe.to_string(), $env, $pwd, Some($msg))
     };
     {
         exe: $exe:literal,
@@ -378,12 +1272,7 @@ macro_rules! cmd {
         pwd: $pwd:expr,
         msg: $msg:expr$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe.to_string(),
-            env: $env,
-            pwd: $pwd,
-            msg: Some($msg),
-        }
+        $crate::Cmd::new($exe.to_string(), $env, $pwd, Some($msg))
     };
     {
         $exe:expr,
@@ -391,12 +1280,7 @@ macro_rules! cmd {
         pwd: $pwd:expr,
         msg: $msg:literal$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe,
-            env: $env,
-            pwd: $pwd,
-            msg: Some($msg.to_string()),
-        }
+        $crate::Cmd::new($exe, $env, $pwd, Some($msg.to_string()))
     };
     {
         exe: $exe:expr,
@@ -404,12 +1288,7 @@ macro_rules! cmd {
         pwd: $pwd:expr,
         msg: $msg:literal$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe,
-            env: $env,
-            pwd: $pwd,
-            msg: Some($msg.to_string()),
-        }
+        $crate::Cmd::new($exe, $env, $pwd, Some($msg.to_string()))
     };
     {
         $exe:expr,
@@ -417,12 +1296,7 @@ macro_rules! cmd {
         pwd: $pwd:expr,
         msg: Some($msg:expr)$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe,
-            env: $env,
-            pwd: $pwd,
-            msg: Some($msg),
-        }
+        $crate::Cmd::new($exe, $env, $pwd, Some($msg))
     };
     {
         exe: $exe:expr,
@@ -430,12 +1304,7 @@ macro_rules! cmd {
         pwd: $pwd:expr,
         msg: Some($msg:expr)$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe,
-            env: $env,
-            pwd: $pwd,
-            msg: Some($msg),
-        }
+        $crate::Cmd::new($exe, $env, $pwd, Some($msg))
     };
     {
         $exe:expr,
@@ -443,12 +1312,7 @@ macro_rules! cmd {
         pwd: $pwd:expr,
         msg: None$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe,
-            env: $env,
-            pwd: $pwd,
-            msg: None,
-        }
+        $crate::Cmd::new($exe, $env, $pwd, None)
     };
     {
         exe: $exe:expr,
@@ -456,12 +1320,7 @@ macro_rules! cmd {
         pwd: $pwd:expr,
         msg: None$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe,
-            env: $env,
-            pwd: $pwd,
-            msg: None,
-        }
+        $crate::Cmd::new($exe, $env, $pwd, None)
     };
     {
         $exe:expr,
@@ -469,12 +1328,7 @@ macro_rules! cmd {
         pwd: $pwd:expr,
         msg: $msg:expr$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe,
-            env: $env,
-            pwd: $pwd,
-            msg: Some($msg),
-        }
+        $crate::Cmd::new($exe, $env, $pwd, Some($msg))
     };
     {
         exe: $exe:expr,
@@ -482,66 +1336,146 @@ macro_rules! cmd {
         pwd: $pwd:expr,
         msg: $msg:expr$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe,
-            env: $env,
-            pwd: $pwd,
-            msg: Some($msg),
-        }
+        $crate::Cmd::new($exe, $env, $pwd, Some($msg))
     };
     {
         $exe:literal,
         env: $env:expr,
         pwd: $pwd:expr$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe.to_string(),
-            env: $env,
-            pwd: $pwd,
-            msg: None,
-        }
+        $crate::Cmd::new($exe.to_string(), $env, $pwd, None)
     };
     {
         exe: $exe:literal,
         env: $env:expr,
         pwd: $pwd:expr$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe.to_string(),
-            env: $env,
-            pwd: $pwd,
-            msg: None,
-        }
+        $crate::Cmd::new($exe.to_string(), $env, $pwd, None)
     };
     {
         $exe:expr,
         env: $env:expr,
         pwd: $pwd:expr$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe,
-            env: $env,
-            pwd: $pwd,
-            msg: None,
-        }
+        $crate::Cmd::new($exe, $env, $pwd, None)
     };
     {
         exe: $exe:expr,
         env: $env:expr,
         pwd: $pwd:expr$(,)?
     } => {
-        $crate::Cmd {
-            exe: $exe,
-            env: $env,
-            pwd: $pwd,
-            msg: None,
-        }
+        $crate::Cmd::new($exe, $env, $pwd, None)
     };
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Cmd, Env, Location};
+    use std::path::PathBuf;
+
+    use crate::{Cmd, Env, Location, Shell};
+
+    #[test]
+    fn cmd_macro_env_plus() {
+        struct Loc(PathBuf);
+        impl Location for Loc {
+            fn apex() -> Self {
+                Self(PathBuf::from("/"))
+            }
+            fn as_path(&self) -> &PathBuf {
+                &self.0
+            }
+        }
+
+        let port = 3000;
+        let cmd = cmd! {
+          "cargo run",
+          env: Env::one("RUST_LOG", "info").insert("HOST", "localhost"),
+          env+: { "RUST_LOG" => "debug", "PORT" => port },
+          pwd: Loc::apex(),
+          msg: "Running",
+        };
+        assert_eq!(cmd.env().get("RUST_LOG").map(String::as_str), Some("debug"));
+        assert_eq!(cmd.env().get("HOST").map(String::as_str), Some("localhost"));
+        assert_eq!(cmd.env().get("PORT").map(String::as_str), Some("3000"));
+        assert_eq!(cmd.msg().map(String::as_str), Some("Running"));
+
+        let cmd: Cmd<Loc> = cmd! {
+          exe: "ls",
+          env: Env::empty(),
+          env+: {},
+          pwd: Loc::apex(),
+        };
+        assert!(cmd.env().get("PORT").is_none());
+    }
+
+    #[tokio::test]
+    async fn console_writer() {
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Clone)]
+        struct Loc(PathBuf);
+        impl Location for Loc {
+            fn apex() -> Self {
+                Self(std::env::temp_dir())
+            }
+            fn as_path(&self) -> &PathBuf {
+                &self.0
+            }
+        }
+
+        let lines = Arc::new(Mutex::new(vec![]));
+        let writer = lines.clone();
+        crate::set_console_writer(Some(Arc::new(move |line| {
+            writer.lock().unwrap().push(line.to_owned())
+        })));
+        let res = Cmd::new(
+            "echo steward-out; echo steward-err >&2",
+            Env::empty(),
+            Loc::apex(),
+            None,
+        )
+        .run()
+        .await;
+        crate::set_console_writer(None);
+
+        res.unwrap();
+        // Other tests might print to the console meanwhile
+        let lines = lines.lock().unwrap();
+        assert!(lines.iter().any(|x| x == "steward-out"));
+        assert!(lines.iter().any(|x| x == "steward-err"));
+    }
+
+    #[test]
+    fn sh_quote() {
+        let sh = Shell::new("/bin/sh");
+        assert_eq!(sh.quote("src/main.rs"), "src/main.rs");
+        assert_eq!(sh.quote(""), "''");
+        assert_eq!(sh.quote("my file; rm -rf ~"), "'my file; rm -rf ~'");
+        assert_eq!(sh.quote("it's $HOME"), "'it'\\''s $HOME'");
+    }
+
+    #[test]
+    fn cmd_exe_quote() {
+        let cmd = Shell::new("cmd");
+        assert_eq!(cmd.quote("a b"), r#"^"a b^""#);
+        assert_eq!(cmd.quote(r"C:\my dir\"), r#"^"C:\my dir\\^""#);
+        assert_eq!(
+            cmd.quote(r#"say "hi" & %PATH%"#),
+            r#"^"say \^"hi\^" ^& ^%PATH^%^""#
+        );
+    }
+
+    #[test]
+    fn powershell_quote() {
+        let pwsh = Shell::powershell();
+        assert_eq!(pwsh.quote(r"C:\src\main.rs"), r"C:\src\main.rs");
+        assert_eq!(pwsh.quote("a,b"), "'a,b'");
+        assert_eq!(pwsh.quote("it's $env:PATH"), "'it''s $env:PATH'");
+        assert_eq!(
+            pwsh.quote(r#"say "hi" & `echo` ^ %PATH%"#),
+            r#"'say "hi" & `echo` ^ %PATH%'"#
+        );
+    }
 
     #[allow(dead_code)]
     fn cmd_macro_unlabeled_exe_literal_msg_literal<Loc: Location>(env: Env, loc: Loc) -> Cmd<Loc> {
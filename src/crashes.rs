@@ -0,0 +1,114 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Crash-loop detection of the processes of a [`ProcessPool`](crate::ProcessPool).
+///
+/// Crashes of every process are persisted under `<state_dir>/crashes/<tag>`, so a process that keeps crashing
+/// across pool runs (e.g. when a pool is restarted by a supervisor) is detected as well.
+///
+/// ```ignore
+/// let opts = PoolOptions {
+///     crash_loop: Some(CrashLoop {
+///         max_crashes: 3,
+///         halt: true,
+///         ..Default::default()
+///     }),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Debug)]
+pub struct CrashLoop {
+    /// Directory where the crash history is stored. Default: `.steward`.
+    pub state_dir: PathBuf,
+    /// Only the crashes within this window count. Default: 10 minutes.
+    pub window: Duration,
+    /// Number of crashes within the window that makes a crash loop. Default: 5.
+    pub max_crashes: usize,
+    /// Whether a crash-looping process must not be restarted. Otherwise, only a warning is printed. Default: `false`.
+    pub halt: bool,
+}
+
+impl Default for CrashLoop {
+    fn default() -> Self {
+        Self {
+            state_dir: PathBuf::from(".steward"),
+            window: Duration::from_secs(600),
+            max_crashes: 5,
+            halt: false,
+        }
+    }
+}
+
+impl CrashLoop {
+    /// Records a crash of a process. Returns the number of crashes within the window, including this one.
+    pub(crate) fn record(&self, tag: &str) -> io::Result<usize> {
+        let file = self.file(tag);
+        let now = SystemTime::now();
+        let mut crashes = self.history(tag)?;
+        crashes.push(now);
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let content = crashes
+            .iter()
+            .filter_map(|at| at.duration_since(UNIX_EPOCH).ok())
+            .map(|x| format!("{}\n", x.as_secs()))
+            .collect::<String>();
+        fs::write(&file, content)?;
+        Ok(crashes.len())
+    }
+
+    /// Returns `true` if the number of crashes within the window makes a crash loop.
+    pub(crate) fn is_looping(&self, crashes: usize) -> bool {
+        crashes >= self.max_crashes
+    }
+
+    /// Crashes of a process within the window, oldest first.
+    pub(crate) fn history(&self, tag: &str) -> io::Result<Vec<SystemTime>> {
+        let content = match fs::read_to_string(self.file(tag)) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err),
+        };
+        let now = SystemTime::now();
+        Ok(content
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+            .filter(|at| match now.duration_since(*at) {
+                Ok(ago) => ago <= self.window,
+                Err(_) => true,
+            })
+            .collect())
+    }
+
+    fn file(&self, tag: &str) -> PathBuf {
+        self.state_dir
+            .join("crashes")
+            .join(crate::checkpoint::sanitize(tag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crash_history() {
+        let dir = std::env::temp_dir().join(format!("steward-crashes-{}", std::process::id()));
+        let crash_loop = CrashLoop {
+            state_dir: dir.clone(),
+            max_crashes: 2,
+            ..Default::default()
+        };
+        assert_eq!(crash_loop.record("web server").unwrap(), 1);
+        assert!(!crash_loop.is_looping(1));
+        assert_eq!(crash_loop.record("web server").unwrap(), 2);
+        assert!(crash_loop.is_looping(2));
+        assert!(dir.join("crashes").join("web_server").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
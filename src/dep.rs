@@ -1,6 +1,12 @@
-use std::error::Error as StdError;
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
+use tokio::{sync::OnceCell, time};
 
 /// Dependency trait.
 ///
@@ -12,12 +18,208 @@ pub trait Dependency: Send + Sync {
     /// A method that checks if a dependency is available.
     async fn check(&self) -> Result<(), ()>;
     /// A method that resolves when a dependency becomes available.
-    async fn wait(&self) -> Result<(), Box<dyn DependencyWaitError>>;
+    ///
+    /// By default, it calls [`Dependency::wait_with_deadline`](Dependency::wait_with_deadline) without
+    /// an effective deadline, so a dependency implements only one of these methods, and must implement at least one.
+    async fn wait(&self) -> Result<(), Box<dyn DependencyWaitError>> {
+        self.wait_with_deadline(self::no_deadline()).await
+    }
+    /// Same as [`Dependency::wait`](Dependency::wait), but gives up at the deadline if it comes before
+    /// the own timeout of a dependency. A [`ProcessPool`](crate::ProcessPool) passes its
+    /// [`PoolOptions::deps_deadline`](crate::PoolOptions::deps_deadline) down this way, and composite dependencies
+    /// can share the remaining time between their parts.
+    ///
+    /// By default, [`Dependency::wait`](Dependency::wait) is cancelled at the deadline. The built-in dependencies
+    /// implement this method only and respect the deadline between attempts.
+    async fn wait_with_deadline(
+        &self,
+        deadline: Instant,
+    ) -> Result<(), Box<dyn DependencyWaitError>> {
+        match time::timeout_at(deadline.into(), self.wait()).await {
+            Ok(res) => res,
+            Err(_) => Err(Box::new(DeadlineError)),
+        }
+    }
+    /// A hint printed when waiting for a dependency fails, e.g. `is docker compose up?`.
+    fn hint(&self) -> Option<&str> {
+        None
+    }
+    /// A key identifying equivalent dependencies, e.g. the same address with the same timeout.
+    /// Dependencies of a [`ProcessPool`](crate::ProcessPool) with the same key are waited for once per run,
+    /// and the result is shared. `None` means the dependency is always waited for on its own.
+    fn key(&self) -> Option<String> {
+        None
+    }
 }
 
+/// A deadline that never comes before the own timeout of a dependency. It's far enough
+/// and still within the range of the timers of the runtime.
+fn no_deadline() -> Instant {
+    Instant::now() + Duration::from_secs(365 * 24 * 60 * 60)
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Deadline exceeded")]
+struct DeadlineError;
+
+impl DependencyWaitError for DeadlineError {}
+
 /// Error returned from the [`Dependency::wait`](Dependency::wait) method must implement this trait.
 ///
 /// ```ignore
 /// impl DependencyWaitError for MyDependencyWaitError {}
 /// ```
 pub trait DependencyWaitError: StdError + Send + Sync {}
+
+/// Waits for a dependency by polling its [`Dependency::check`](Dependency::check) method according to the [`RetryPolicy`](crate::RetryPolicy),
+/// instead of its own [`Dependency::wait`](Dependency::wait) method.
+///
+/// ```ignore
+/// let server = Retry {
+///     dep: TcpService::local("server", 3000, Duration::ZERO),
+///     policy: RetryPolicy::exponential(Duration::from_millis(100), Duration::from_secs(5))
+///         .timeout(Duration::from_secs(120)),
+/// };
+///
+/// PoolEntry::ProcessWithDep {
+///     process: client::watch(),
+///     dependency: Box::new(server),
+/// }
+/// ```
+pub struct Retry<D> {
+    /// A dependency to wait for.
+    pub dep: D,
+    /// A policy of polling the dependency.
+    pub policy: crate::RetryPolicy,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Gave up after {attempts} attempts")]
+struct RetryWaitError {
+    attempts: u32,
+}
+
+impl DependencyWaitError for RetryWaitError {}
+
+#[async_trait]
+impl<D> Dependency for Retry<D>
+where
+    D: Dependency,
+{
+    fn tag(&self) -> &str {
+        self.dep.tag()
+    }
+
+    async fn check(&self) -> Result<(), ()> {
+        self.dep.check().await
+    }
+
+    fn hint(&self) -> Option<&str> {
+        self.dep.hint()
+    }
+
+    fn key(&self) -> Option<String> {
+        self.dep
+            .key()
+            .map(|key| format!("retry:{} {:?}", key, self.policy))
+    }
+
+    async fn wait_with_deadline(
+        &self,
+        deadline: Instant,
+    ) -> Result<(), Box<dyn DependencyWaitError>> {
+        let mut attempts = self.policy.start().until(Some(deadline));
+        loop {
+            if self.dep.check().await.is_ok() {
+                return Ok(());
+            }
+            if !attempts.next().await {
+                return Err(Box::new(RetryWaitError {
+                    attempts: attempts.failed(),
+                }));
+            }
+        }
+    }
+}
+
+/// Results of the dependencies of a pool run, shared between the processes with equivalent dependencies.
+#[derive(Default)]
+pub(crate) struct SharedDeps(Mutex<HashMap<String, Arc<SharedResult>>>);
+
+type SharedResult = OnceCell<Result<(), String>>;
+
+#[derive(thiserror::Error, Debug)]
+#[error("{0}")]
+struct SharedWaitError(String);
+
+impl DependencyWaitError for SharedWaitError {}
+
+impl SharedDeps {
+    /// Waits for a dependency, or for the result of an equivalent dependency that is already being waited for.
+    pub(crate) async fn wait(
+        &self,
+        dep: &dyn Dependency,
+        deadline: Option<Instant>,
+    ) -> Result<(), Box<dyn DependencyWaitError>> {
+        let wait = || async {
+            match deadline {
+                Some(deadline) => dep.wait_with_deadline(deadline).await,
+                None => dep.wait().await,
+            }
+        };
+        let cell = match dep.key() {
+            Some(key) => self
+                .0
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .entry(key)
+                .or_default()
+                .clone(),
+            None => return wait().await,
+        };
+        cell.get_or_init(|| async { wait().await.map_err(|err| err.to_string()) })
+            .await
+            .clone()
+            .map_err(|err| Box::new(SharedWaitError(err)) as Box<dyn DependencyWaitError>)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    struct Counted<'a>(&'a AtomicUsize);
+
+    #[async_trait]
+    impl Dependency for Counted<'_> {
+        fn tag(&self) -> &str {
+            "counted"
+        }
+
+        async fn check(&self) -> Result<(), ()> {
+            Ok(())
+        }
+
+        async fn wait(&self) -> Result<(), Box<dyn DependencyWaitError>> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            tokio::task::yield_now().await;
+            Ok(())
+        }
+
+        fn key(&self) -> Option<String> {
+            Some("counted".to_owned())
+        }
+    }
+
+    #[tokio::test]
+    async fn shared_deps() {
+        let waits = AtomicUsize::new(0);
+        let deps = SharedDeps::default();
+        let (a, b) = (Counted(&waits), Counted(&waits));
+        let (a, b) = tokio::join!(deps.wait(&a, None), deps.wait(&b, None));
+        assert!(a.is_ok() && b.is_ok());
+        assert_eq!(waits.load(Ordering::Relaxed), 1);
+    }
+}
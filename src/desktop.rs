@@ -0,0 +1,66 @@
+use std::{
+    process::{Command, Stdio},
+    thread,
+};
+
+/// Shows a native desktop notification in the background.
+///
+/// Uses `notify-send` on Linux, `osascript` on macOS and PowerShell on Windows.
+/// Failures are ignored, since a notification must not break a run.
+pub(crate) fn notify(title: &str, body: &str) {
+    let mut command = self::command(title, body);
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    // Waits in a thread, so the notifier doesn't turn into a zombie
+    thread::spawn(move || command.status().ok());
+}
+
+#[cfg(target_os = "macos")]
+fn command(title: &str, body: &str) -> Command {
+    let quote = |value: &str| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut command = Command::new("osascript");
+    command.args([
+        "-e",
+        &format!(
+            "display notification {} with title {}",
+            quote(body),
+            quote(title)
+        ),
+    ]);
+    command
+}
+
+#[cfg(windows)]
+fn command(title: &str, body: &str) -> Command {
+    let quote = |value: &str| format!("'{}'", value.replace('\'', "''"));
+    let script = format!(
+        "Add-Type -AssemblyName System.Windows.Forms; \
+         $icon = New-Object System.Windows.Forms.NotifyIcon; \
+         $icon.Icon = [System.Drawing.SystemIcons]::Error; \
+         $icon.Visible = $true; \
+         $icon.ShowBalloonTip(10000, {}, {}, 'Error'); \
+         Start-Sleep -Seconds 10; \
+         $icon.Dispose()",
+        quote(title),
+        quote(body)
+    );
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn command(title: &str, body: &str) -> Command {
+    let mut command = Command::new("notify-send");
+    command.args([
+        "--app-name",
+        "steward",
+        "--urgency",
+        "critical",
+        title,
+        body,
+    ]);
+    command
+}
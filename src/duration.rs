@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use crate::{Error, Result};
+
+/// Parses a human-friendly duration, e.g. `90s`, `2m`, `1h 30m` or `1.5s`. A bare number is a number of seconds.
+/// Fails with [`Error::InvalidDuration`](Error::InvalidDuration) on anything else.
+///
+/// Supported units: `ms`, `s`, `m`, `h` and `d`. Useful for durations that come from a config or a command line:
+///
+/// ```ignore
+/// let timeout = steward::parse_duration(&std::env::var("DEPS_DEADLINE")?)?;
+/// ```
+pub fn parse_duration(value: &str) -> Result<Duration> {
+    let invalid = |reason: String| Error::InvalidDuration {
+        value: value.to_owned(),
+        reason,
+    };
+
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return Err(invalid("it's empty".to_string()));
+    }
+    if let Ok(secs) = rest.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut total = Duration::ZERO;
+    while !rest.is_empty() {
+        let (number, tail) = rest.split_at(
+            rest.find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len()),
+        );
+        let tail = tail.trim_start();
+        let (unit, tail) = tail.split_at(
+            tail.find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(tail.len()),
+        );
+        let number = number
+            .parse::<f64>()
+            .map_err(|_| invalid(format!("expected a number at `{}`", rest)))?;
+        let secs = match unit {
+            "ms" => 0.001,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            "d" => 86400.0,
+            "" => return Err(invalid("expected a unit, e.g. `s` or `m`".to_string())),
+            unit => return Err(invalid(format!("unknown unit `{}`", unit))),
+        };
+        total += Duration::try_from_secs_f64(number * secs)
+            .map_err(|_| invalid("it's too long".to_string()))?;
+        rest = tail.trim_start();
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::parse_duration;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+        assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(
+            parse_duration(" 1h 02m 13s ").unwrap(),
+            Duration::from_secs(3733)
+        );
+        assert_eq!(parse_duration("1m30s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("3 h").unwrap(), Duration::from_secs(10800));
+
+        assert_eq!(
+            parse_duration("").unwrap_err().to_string(),
+            r#""" is not a valid duration: it's empty."#
+        );
+        assert_eq!(
+            parse_duration("5 minutes").unwrap_err().to_string(),
+            r#""5 minutes" is not a valid duration: unknown unit `minutes`."#
+        );
+        assert_eq!(
+            parse_duration("1.5").unwrap_err().to_string(),
+            r#""1.5" is not a valid duration: expected a unit, e.g. `s` or `m`."#
+        );
+        assert_eq!(
+            parse_duration("-5s").unwrap_err().to_string(),
+            r#""-5s" is not a valid duration: expected a number at `-5s`."#
+        );
+    }
+}
@@ -1,7 +1,14 @@
-use std::collections::{hash_map, HashMap};
+use std::{
+    borrow::Cow,
+    collections::{hash_map, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, Result};
 
 /// Environment data for a [`Cmd`](crate::Cmd).
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Env(HashMap<String, String>);
 
 impl Env {
@@ -69,6 +76,151 @@ impl Env {
     pub fn get(&self, k: &str) -> Option<&String> {
         self.0.get(k)
     }
+
+    /// Resolves references to other variables in the values of the container.
+    ///
+    /// A reference looks like `${KEY}` and is resolved to the value of `KEY` in the container,
+    /// which is resolved recursively, or in the environment of the current process, if the container doesn't have it.
+    /// To keep `${` in a value as is, escape it as `$${`.
+    ///
+    /// ```ignore
+    /// let env = Env::from_vec(vec![
+    ///     ("DB_HOST", "localhost"),
+    ///     ("DB_PORT", "5432"),
+    ///     ("DATABASE_URL", "postgres://${DB_HOST}:${DB_PORT}/app"),
+    /// ])
+    /// .interpolate()?;
+    /// ```
+    ///
+    /// Returns an error if variables reference each other in a cycle or a referenced variable is not defined.
+    pub fn interpolate(self) -> Result<Self> {
+        let mut resolved = HashMap::with_capacity(self.0.len());
+        for key in self.0.keys() {
+            self.resolve(key, &mut resolved, &mut vec![])?;
+        }
+        Ok(Self(resolved))
+    }
+
+    fn resolve<'a>(
+        &'a self,
+        key: &'a str,
+        resolved: &mut HashMap<String, String>,
+        stack: &mut Vec<&'a str>,
+    ) -> Result<String> {
+        if let Some(val) = resolved.get(key) {
+            return Ok(val.to_owned());
+        }
+        if let Some(idx) = stack.iter().position(|x| *x == key) {
+            let mut keys = stack[idx..]
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>();
+            keys.push(key.to_owned());
+            return Err(Error::EnvCycle { keys });
+        }
+        let raw = match self.0.get(key) {
+            Some(raw) => raw,
+            None => {
+                return std::env::var(key).map_err(|_| Error::EnvUndefined {
+                    key: stack.last().map(|x| x.to_string()).unwrap_or_default(),
+                    var: key.to_owned(),
+                })
+            }
+        };
+
+        stack.push(key);
+        let mut val = String::with_capacity(raw.len());
+        let mut rest = raw.as_str();
+        while let Some(idx) = rest.find('$') {
+            val.push_str(&rest[..idx]);
+            rest = &rest[idx..];
+            if let Some(tail) = rest.strip_prefix("$${") {
+                val.push_str("${");
+                rest = tail;
+            } else if let Some((var, tail)) = rest
+                .strip_prefix("${")
+                .and_then(|tail| tail.split_once('}'))
+            {
+                val.push_str(&self.resolve(var, resolved, stack)?);
+                rest = tail;
+            } else {
+                val.push('$');
+                rest = &rest[1..];
+            }
+        }
+        val.push_str(rest);
+        stack.pop();
+
+        resolved.insert(key.to_owned(), val.to_owned());
+        Ok(val)
+    }
+
+    /// Writes the container to a dotenv file, so it can be handed to tools that only read `.env` files.
+    /// Keys are sorted and values are quoted when needed.
+    ///
+    /// ```ignore
+    /// env.write_dotenv(Loc::client().as_path().join(".env"))?;
+    /// ```
+    pub fn write_dotenv(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write_dotenv_redacted(path, &[])
+    }
+
+    /// Same as [`Env::write_dotenv`](Env::write_dotenv), but the values of the `redacted` keys are not written.
+    /// Such entries are written as comments instead, so the file still lists every key it's expected to have.
+    ///
+    /// ```ignore
+    /// env.write_dotenv_redacted(Loc::client().as_path().join(".env.example"), &["API_TOKEN"])?;
+    /// ```
+    pub fn write_dotenv_redacted(&self, path: impl AsRef<Path>, redacted: &[&str]) -> Result<()> {
+        fs::write(path, self.to_dotenv(redacted))?;
+        Ok(())
+    }
+
+    /// Entries sorted by key, so the output built from them is stable.
+    pub(crate) fn sorted(&self) -> Vec<(&str, &str)> {
+        let mut entries = self
+            .0
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect::<Vec<_>>();
+        entries.sort();
+        entries
+    }
+
+    fn to_dotenv(&self, redacted: &[&str]) -> String {
+        self.sorted()
+            .into_iter()
+            .fold(String::new(), |mut acc, (key, val)| {
+                if redacted.contains(&key) {
+                    acc.push_str(&format!("# {}=<redacted>\n", key));
+                } else {
+                    acc.push_str(&format!("{}={}\n", key, dotenv_quote(val)));
+                }
+                acc
+            })
+    }
+}
+
+/// Quotes a dotenv value, unless it consists of characters that are safe to leave unquoted.
+fn dotenv_quote(val: &str) -> Cow<'_, str> {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_-.,:/@%+".contains(c);
+    if val.chars().all(safe) {
+        return Cow::Borrowed(val);
+    }
+    let mut quoted = String::with_capacity(val.len() + 2);
+    quoted.push('"');
+    for c in val.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '$' => quoted.push_str("\\$"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    Cow::Owned(quoted)
 }
 
 impl IntoIterator for Env {
@@ -80,6 +232,198 @@ impl IntoIterator for Env {
     }
 }
 
+/// A source of environment variables for a [`Cmd`](crate::Cmd), loaded every time the command runs.
+/// See [`Cmd::env_source`](crate::Cmd::env_source).
+///
+/// Built-in sources are [`DotenvFile`](DotenvFile), [`ParentEnv`](ParentEnv), `JsonFile` (behind the `json` feature) and
+/// [`Env`](Env) itself. Custom sources, e.g. a secret store, implement this trait:
+///
+/// ```ignore
+/// struct Vault(&'static str);
+///
+/// impl EnvSource for Vault {
+///     fn load(&self) -> Result<Env> {
+///         let output = std::process::Command::new("vault").args(["kv", "get", "-format=json", self.0]).output()?;
+///         Ok(parse_secrets(&output.stdout))
+///     }
+/// }
+/// ```
+pub trait EnvSource: Send + Sync {
+    /// Loads the variables.
+    fn load(&self) -> Result<Env>;
+}
+
+impl EnvSource for Env {
+    fn load(&self) -> Result<Env> {
+        Ok(self.clone())
+    }
+}
+
+/// Environment of the current process as an [`EnvSource`](EnvSource), so it can take precedence over other sources.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParentEnv;
+
+impl EnvSource for ParentEnv {
+    fn load(&self) -> Result<Env> {
+        Ok(Env::parent())
+    }
+}
+
+/// A dotenv file as an [`EnvSource`](EnvSource).
+///
+/// Supports `KEY=value` lines with optional `export` prefixes, `#` comments, single quoted values (taken literally)
+/// and double quoted values (with `\n`, `\r`, `\"`, `\\` and `\$` escapes), i.e. the format written by
+/// [`Env::write_dotenv`](Env::write_dotenv). References to other variables are not resolved,
+/// see [`Env::interpolate`](Env::interpolate).
+#[derive(Clone, Debug)]
+pub struct DotenvFile {
+    /// Path of the file.
+    pub path: PathBuf,
+    /// Whether a missing file is treated as empty rather than as an error, e.g. for `.env.local` overrides.
+    pub optional: bool,
+}
+
+impl DotenvFile {
+    /// Constructs a source of a dotenv file that must exist.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            optional: false,
+        }
+    }
+
+    /// Treats a missing file as empty.
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+}
+
+impl EnvSource for DotenvFile {
+    fn load(&self) -> Result<Env> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => self::parse_dotenv(&content).map_err(|(line, reason)| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}:{}: {}", self.path.display(), line, reason),
+                )
+                .into()
+            }),
+            Err(error) if self.optional && error.kind() == io::ErrorKind::NotFound => {
+                Ok(Env::empty())
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+/// Parses the content of a dotenv file. Returns the number of an invalid line and why it's invalid on failure.
+fn parse_dotenv(content: &str) -> std::result::Result<Env, (usize, &'static str)> {
+    let mut data = HashMap::new();
+    for (idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, raw) = line
+            .split_once('=')
+            .ok_or((idx + 1, "expected KEY=value"))?;
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err((idx + 1, "invalid key"));
+        }
+        let raw = raw.trim_start();
+        let val = if let Some(rest) = raw.strip_prefix('\'') {
+            let (val, _) = rest
+                .split_once('\'')
+                .ok_or((idx + 1, "unterminated single quote"))?;
+            val.to_owned()
+        } else if let Some(rest) = raw.strip_prefix('"') {
+            let mut val = String::with_capacity(rest.len());
+            let mut chars = rest.chars();
+            loop {
+                match chars.next() {
+                    None => return Err((idx + 1, "unterminated double quote")),
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => val.push('\n'),
+                        Some('r') => val.push('\r'),
+                        Some(c @ ('"' | '\\' | '$')) => val.push(c),
+                        Some(c) => {
+                            val.push('\\');
+                            val.push(c);
+                        }
+                        None => return Err((idx + 1, "unterminated double quote")),
+                    },
+                    Some(c) => val.push(c),
+                }
+            }
+            val
+        } else {
+            // An unquoted value ends at an inline comment
+            let val = match raw.find(" #") {
+                Some(end) => &raw[..end],
+                None => raw,
+            };
+            val.trim_end().to_owned()
+        };
+        data.insert(key.to_owned(), val);
+    }
+    Ok(Env(data))
+}
+
+/// A JSON file with an object of variables as an [`EnvSource`](EnvSource), e.g. `{"PORT": 3000, "DEBUG": true}`.
+/// Numbers and booleans are converted to strings, `null` values are skipped, nested values are an error.
+#[cfg(feature = "json")]
+#[derive(Clone, Debug)]
+pub struct JsonFile {
+    /// Path of the file.
+    pub path: PathBuf,
+}
+
+#[cfg(feature = "json")]
+impl JsonFile {
+    /// Constructs a source of a JSON file.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[cfg(feature = "json")]
+impl EnvSource for JsonFile {
+    fn load(&self) -> Result<Env> {
+        use serde_json::Value;
+
+        let invalid = |reason: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", self.path.display(), reason),
+            )
+        };
+        let content = fs::read(&self.path)?;
+        let object = match serde_json::from_slice(&content) {
+            Ok(Value::Object(object)) => object,
+            Ok(_) => return Err(invalid("expected an object".to_string()).into()),
+            Err(error) => return Err(invalid(error.to_string()).into()),
+        };
+        let mut data = HashMap::with_capacity(object.len());
+        for (key, val) in object {
+            let val = match val {
+                Value::Null => continue,
+                Value::String(val) => val,
+                Value::Bool(val) => val.to_string(),
+                Value::Number(val) => val.to_string(),
+                Value::Array(_) | Value::Object(_) => {
+                    return Err(invalid(format!("value of {} is not a scalar", key)).into())
+                }
+            };
+            data.insert(key, val);
+        }
+        Ok(Env(data))
+    }
+}
+
 /// Convenience struct for dealing with the `PATH` environment variable.
 pub struct PATH;
 
@@ -102,4 +446,124 @@ impl PATH {
             None => x.to_string(),
         }
     }
+
+    /// Looks up an executable in the provided `PATH` value and returns its full path.
+    /// If `path` is `None`, the `PATH` of the current process is used.
+    pub fn which(name: &str, path: Option<&str>) -> Option<PathBuf> {
+        let path = match path {
+            Some(path) => path.to_owned(),
+            None => PATH::get()?,
+        };
+        std::env::split_paths(&path)
+            .map(|dir| dir.join(name))
+            .find_map(executable)
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn executable(path: PathBuf) -> Option<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    match path.metadata() {
+        Ok(meta) if meta.is_file() && meta.permissions().mode() & 0o111 != 0 => Some(path),
+        Ok(_) | Err(_) => None,
+    }
+}
+
+#[cfg(windows)]
+pub(crate) fn executable(path: PathBuf) -> Option<PathBuf> {
+    if path.extension().is_some() && path.is_file() {
+        return Some(path);
+    }
+    let exts = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+    exts.split(';')
+        .filter(|ext| !ext.is_empty())
+        .map(|ext| {
+            let mut file = path.clone().into_os_string();
+            file.push(ext);
+            PathBuf::from(file)
+        })
+        .find(|path| path.is_file())
+}
+
+pub(crate) fn is_path(name: &str) -> bool {
+    Path::new(name).components().count() > 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_dotenv, Env};
+    use crate::Error;
+
+    #[test]
+    fn dotenv_parsing() {
+        let env = parse_dotenv(
+            "# comment\n\nexport PORT=3000\nURL = http://localhost # local\nMSG=\"say \\\"hi\\\"\\n$\"\nRAW='a \\n #b'\n",
+        )
+        .unwrap();
+        assert_eq!(env.get("PORT").unwrap(), "3000");
+        assert_eq!(env.get("URL").unwrap(), "http://localhost");
+        assert_eq!(env.get("MSG").unwrap(), "say \"hi\"\n$");
+        assert_eq!(env.get("RAW").unwrap(), "a \\n #b");
+        assert_eq!(
+            parse_dotenv("A=1\nB").err(),
+            Some((2, "expected KEY=value"))
+        );
+
+        let written = Env::from_vec(vec![("A", "x \"y\" $z\n")]).to_dotenv(&[]);
+        assert_eq!(
+            parse_dotenv(&written).unwrap().get("A").unwrap(),
+            "x \"y\" $z\n"
+        );
+    }
+
+    #[test]
+    fn interpolate_references() {
+        let env = Env::from_vec(vec![
+            ("DB_HOST", "localhost"),
+            ("DB_PORT", "5432"),
+            ("DB_ADDR", "${DB_HOST}:${DB_PORT}"),
+            ("DATABASE_URL", "postgres://${DB_ADDR}/app"),
+            ("LITERAL", "$${DB_HOST} costs $5"),
+        ])
+        .interpolate()
+        .unwrap();
+        assert_eq!(
+            env.get("DATABASE_URL").unwrap(),
+            "postgres://localhost:5432/app"
+        );
+        assert_eq!(env.get("LITERAL").unwrap(), "${DB_HOST} costs $5");
+    }
+
+    #[test]
+    fn interpolate_detects_cycles() {
+        let env = Env::from_vec(vec![("A", "${B}"), ("B", "x${C}"), ("C", "${B}")]);
+        match env.interpolate() {
+            Err(Error::EnvCycle { keys }) => {
+                assert!(keys == ["B", "C", "B"] || keys == ["C", "B", "C"])
+            }
+            _ => panic!("Expected a cycle"),
+        }
+    }
+
+    #[test]
+    fn dotenv_sorted_and_quoted() {
+        let env = Env::from_vec(vec![
+            ("PORT", "3000"),
+            ("GREETING", "Hello, \"world\"!"),
+            ("MULTILINE", "a\nb"),
+            ("PRICE", "$5"),
+            ("EMPTY", ""),
+        ]);
+        assert_eq!(
+            env.to_dotenv(&[]),
+            "EMPTY=\nGREETING=\"Hello, \\\"world\\\"!\"\nMULTILINE=\"a\\nb\"\nPORT=3000\nPRICE=\"\\$5\"\n"
+        );
+    }
+
+    #[test]
+    fn dotenv_redacted() {
+        let env = Env::from_vec(vec![("TOKEN", "secret"), ("PORT", "3000")]);
+        assert_eq!(env.to_dotenv(&["TOKEN"]), "PORT=3000\n# TOKEN=<redacted>\n");
+    }
 }
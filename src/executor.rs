@@ -0,0 +1,697 @@
+use std::{
+    future::Future,
+    path::PathBuf,
+    process::{self, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use tokio::{
+    io::{self, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    process::Command,
+    task,
+    time::Instant,
+};
+
+use crate::{
+    cmd::Output, Backend, CmdOutput, Env, Error, ExitResult, ProcessGroup, Result, RunningProcess,
+    Shell, SpawnOptions,
+};
+
+tokio::task_local! {
+    static EXECUTOR: Arc<dyn Executor>;
+}
+
+/// Executes one-off commands, i.e. [`Cmd::run`](crate::Cmd::run), [`Cmd::silent`](crate::Cmd::silent),
+/// [`Cmd::output`](crate::Cmd::output) and [`Cmd::run_captured`](crate::Cmd::run_captured), and spawns commands
+/// via [`Cmd::spawn`](crate::Cmd::spawn) (and so [`Cmd::stream`](crate::Cmd::stream)).
+///
+/// By default, commands are executed by [`SystemExecutor`](SystemExecutor), which spawns processes.
+/// Another executor can be used within a scope via [`with_executor`](with_executor), e.g. to test
+/// orchestration code without spawning processes. See [`MockExecutor`](MockExecutor).
+///
+/// Processes of a [`ProcessPool`](crate::ProcessPool) always spawn real processes.
+#[async_trait]
+pub trait Executor: Send + Sync {
+    /// Executes a command and returns its stdout, if it's captured (see [`OutputMode`](OutputMode)).
+    /// A command that exits with a non-zero code results in [`Error::NonZeroExitCode`](Error::NonZeroExitCode).
+    async fn execute(&self, invocation: &Invocation) -> Result<Output>;
+
+    /// Executes a command and returns both its stdout and stderr ([`Cmd::run_captured`](crate::Cmd::run_captured)).
+    /// The output is printed as well in the [`OutputMode::Tee`](OutputMode::Tee) mode only. By default, it calls [`Executor::execute`](Executor::execute) and returns empty stderr.
+    async fn execute_captured(&self, invocation: &Invocation) -> Result<CmdOutput> {
+        match self.execute(invocation).await? {
+            Output::Data(stdout) => Ok(CmdOutput {
+                stdout,
+                stderr: vec![],
+                interrupted: false,
+            }),
+            Output::Interrupted => Ok(CmdOutput {
+                stdout: vec![],
+                stderr: vec![],
+                interrupted: true,
+            }),
+        }
+    }
+
+    /// Spawns a command and returns a handle to its process ([`Cmd::spawn`](crate::Cmd::spawn)).
+    /// By default, it spawns a real process.
+    fn spawn(&self, invocation: &Invocation, opts: SpawnOptions) -> Result<RunningProcess> {
+        invocation.spawn(opts, Env::empty())
+    }
+}
+
+/// A one-off command passed to an [`Executor`](Executor).
+#[derive(Clone)]
+pub struct Invocation {
+    /// Command to run.
+    pub exe: String,
+    /// Environment of a process.
+    pub env: Env,
+    /// Working directory of a process.
+    pub pwd: PathBuf,
+    /// Whether to create the working directory before spawning a process.
+    pub create_pwd: bool,
+    /// Shell that runs the command.
+    pub shell: Shell,
+    /// Where and how the command is executed.
+    pub backend: Backend,
+    /// What happens to the output of a process.
+    pub output: OutputMode,
+    /// Duration after which the process is stopped, i.e. the hard limit of its [`Budget`](crate::Budget).
+    pub deadline: Option<Duration>,
+}
+
+/// What happens to the output of a one-off command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Output is inherited from the current process ([`Cmd::run`](crate::Cmd::run)), or passed to the console writer line by line,
+    /// if it's set. See [`set_console_writer`](crate::set_console_writer).
+    Inherit,
+    /// Output is discarded ([`Cmd::silent`](crate::Cmd::silent)).
+    Silent,
+    /// Output is captured ([`Cmd::output`](crate::Cmd::output), [`Cmd::output_lines`](crate::Cmd::output_lines)).
+    Capture,
+    /// Output is printed and captured at the same time ([`Cmd::run_captured`](crate::Cmd::run_captured)).
+    Tee,
+}
+
+impl Invocation {
+    /// When the process must be stopped.
+    fn deadline(&self) -> Option<Instant> {
+        self.deadline.map(|x| Instant::now() + x)
+    }
+
+    /// Spawns a process with the environment of the invocation layered on top of the `base` environment.
+    pub(crate) fn spawn(&self, opts: SpawnOptions, base: Env) -> Result<RunningProcess> {
+        let SpawnOptions {
+            stdin,
+            stdout,
+            stderr,
+            timeout,
+            pwd,
+            process_group,
+            stop_signal,
+            #[cfg(unix)]
+            run_as,
+            #[cfg(target_os = "linux")]
+            sandbox,
+            #[cfg(all(target_os = "linux", feature = "cgroup"))]
+            cgroup,
+        } = opts;
+
+        let pwd = pwd.as_ref().unwrap_or(&self.pwd);
+
+        // Working directory is not used by remote backends
+        if self.backend.is_local() {
+            if self.create_pwd {
+                std::fs::create_dir_all(pwd)?;
+            }
+
+            if !pwd.is_dir() {
+                return Err(Error::InvalidWorkingDir {
+                    path: pwd.to_owned(),
+                });
+            }
+        }
+
+        let env = base.extend(self.env.to_owned());
+        let argv = self.backend.argv(&self.exe, &self.shell, &env)?;
+
+        #[cfg(unix)]
+        let mut command = match &run_as {
+            None | Some(crate::RunAs::Ids { .. }) => {
+                let mut command = Command::new(&argv[0]);
+                command.args(&argv[1..]);
+                command
+            }
+            Some(crate::RunAs::Sudo(user)) => {
+                let mut command = Command::new("sudo");
+                command.args(["-E", "-u", user, "--"]).args(&argv);
+                command
+            }
+        };
+
+        #[cfg(windows)]
+        let mut command = {
+            let mut command = Command::new(&argv[0]);
+            match argv[1..].split_last() {
+                // `cmd.exe` doesn't follow the MSVC rules, so quoting of the standard library mangles quotes,
+                // carets and ampersands within the command. Instead, the command is passed verbatim in outer quotes,
+                // which `cmd.exe /c` strips.
+                Some((exe, args)) if self.backend.is_local() && self.shell.is_cmd_exe() => {
+                    command.args(args).raw_arg(format!("\"{}\"", exe));
+                }
+                Some(_) | None => {
+                    command.args(&argv[1..]);
+                }
+            }
+            command
+        };
+
+        #[cfg(unix)]
+        if let Some(crate::RunAs::Ids { uid, gid }) = run_as {
+            command.uid(uid).gid(gid);
+        }
+
+        #[cfg(unix)]
+        if process_group != ProcessGroup::Inherit {
+            // SAFETY: The closure only performs system calls.
+            unsafe {
+                command.pre_exec(move || {
+                    let res = match process_group {
+                        ProcessGroup::Inherit => Ok(()),
+                        ProcessGroup::New => nix::unistd::setpgid(
+                            nix::unistd::Pid::from_raw(0),
+                            nix::unistd::Pid::from_raw(0),
+                        ),
+                        ProcessGroup::Session => nix::unistd::setsid().map(drop),
+                    };
+                    res.map_err(|err| match err.as_errno() {
+                        Some(errno) => std::io::Error::from_raw_os_error(errno as i32),
+                        None => std::io::Error::from(std::io::ErrorKind::Other),
+                    })
+                });
+            }
+        }
+
+        #[cfg(windows)]
+        if process_group != ProcessGroup::Inherit {
+            command.creation_flags(winapi::um::winbase::CREATE_NEW_PROCESS_GROUP);
+        }
+
+        // Joins the cgroup before entering the sandbox, since the latter might change the user namespace
+        #[cfg(all(target_os = "linux", feature = "cgroup"))]
+        if let Some(cgroup) = cgroup {
+            crate::cgroup::join(&mut command, &cgroup);
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(sandbox) = sandbox {
+            sandbox.apply(&mut command);
+        }
+
+        if self.backend.is_local() {
+            command.current_dir(pwd);
+        }
+
+        command.envs(env).stdin(stdin).stdout(stdout).stderr(stderr);
+
+        #[cfg(target_os = "linux")]
+        let process = crate::reaper::spawn(|| command.spawn())?;
+        #[cfg(not(target_os = "linux"))]
+        let process = command.spawn()?;
+
+        Ok(RunningProcess {
+            process,
+            timeout,
+            stop_signal,
+            detached: process_group != ProcessGroup::Inherit,
+            paused: Default::default(),
+        })
+    }
+}
+
+/// The default [`Executor`](Executor), which spawns processes.
+pub struct SystemExecutor;
+
+#[async_trait]
+impl Executor for SystemExecutor {
+    async fn execute(&self, invocation: &Invocation) -> Result<Output> {
+        let (stdout, stderr) = match invocation.output {
+            OutputMode::Inherit if !crate::fmt::has_console_writer() => {
+                (Stdio::inherit(), Stdio::inherit())
+            }
+            OutputMode::Silent => (Stdio::null(), Stdio::null()),
+            OutputMode::Capture => (Stdio::piped(), Stdio::piped()),
+            // Inherited output would bypass the console writer, so it's passed to the writer line by line instead
+            OutputMode::Inherit | OutputMode::Tee => {
+                return match self.execute_captured(invocation).await? {
+                    CmdOutput {
+                        interrupted: true, ..
+                    } => Ok(Output::Interrupted),
+                    CmdOutput { stdout, .. } => Ok(Output::Data(stdout)),
+                }
+            }
+        };
+        let opts = SpawnOptions {
+            stdout,
+            stderr,
+            ..Default::default()
+        };
+
+        let deadline = invocation.deadline();
+        match invocation
+            .spawn(opts, Env::empty())?
+            .wait_until(crate::process::deadline(deadline))
+            .await?
+        {
+            ExitResult::Output(output) => Ok(Output::Data(output.stdout)),
+            ExitResult::Interrupted | ExitResult::Killed { pid: _ } => Ok(Output::Interrupted),
+        }
+    }
+
+    async fn execute_captured(&self, invocation: &Invocation) -> Result<CmdOutput> {
+        let opts = SpawnOptions {
+            stdout: Stdio::piped(),
+            stderr: Stdio::piped(),
+            ..Default::default()
+        };
+
+        let deadline = invocation.deadline();
+        let mut running = invocation.spawn(opts, Env::empty())?;
+        // Output is printed as it's collected only in the `Tee` mode (or `Inherit` one, redirected to the console writer)
+        let print = matches!(invocation.output, OutputMode::Tee | OutputMode::Inherit);
+        let writer = print && crate::fmt::has_console_writer();
+        let stdout = running.stdout().map(|x| match (print, writer) {
+            (true, true) => task::spawn(self::tee_lines(x)),
+            (true, false) => task::spawn(self::tee(x, io::stdout())),
+            (false, _) => task::spawn(self::tee(x, io::sink())),
+        });
+        let stderr = running.stderr().map(|x| match (print, writer) {
+            (true, true) => task::spawn(self::tee_lines(x)),
+            (true, false) => task::spawn(self::tee(x, io::stderr())),
+            (false, _) => task::spawn(self::tee(x, io::sink())),
+        });
+        let res = running.wait_until(crate::process::deadline(deadline)).await;
+
+        // Pipes are closed once the process exits, so the rest of the output is collected right away
+        let stdout = self::collect(stdout).await;
+        let stderr = self::collect(stderr).await;
+        match res {
+            Ok(ExitResult::Output(_)) => Ok(CmdOutput {
+                stdout,
+                stderr,
+                interrupted: false,
+            }),
+            Ok(ExitResult::Interrupted | ExitResult::Killed { pid: _ }) => Ok(CmdOutput {
+                stdout,
+                stderr,
+                interrupted: true,
+            }),
+            Err(Error::NonZeroExitCode { code, mut output }) => {
+                output.stdout = stdout;
+                output.stderr = stderr;
+                Err(Error::NonZeroExitCode { code, output })
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Passes a stream to the console writer line by line while collecting it. See [`set_console_writer`](crate::set_console_writer).
+async fn tee_lines<R>(reader: R) -> Vec<u8>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut reader = BufReader::new(reader);
+    let mut collected = vec![];
+    loop {
+        let start = collected.len();
+        match reader.read_until(b'\n', &mut collected).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let line = &collected[start..];
+                let line = line.strip_suffix(b"\n").unwrap_or(line);
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                crate::fmt::write_line(String::from_utf8_lossy(line));
+            }
+        }
+    }
+    collected
+}
+
+/// Copies a stream to the output while collecting it.
+async fn tee<R, W>(mut reader: R, mut output: W) -> Vec<u8>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut collected = vec![];
+    let mut buf = [0; 8192];
+    loop {
+        match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                collected.extend_from_slice(&buf[..n]);
+                // Output is best effort: failing to print doesn't stop collecting
+                let _ = output.write_all(&buf[..n]).await;
+                let _ = output.flush().await;
+            }
+        }
+    }
+    collected
+}
+
+/// Waits for the output collected in the background.
+pub(crate) async fn collect(handle: Option<task::JoinHandle<Vec<u8>>>) -> Vec<u8> {
+    match handle {
+        Some(handle) => handle.await.unwrap_or_default(),
+        None => vec![],
+    }
+}
+
+/// Runs a future with the executor, so all the one-off commands run within it (but not within the tasks
+/// it spawns via [`tokio::spawn`](tokio::spawn)) are executed by this executor.
+///
+/// ```ignore
+/// #[tokio::test]
+/// async fn builds_before_deploy() {
+///     let executor = MockExecutor::new().respond("git rev-parse HEAD", "abc123\n");
+///
+///     steward::with_executor(executor.clone(), deploy()).await.unwrap();
+///
+///     assert_eq!(
+///         executor.exes(),
+///         ["cargo build --release", "git rev-parse HEAD", "./deploy.sh abc123"]
+///     );
+/// }
+/// ```
+pub async fn with_executor<E, F>(executor: E, f: F) -> F::Output
+where
+    E: Executor + 'static,
+    F: Future,
+{
+    EXECUTOR.scope(Arc::new(executor), f).await
+}
+
+/// Executes a one-off command with the executor of the current scope.
+pub(crate) async fn execute(invocation: &Invocation) -> Result<Output> {
+    match EXECUTOR.try_with(Arc::clone) {
+        Ok(executor) => executor.execute(invocation).await,
+        Err(_) => SystemExecutor.execute(invocation).await,
+    }
+}
+
+/// Spawns a command with the executor of the current scope.
+pub(crate) fn spawn(invocation: &Invocation, opts: SpawnOptions) -> Result<RunningProcess> {
+    match EXECUTOR.try_with(Arc::clone) {
+        Ok(executor) => executor.spawn(invocation, opts),
+        Err(_) => SystemExecutor.spawn(invocation, opts),
+    }
+}
+
+/// Executes a one-off command with the executor of the current scope, capturing both stdout and stderr.
+pub(crate) async fn execute_captured(invocation: &Invocation) -> Result<CmdOutput> {
+    match EXECUTOR.try_with(Arc::clone) {
+        Ok(executor) => executor.execute_captured(invocation).await,
+        Err(_) => SystemExecutor.execute_captured(invocation).await,
+    }
+}
+
+/// An [`Executor`](Executor) that doesn't spawn processes. It records invocations and responds with canned outputs.
+///
+/// Responses are matched against a command: the first response with a pattern that the command contains is used.
+/// Commands without a matching response succeed with empty output. Spawned commands are recorded as well,
+/// but fail with [`Error::IoError`](Error::IoError), since there is no process to provide a handle to. Clones share the state,
+/// so a clone can be passed to [`with_executor`](with_executor) and the original one inspected afterwards.
+#[derive(Clone, Default)]
+pub struct MockExecutor {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    invocations: Vec<Invocation>,
+    responses: Vec<(String, MockResponse)>,
+}
+
+enum MockResponse {
+    Stdout(Vec<u8>),
+    Exit(i32),
+    Interrupted,
+}
+
+impl MockExecutor {
+    /// Constructs a new executor without responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes commands that contain the pattern succeed with the stdout.
+    pub fn respond(self, pattern: impl Into<String>, stdout: impl Into<Vec<u8>>) -> Self {
+        self.push(pattern, MockResponse::Stdout(stdout.into()))
+    }
+
+    /// Makes commands that contain the pattern exit with the non-zero code.
+    pub fn fail(self, pattern: impl Into<String>, code: i32) -> Self {
+        self.push(pattern, MockResponse::Exit(code))
+    }
+
+    /// Makes commands that contain the pattern behave as if they were interrupted (e.g. with `Ctrl+C`).
+    pub fn interrupt(self, pattern: impl Into<String>) -> Self {
+        self.push(pattern, MockResponse::Interrupted)
+    }
+
+    /// Returns the recorded invocations in order.
+    pub fn invocations(&self) -> Vec<Invocation> {
+        self.state().invocations.clone()
+    }
+
+    /// Returns the recorded commands in order.
+    pub fn exes(&self) -> Vec<String> {
+        self.state()
+            .invocations
+            .iter()
+            .map(|x| x.exe.to_owned())
+            .collect()
+    }
+
+    fn push(self, pattern: impl Into<String>, response: MockResponse) -> Self {
+        self.state().responses.push((pattern.into(), response));
+        self
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[async_trait]
+impl Executor for MockExecutor {
+    async fn execute(&self, invocation: &Invocation) -> Result<Output> {
+        let mut state = self.state();
+        state.invocations.push(invocation.to_owned());
+        let response = state
+            .responses
+            .iter()
+            .find(|(pattern, _)| invocation.exe.contains(pattern.as_str()))
+            .map(|(_, response)| response);
+        match response {
+            None => Ok(Output::Data(vec![])),
+            Some(MockResponse::Stdout(stdout)) => Ok(Output::Data(stdout.to_owned())),
+            Some(MockResponse::Interrupted) => Ok(Output::Interrupted),
+            Some(MockResponse::Exit(code)) => Err(Error::NonZeroExitCode {
+                code: Some(*code),
+                output: process::Output {
+                    status: self::exit_status(*code),
+                    stdout: vec![],
+                    stderr: vec![],
+                },
+            }),
+        }
+    }
+
+    fn spawn(&self, invocation: &Invocation, _opts: SpawnOptions) -> Result<RunningProcess> {
+        self.state().invocations.push(invocation.to_owned());
+        Err(Error::IoError(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("MockExecutor doesn't spawn processes: {}", invocation.exe),
+        )))
+    }
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    // The raw status holds the exit code in the second byte
+    ExitStatus::from_raw((code & 0xff) << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{Cmd, Location};
+
+    #[derive(Clone)]
+    struct Loc(PathBuf);
+
+    impl Location for Loc {
+        fn apex() -> Self {
+            Self(PathBuf::from("/nonexistent"))
+        }
+
+        fn as_path(&self) -> &PathBuf {
+            &self.0
+        }
+    }
+
+    fn cmd(exe: &str) -> Cmd<Loc> {
+        Cmd::new(exe, Env::empty(), Loc::apex(), None)
+    }
+
+    #[tokio::test]
+    async fn mock_executor() {
+        let executor = MockExecutor::new()
+            .respond("rev-parse", "abc123")
+            .fail("deploy", 2);
+
+        let res = with_executor(executor.clone(), async {
+            let rev = cmd("git rev-parse HEAD").output().await?.unwrap_string()?;
+            cmd("cargo build").silent().await?;
+            cmd(&format!("./deploy.sh {}", rev)).silent().await
+        })
+        .await;
+
+        assert!(matches!(
+            res,
+            Err(Error::NonZeroExitCode { code: Some(2), .. })
+        ));
+        assert_eq!(
+            executor.exes(),
+            ["git rev-parse HEAD", "cargo build", "./deploy.sh abc123"]
+        );
+        assert_eq!(executor.invocations()[0].output, OutputMode::Capture);
+    }
+
+    #[tokio::test]
+    async fn mock_spawn() {
+        let executor = MockExecutor::new();
+        let res = with_executor(executor.clone(), async {
+            cmd("tail -f log/server.log").stream().map(drop)
+        })
+        .await;
+        assert!(matches!(res, Err(Error::IoError(_))));
+        assert_eq!(executor.exes(), ["tail -f log/server.log"]);
+    }
+
+    #[tokio::test]
+    async fn watch_via_executor() {
+        let executor = MockExecutor::new().interrupt("build");
+        let glob = std::env::temp_dir().join("steward-watch-*.none");
+        with_executor(executor.clone(), cmd("cargo build").watch([glob]))
+            .await
+            .unwrap();
+        assert_eq!(executor.exes(), ["cargo build"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn special_chars_in_args() {
+        let arg = r#"my file "quoted" & it's ^ $HOME %PATH%"#;
+        let cmd = Cmd::new(
+            "printf '%s\\n'",
+            Env::empty(),
+            Loc(std::env::temp_dir()),
+            None,
+        )
+        .arg(arg)
+        .raw_arg("| tr a A");
+        let lines = cmd.output_lines().await.unwrap();
+        assert_eq!(lines, [arg.replace('a', "A")]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn spawn_options() {
+        let opts = SpawnOptions::default()
+            .stdout(Stdio::piped())
+            .pwd("/")
+            .process_group(ProcessGroup::New);
+        // The pid and the process group of the shell
+        let running = cmd("pwd; cut -d' ' -f1,5 /proc/$$/stat")
+            .spawn(opts)
+            .unwrap();
+        let output = running.into_child().wait_with_output().await.unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let (pwd, ids) = stdout.trim().split_once('\n').unwrap();
+        assert_eq!(pwd, "/");
+        let (pid, pgid) = ids.split_once(' ').unwrap();
+        assert_eq!(pid, pgid);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn kill_on_timeout() {
+        let opts = || SpawnOptions {
+            stdout: Stdio::null(),
+            stderr: Stdio::null(),
+            timeout: crate::KillTimeout::new(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let cmd = |exe| Cmd::new(exe, Env::empty(), Loc(std::env::temp_dir()), None);
+
+        // Stopped once the shell ignores SIGINT
+        let running = cmd("trap '' INT; sleep 5").spawn(opts()).unwrap();
+        let res = running
+            .wait_until(tokio::time::sleep(Duration::from_millis(200)))
+            .await;
+        assert!(matches!(res, Ok(ExitResult::Killed { .. })));
+
+        // The process has exited, but it's not reaped yet
+        let running = cmd("exit 0").spawn(opts()).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let res = running.wait_until(std::future::ready(())).await;
+        assert!(matches!(
+            res,
+            Ok(ExitResult::Interrupted | ExitResult::Output(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn parsed_output() {
+        let executor = MockExecutor::new()
+            .respond("ls-files", "src/lib.rs\nsrc/cmd.rs\n")
+            .respond("metadata", "{\"version\": 1}")
+            .interrupt("status");
+
+        with_executor(executor, async {
+            let files = cmd("git ls-files").output_lines().await.unwrap();
+            assert_eq!(files, ["src/lib.rs", "src/cmd.rs"]);
+            let res = cmd("git status").output_lines().await;
+            assert!(
+                matches!(res, Err(Error::InvalidOutput { reason, .. }) if reason == "interrupted")
+            );
+            #[cfg(feature = "json")]
+            {
+                let res = cmd("cargo metadata").output_json::<u32>().await;
+                assert!(matches!(res, Err(Error::InvalidOutput { .. })));
+                let version = cmd("cargo metadata")
+                    .output_json::<std::collections::HashMap<String, u32>>()
+                    .await
+                    .unwrap();
+                assert_eq!(version["version"], 1);
+            }
+        })
+        .await;
+    }
+}
@@ -1,6 +1,18 @@
-use std::fmt::Display;
+use std::{
+    borrow::Cow,
+    fmt::Display,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, RwLock,
+    },
+};
 
-/// Formats a headline that gets printed to console when running a command.
+use console::{Color, StyledObject};
+use once_cell::sync::Lazy;
+
+use crate::Env;
+
+/// Formats a headline that gets printed to console when running a command, according to the current [`Theme`](crate::Theme).
 ///
 /// ```sh
 /// ❯ Building server: $ cargo build [@ my-project/server]
@@ -8,14 +20,33 @@ use std::fmt::Display;
 #[macro_export]
 macro_rules! headline {
     ($cmd:expr) => {{
-        let cmd = console::style(format!("$ {} [@ {}]", $cmd.exe(), $cmd.pwd().display())).dim();
-        match $cmd.msg() {
-            Some(msg) => format!("❯ {} {}", console::style(format!("{}:", msg)).bold(), cmd),
-            None => format!("❯ {}", cmd),
-        }
+        $crate::fmt::headline($cmd.msg().map(|x| x.as_str()), $cmd.exe(), &$cmd.place())
     }};
 }
 
+pub(crate) fn headline(msg: Option<&str>, exe: &str, place: &str) -> String {
+    self::themed_headline(&self::theme(), msg, exe, place)
+}
+
+fn themed_headline(theme: &Theme, msg: Option<&str>, exe: &str, place: &str) -> String {
+    let cmd = if theme.show_cwd {
+        format!("$ {} [@ {}]", exe, place)
+    } else {
+        format!("$ {}", exe)
+    };
+    let cmd = self::style(cmd).dim();
+    let line = match (msg.filter(|_| theme.show_msg), theme.order) {
+        (Some(msg), HeadlineOrder::MsgFirst) => {
+            format!("{} {}", self::style(format!("{}:", msg)).bold(), cmd)
+        }
+        (Some(msg), HeadlineOrder::CmdFirst) => {
+            format!("{}  {}", cmd, self::style(format!("# {}", msg)).bold())
+        }
+        (None, _) => cmd.to_string(),
+    };
+    self::prefixed(theme, line)
+}
+
 /// Prints a formatted message to console.
 ///
 /// ```rust
@@ -28,9 +59,442 @@ macro_rules! headline {
 /// ❯ Nothing to do. Exiting.
 /// ```
 pub fn print(msg: impl Display) {
-    eprintln!("{}", self::plain_headline(msg))
+    console!("{}", self::plain_headline(msg))
+}
+
+/// A function that receives the console output of steward. See [`set_console_writer`](set_console_writer).
+pub type ConsoleWriter = Arc<dyn Fn(&str) + Send + Sync>;
+
+static CONSOLE_WRITER: Lazy<RwLock<Option<ConsoleWriter>>> = Lazy::new(|| RwLock::new(None));
+
+/// Redirects the console output of steward from stderr to a function: headlines of commands, the tagged output
+/// of the processes of a [`ProcessPool`](crate::ProcessPool), and the status of the processes and their dependencies.
+/// `None` restores the output to stderr.
+///
+/// It lets an application that draws progress bars on its own print the output above them,
+/// instead of fighting over the cursor, e.g. with [`indicatif`](https://docs.rs/indicatif):
+///
+/// ```ignore
+/// let progress = MultiProgress::new();
+/// let writer = progress.clone();
+/// steward::set_console_writer(Some(Arc::new(move |line| {
+///     writer.println(line).ok();
+/// })));
+///
+/// let bar = progress.add(ProgressBar::new_spinner());
+/// let pool = ProcessPool::start(pool![server::watch(), client::watch()], PoolOptions::default());
+/// pool.ready().await?;
+/// bar.finish_with_message("Ready");
+/// ```
+///
+/// A message may span several lines. Output of one-off commands (e.g. [`Cmd::run`](crate::Cmd::run)) is passed
+/// to the writer line by line as well, instead of being inherited from the current process.
+/// To render the state of the processes of a pool, e.g. as a spinner per process, see [`PoolHandle::stats`](crate::PoolHandle::stats).
+pub fn set_console_writer(writer: Option<ConsoleWriter>) {
+    *CONSOLE_WRITER
+        .write()
+        .unwrap_or_else(|err| err.into_inner()) = writer;
+}
+
+/// Whether the console output is redirected via [`set_console_writer`](set_console_writer).
+pub(crate) fn has_console_writer() -> bool {
+    CONSOLE_WRITER
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .is_some()
+}
+
+/// Prints a line to stderr, or passes it to the writer set by [`set_console_writer`](set_console_writer).
+pub(crate) fn write_line(line: impl Display) {
+    let writer = CONSOLE_WRITER
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone();
+    match writer {
+        Some(writer) => writer(&line.to_string()),
+        None => eprintln!("{}", line),
+    }
 }
 
 pub(crate) fn plain_headline(msg: impl Display) -> String {
-    format!("❯ {}", console::style(msg).bold())
+    self::prefixed(&self::theme(), self::style(msg).bold())
+}
+
+/// Prefixes a message of steward according to the current [`Theme`](Theme).
+pub(crate) fn line(line: impl Display) -> String {
+    self::prefixed(&self::theme(), line)
+}
+
+fn prefixed(theme: &Theme, line: impl Display) -> String {
+    if theme.prefix.is_empty() {
+        line.to_string()
+    } else {
+        format!("{} {}", theme.prefix, line)
+    }
+}
+
+/// Layout of the headlines that are printed to console when running commands, and of the messages of steward,
+/// e.g. to use `[steward]`-style prefixes, or to print bare commands only. Applies to the current process.
+///
+/// ```ignore
+/// Theme {
+///     prefix: "[steward]".into(),
+///     show_cwd: false,
+///     ..Default::default()
+/// }
+/// .set();
+/// ```
+///
+/// would print
+///
+/// ```sh
+/// [steward] Building server: $ cargo build
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    /// Prefix of the headlines, `❯` by default. An empty prefix is omitted.
+    pub prefix: Cow<'static, str>,
+    /// Whether to print the message of a command, e.g. `Building server:`.
+    pub show_msg: bool,
+    /// Whether to print where a command runs, e.g. `[@ my-project/server]`.
+    pub show_cwd: bool,
+    /// Whether the message of a command is printed before or after the command. See [`HeadlineOrder`](HeadlineOrder).
+    pub order: HeadlineOrder,
+}
+
+/// Order of the message of a command and the command itself in a headline. See [`Theme`](Theme).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HeadlineOrder {
+    /// `❯ Building server: $ cargo build [@ my-project/server]`
+    #[default]
+    MsgFirst,
+    /// `❯ $ cargo build [@ my-project/server]  # Building server`
+    CmdFirst,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            prefix: Cow::Borrowed("❯"),
+            show_msg: true,
+            show_cwd: true,
+            order: HeadlineOrder::MsgFirst,
+        }
+    }
+}
+
+static THEME: Lazy<RwLock<Theme>> = Lazy::new(|| RwLock::new(Theme::default()));
+
+impl Theme {
+    /// Sets the theme for the current process.
+    pub fn set(self) {
+        *THEME.write().unwrap_or_else(|err| err.into_inner()) = self;
+    }
+
+    /// Returns the theme of the current process.
+    pub fn get() -> Self {
+        self::theme().clone()
+    }
+}
+
+fn theme() -> std::sync::RwLockReadGuard<'static, Theme> {
+    THEME.read().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Controls colors in the output of steward and the color hints passed to child processes.
+///
+/// By default, the mode is [`ColorMode::Auto`](ColorMode::Auto): the output is colored only when stderr
+/// of the current process is a terminal. So logs of CI runs stay clean, while local runs stay colorful.
+///
+/// ```ignore
+/// // e.g. when a user passed `--no-color` flag to your cli
+/// ColorMode::Never.set();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Colors are enabled when stderr is a terminal.
+    /// Respects [`NO_COLOR`](https://no-color.org) and [`CLICOLOR`](http://bixense.com/clicolors/) conventions.
+    Auto,
+    /// Colors are always enabled.
+    Always,
+    /// Colors are always disabled.
+    Never,
+}
+
+static COLOR_MODE: AtomicU8 = AtomicU8::new(ColorMode::Auto as u8);
+
+static AUTO_COLORS: Lazy<bool> = Lazy::new(|| match std::env::var("NO_COLOR") {
+    Ok(val) if !val.is_empty() => false,
+    Ok(_) | Err(_) => console::colors_enabled_stderr(),
+});
+
+impl ColorMode {
+    /// Sets the color mode for the current process.
+    pub fn set(self) {
+        COLOR_MODE.store(self as u8, Ordering::Relaxed)
+    }
+
+    /// Returns the color mode of the current process.
+    pub fn get() -> Self {
+        match COLOR_MODE.load(Ordering::Relaxed) {
+            x if x == Self::Always as u8 => Self::Always,
+            x if x == Self::Never as u8 => Self::Never,
+            _ => Self::Auto,
+        }
+    }
+
+    /// Returns `true` if the output should be colored.
+    pub fn enabled() -> bool {
+        match Self::get() {
+            Self::Auto => *AUTO_COLORS,
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+
+    /// Environment that tells a child process whether it should color its output.
+    ///
+    /// Children of a [`ProcessPool`](crate::ProcessPool) write to a pipe, so most of the tools
+    /// would disable colors on their own. These hints make them follow the mode of the current process instead.
+    /// Values set in the environment of a command take precedence over these.
+    pub fn child_env() -> Env {
+        if Self::enabled() {
+            Env::one("FORCE_COLOR", 1).insert("CLICOLOR_FORCE", 1)
+        } else {
+            Env::one("NO_COLOR", 1).insert("CLICOLOR", 0)
+        }
+    }
+}
+
+/// Styles an object according to the current [`ColorMode`](ColorMode).
+pub(crate) fn style<D>(val: D) -> StyledObject<D> {
+    console::style(val).force_styling(ColorMode::enabled())
+}
+
+/// Formatting options of the [`ProcessPool`](crate::ProcessPool) output.
+#[derive(Clone, Debug)]
+pub struct PoolFormat {
+    /// Max width of a tag in the tag column. Longer tags are truncated. By default, the tag column fits the longest tag.
+    pub max_tag_width: Option<usize>,
+    /// What to do with lines that don't fit the terminal width. See [`LongLines`](LongLines).
+    pub long_lines: LongLines,
+    /// Whether to repeat a tag on continuation lines when [`LongLines::Wrap`](LongLines::Wrap) is used.
+    pub repeat_tag: bool,
+}
+
+impl Default for PoolFormat {
+    fn default() -> Self {
+        Self {
+            max_tag_width: None,
+            long_lines: LongLines::Keep,
+            repeat_tag: true,
+        }
+    }
+}
+
+/// Defines how lines that don't fit the terminal width are handled. When stderr is not a terminal, lines are kept as is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LongLines {
+    /// Lines are printed as is and wrapped by the terminal.
+    Keep,
+    /// Lines are wrapped, so continuation lines are aligned with the tag column.
+    Wrap,
+    /// Lines are truncated to the terminal width.
+    Truncate,
+}
+
+/// Renders the tag column of the [`ProcessPool`](crate::ProcessPool) output.
+///
+/// ```sh
+/// server  | Listening on 127.0.0.1:3000
+/// client  | Compiled successfully.
+/// ```
+pub(crate) struct TagColumn {
+    col: String,
+    blank: String,
+    width: usize,
+    format: PoolFormat,
+}
+
+impl TagColumn {
+    pub(crate) fn new(tag: &str, color: Color, tag_width: usize, format: &PoolFormat) -> Self {
+        let tag = self::truncate(tag, tag_width);
+        let pad = " ".repeat(tag_width.saturating_sub(console::measure_text_width(&tag)) + 2);
+        let pipe = self::style("|").fg(color).bold();
+        let col = format!("{}{}{}", self::style(&tag).fg(color).bold(), pad, pipe);
+        let blank = format!("{}{}", " ".repeat(tag_width + 2), pipe);
+        Self {
+            col,
+            blank,
+            width: tag_width + 3,
+            format: format.to_owned(),
+        }
+    }
+
+    /// Prints a line of a process output.
+    pub(crate) fn print(&self, line: &str) {
+        let available = match self.format.long_lines {
+            LongLines::Keep => None,
+            LongLines::Wrap | LongLines::Truncate => console::Term::stderr()
+                .size_checked()
+                .map(|(_rows, cols)| (cols as usize).saturating_sub(self.width + 1).max(1)),
+        };
+
+        match (self.format.long_lines, available) {
+            (LongLines::Keep, _) | (_, None) => console!("{} {}", self.col, line),
+            (LongLines::Truncate, Some(width)) => {
+                console!("{} {}", self.col, self::truncate(line, width))
+            }
+            (LongLines::Wrap, Some(width)) => {
+                for (idx, chunk) in self::wrap(line, width).into_iter().enumerate() {
+                    let col = if idx == 0 || self.format.repeat_tag {
+                        &self.col
+                    } else {
+                        &self.blank
+                    };
+                    console!("{} {}", col, chunk);
+                }
+            }
+        }
+    }
+}
+
+impl Display for TagColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.col)
+    }
+}
+
+/// Formats a duration for humans, e.g. `250ms`, `42s` or `1h 02m 13s`.
+pub(crate) fn duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if secs == 0 {
+        format!("{}ms", duration.as_millis())
+    } else if h > 0 {
+        format!("{}h {:02}m {:02}s", h, m, s)
+    } else if m > 0 {
+        format!("{}m {:02}s", m, s)
+    } else {
+        format!("{}s", s)
+    }
+}
+
+/// Formats a number of bytes for humans, e.g. `512 KiB` or `1.5 GiB`.
+pub(crate) fn bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value.fract() < 0.05 || value >= 100.0 {
+        format!("{:.0} {}", value, UNITS[unit])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Truncates a string to the given visible width with an ellipsis.
+/// Unlike `console::truncate_str`, keeps a string that fits the width exactly as is.
+fn truncate(s: &str, width: usize) -> Cow<'_, str> {
+    if console::measure_text_width(s) <= width {
+        Cow::Borrowed(s)
+    } else {
+        console::truncate_str(s, width, "…")
+    }
+}
+
+/// Splits a line into chunks of the given visible width. ANSI escape codes don't count towards the width
+/// and the active ones are carried over to the next chunk, since the tag column resets styles.
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    const RESET: &str = "\x1b[0m";
+
+    let mut chunks = vec![];
+    let mut chunk = String::new();
+    let mut chunk_width = 0;
+    let mut active = String::new();
+    for (part, is_ansi) in console::AnsiCodeIterator::new(line) {
+        if is_ansi {
+            if part == RESET || part == "\x1b[m" {
+                active.clear();
+            } else {
+                active.push_str(part);
+            }
+            chunk.push_str(part);
+            continue;
+        }
+        for char in part.chars() {
+            let char_width = console::measure_text_width(char.encode_utf8(&mut [0; 4]));
+            if chunk_width + char_width > width && chunk_width > 0 {
+                if !active.is_empty() {
+                    chunk.push_str(RESET);
+                }
+                chunks.push(std::mem::replace(&mut chunk, active.clone()));
+                chunk_width = 0;
+            }
+            chunk.push(char);
+            chunk_width += char_width;
+        }
+    }
+    chunks.push(chunk);
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn truncate_keeps_fitting_string() {
+        assert_eq!(super::truncate("bad", 3), "bad");
+        assert_eq!(super::truncate("server", 3), "se…");
+    }
+
+    #[test]
+    fn wrap_plain_line() {
+        assert_eq!(super::wrap("abcdefg", 3), vec!["abc", "def", "g"]);
+    }
+
+    #[test]
+    fn wrap_carries_styles_over() {
+        assert_eq!(
+            super::wrap("\x1b[31mabcd\x1b[0m", 2),
+            vec!["\x1b[31mab\x1b[0m", "\x1b[31mcd\x1b[0m"]
+        );
+    }
+
+    #[test]
+    fn themed_headlines() {
+        use super::{themed_headline, HeadlineOrder, Theme};
+
+        let headline = |theme: &Theme| {
+            console::strip_ansi_codes(&themed_headline(theme, Some("Building"), "make", "app"))
+                .into_owned()
+        };
+        assert_eq!(headline(&Theme::default()), "❯ Building: $ make [@ app]");
+        let theme = Theme {
+            prefix: "[steward]".into(),
+            show_cwd: false,
+            order: HeadlineOrder::CmdFirst,
+            ..Default::default()
+        };
+        assert_eq!(headline(&theme), "[steward] $ make  # Building");
+        let theme = Theme {
+            prefix: "".into(),
+            show_msg: false,
+            show_cwd: false,
+            ..Default::default()
+        };
+        assert_eq!(headline(&theme), "$ make");
+    }
+
+    #[test]
+    fn human_bytes() {
+        assert_eq!(super::bytes(512), "512 B");
+        assert_eq!(super::bytes(512 * 1024), "512 KiB");
+        assert_eq!(super::bytes(3 * 1024 * 1024 * 1024 / 2), "1.5 GiB");
+    }
 }
@@ -1,9 +1,12 @@
-use std::time::{Duration, Instant};
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
 
 use async_trait::async_trait;
-use tokio::time;
 
-use crate::{Dependency, DependencyWaitError, Location};
+use crate::{env::PATH, Cmd, Dependency, DependencyWaitError, Location, RetryPolicy};
 
 /// File system entry.
 pub struct FsEntry<Loc> {
@@ -13,18 +16,38 @@ pub struct FsEntry<Loc> {
     pub addr: Loc,
     /// FS entry timeout.
     pub timeout: Duration,
+    /// A hint printed when the entry doesn't appear in time, e.g. `run cargo build first`.
+    pub hint: Option<String>,
+}
+
+impl<Loc> FsEntry<Loc> {
+    /// Constructs new FsEntry.
+    pub fn new(tag: impl Into<String>, addr: Loc, timeout: Duration) -> Self {
+        Self {
+            tag: tag.into(),
+            addr,
+            timeout,
+            hint: None,
+        }
+    }
+
+    /// Sets a [hint](FsEntry#structfield.hint) printed when the entry doesn't appear in time.
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
 enum FsEntryWaitError {
     #[error("Timeout")]
     Timeout,
+    #[error("Invalid glob pattern: {0}")]
+    InvalidPattern(String),
 }
 
 impl DependencyWaitError for FsEntryWaitError {}
 
-const ITER_GAP: Duration = Duration::from_millis(250);
-
 #[async_trait]
 impl<Loc> Dependency for FsEntry<Loc>
 where
@@ -34,6 +57,18 @@ where
         &self.tag
     }
 
+    fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    fn key(&self) -> Option<String> {
+        Some(format!(
+            "fs:{} {:?}",
+            self.addr.as_path().display(),
+            self.timeout
+        ))
+    }
+
     async fn check(&self) -> Result<(), ()> {
         let path = self.addr.as_path();
 
@@ -44,23 +79,347 @@ where
         }
     }
 
-    async fn wait(&self) -> Result<(), Box<dyn DependencyWaitError>> {
+    async fn wait_with_deadline(
+        &self,
+        deadline: Instant,
+    ) -> Result<(), Box<dyn DependencyWaitError>> {
         let path = self.addr.as_path();
 
-        let expiration = Instant::now() + self.timeout;
+        let mut attempts = RetryPolicy::poll(self.timeout)
+            .start()
+            .until(Some(deadline));
 
         loop {
             if path.exists() {
                 break;
-            } else {
-                if Instant::now() > expiration {
-                    return Err(Box::new(FsEntryWaitError::Timeout));
-                }
-
-                time::sleep(ITER_GAP).await
+            } else if !attempts.next().await {
+                return Err(Box::new(FsEntryWaitError::Timeout));
             }
         }
 
         Ok(())
     }
 }
+
+/// Any file that matches a glob pattern within a location, e.g. `dist/*.js`.
+/// Useful for build outputs with hashed names, which can't be expressed by an exact path of an [`FsEntry`](FsEntry).
+///
+/// ```ignore
+/// GlobDep::new("client bundle", Loc::client(), "dist/main.*.js", Duration::from_secs(60))
+/// ```
+pub struct GlobDep<Loc> {
+    /// A tag used as an identificator of the dependency in the output.
+    pub tag: String,
+    /// A location the pattern is relative to.
+    pub addr: Loc,
+    /// A glob pattern, e.g. `dist/*.js`. See [`Location::glob`](Location::glob).
+    pub pattern: String,
+    /// Wait timeout.
+    pub timeout: Duration,
+    /// A hint printed when no matching file appears in time, e.g. `is the client watcher running?`.
+    pub hint: Option<String>,
+}
+
+impl<Loc> GlobDep<Loc> {
+    /// Constructs new GlobDep.
+    pub fn new(
+        tag: impl Into<String>,
+        addr: Loc,
+        pattern: impl Into<String>,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            tag: tag.into(),
+            addr,
+            pattern: pattern.into(),
+            timeout,
+            hint: None,
+        }
+    }
+
+    /// Sets a [hint](GlobDep#structfield.hint) printed when no matching file appears in time.
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl<Loc> GlobDep<Loc>
+where
+    Loc: Location,
+{
+    /// Whether a matching file exists.
+    fn matches(&self) -> Result<bool, FsEntryWaitError> {
+        match self.addr.glob(&self.pattern) {
+            Ok(paths) => Ok(paths.iter().any(|path| path.is_file())),
+            Err(err) if err.kind() == io::ErrorKind::InvalidInput => {
+                Err(FsEntryWaitError::InvalidPattern(err.to_string()))
+            }
+            // E.g. a directory that is being removed
+            Err(_) => Ok(false),
+        }
+    }
+}
+
+#[async_trait]
+impl<Loc> Dependency for GlobDep<Loc>
+where
+    Loc: Location,
+{
+    fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    fn key(&self) -> Option<String> {
+        Some(format!(
+            "glob:{} {} {:?}",
+            self.addr.as_path().display(),
+            self.pattern,
+            self.timeout
+        ))
+    }
+
+    async fn check(&self) -> Result<(), ()> {
+        match self.matches() {
+            Ok(true) => Ok(()),
+            Ok(false) | Err(_) => Err(()),
+        }
+    }
+
+    async fn wait_with_deadline(
+        &self,
+        deadline: Instant,
+    ) -> Result<(), Box<dyn DependencyWaitError>> {
+        let mut attempts = RetryPolicy::poll(self.timeout)
+            .start()
+            .until(Some(deadline));
+
+        loop {
+            match self.matches() {
+                Ok(true) => return Ok(()),
+                Ok(false) => (),
+                Err(err) => return Err(Box::new(err)),
+            }
+            if !attempts.next().await {
+                return Err(Box::new(FsEntryWaitError::Timeout));
+            }
+        }
+    }
+}
+
+/// File system entry that exists and hasn't changed (neither its size nor its modification time) for a quiet period.
+/// Useful when a dependent reads a file that a generator is still writing.
+///
+/// ```ignore
+/// FsStableDep::new("schema", Loc::schema(), Duration::from_secs(2), Duration::from_secs(60))
+/// ```
+pub struct FsStableDep<Loc> {
+    /// A tag used as an identificator of the dependency in the output.
+    pub tag: String,
+    /// A location of the FS entry.
+    pub addr: Loc,
+    /// For how long the FS entry must stay unchanged.
+    pub quiet: Duration,
+    /// Wait timeout.
+    pub timeout: Duration,
+    /// A hint printed when the entry doesn't settle in time, e.g. `is codegen stuck?`.
+    pub hint: Option<String>,
+}
+
+impl<Loc> FsStableDep<Loc> {
+    /// Constructs new FsStableDep.
+    pub fn new(tag: impl Into<String>, addr: Loc, quiet: Duration, timeout: Duration) -> Self {
+        Self {
+            tag: tag.into(),
+            addr,
+            quiet,
+            timeout,
+            hint: None,
+        }
+    }
+
+    /// Sets a [hint](FsStableDep#structfield.hint) printed when the entry doesn't settle in time.
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl<Loc> FsStableDep<Loc>
+where
+    Loc: Location,
+{
+    /// Size and modification time of the FS entry, if it exists.
+    fn state(&self) -> Option<(u64, SystemTime)> {
+        let meta = fs::metadata(self.addr.as_path()).ok()?;
+        Some((meta.len(), meta.modified().ok()?))
+    }
+
+    /// Whether the FS entry was modified longer than the quiet period ago.
+    fn is_quiet(&self, modified: SystemTime) -> bool {
+        modified
+            .elapsed()
+            .is_ok_and(|elapsed| elapsed >= self.quiet)
+    }
+}
+
+#[async_trait]
+impl<Loc> Dependency for FsStableDep<Loc>
+where
+    Loc: Location,
+{
+    fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    fn key(&self) -> Option<String> {
+        Some(format!(
+            "fs-stable:{} {:?} {:?}",
+            self.addr.as_path().display(),
+            self.quiet,
+            self.timeout
+        ))
+    }
+
+    async fn check(&self) -> Result<(), ()> {
+        match self.state() {
+            Some((_, modified)) if self.is_quiet(modified) => Ok(()),
+            Some(_) | None => Err(()),
+        }
+    }
+
+    async fn wait_with_deadline(
+        &self,
+        deadline: Instant,
+    ) -> Result<(), Box<dyn DependencyWaitError>> {
+        let mut attempts = RetryPolicy::poll(self.timeout)
+            .start()
+            .until(Some(deadline));
+        // The last seen state and when it was seen first.
+        // Modification time alone is not enough, since its resolution might be coarse.
+        let mut last: Option<((u64, SystemTime), Instant)> = None;
+
+        loop {
+            let state = self.state();
+            last = match (state, last) {
+                (None, _) => None,
+                (Some(state), Some((prev, since))) if state == prev => {
+                    if since.elapsed() >= self.quiet {
+                        return Ok(());
+                    }
+                    Some((prev, since))
+                }
+                (Some(state), _) => {
+                    if self.quiet.is_zero() || self.is_quiet(state.1) {
+                        return Ok(());
+                    }
+                    Some((state, Instant::now()))
+                }
+            };
+            if !attempts.next().await {
+                return Err(Box::new(FsEntryWaitError::Timeout));
+            }
+        }
+    }
+}
+
+/// Executable that becomes available on the `PATH`, e.g. when it gets installed by a bootstrap step.
+pub struct BinDep {
+    /// A tag used as an identificator of the dependency in the output.
+    pub tag: String,
+    /// A name of the executable.
+    pub name: String,
+    /// A `PATH` value to look up the executable in. If `None`, the `PATH` of the current process is used.
+    pub path: Option<String>,
+    /// Wait timeout.
+    pub timeout: Duration,
+    /// A hint printed when the executable doesn't appear on the `PATH` in time, e.g. `run bin/setup to install it`.
+    pub hint: Option<String>,
+}
+
+impl BinDep {
+    /// Constructs new BinDep that looks up the executable on the `PATH` of the current process.
+    pub fn new(name: impl Into<String>, timeout: Duration) -> Self {
+        let name = name.into();
+        Self {
+            tag: name.clone(),
+            name,
+            path: None,
+            timeout,
+            hint: None,
+        }
+    }
+
+    /// Constructs new BinDep that looks up the executable on the effective `PATH` of the command.
+    /// See [`Cmd::which`](crate::Cmd::which).
+    pub fn for_cmd<Loc>(name: impl Into<String>, cmd: &Cmd<Loc>, timeout: Duration) -> Self
+    where
+        Loc: Location,
+    {
+        Self {
+            path: cmd.env().get("PATH").cloned(),
+            ..Self::new(name, timeout)
+        }
+    }
+
+    /// Sets a [hint](BinDep#structfield.hint) printed when the executable doesn't appear on the `PATH` in time.
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    fn which(&self) -> Option<PathBuf> {
+        PATH::which(&self.name, self.path.as_deref())
+    }
+}
+
+#[async_trait]
+impl Dependency for BinDep {
+    fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    fn key(&self) -> Option<String> {
+        Some(format!(
+            "bin:{} {:?} {:?}",
+            self.name, self.path, self.timeout
+        ))
+    }
+
+    async fn check(&self) -> Result<(), ()> {
+        match self.which() {
+            Some(_) => Ok(()),
+            None => Err(()),
+        }
+    }
+
+    async fn wait_with_deadline(
+        &self,
+        deadline: Instant,
+    ) -> Result<(), Box<dyn DependencyWaitError>> {
+        let mut attempts = RetryPolicy::poll(self.timeout)
+            .start()
+            .until(Some(deadline));
+
+        loop {
+            if self.which().is_some() {
+                return Ok(());
+            }
+            if !attempts.next().await {
+                return Err(Box::new(FsEntryWaitError::Timeout));
+            }
+        }
+    }
+}
@@ -12,7 +12,7 @@ where
     Fun: Fn() -> Fut,
     Fut: Future<Output = Result<Ok, Err>>,
 {
-    eprintln!("{}", fmt::plain_headline(msg));
+    console!("{}", fmt::plain_headline(msg));
     f().await
 }
 
@@ -26,7 +26,7 @@ where
     Fun: FnMut() -> Fut,
     Fut: Future<Output = Result<Ok, Err>>,
 {
-    eprintln!("{}", fmt::plain_headline(msg));
+    console!("{}", fmt::plain_headline(msg));
     f().await
 }
 
@@ -40,6 +40,6 @@ where
     Fun: FnOnce() -> Fut,
     Fut: Future<Output = Result<Ok, Err>>,
 {
-    eprintln!("{}", fmt::plain_headline(msg));
+    console!("{}", fmt::plain_headline(msg));
     f().await
 }
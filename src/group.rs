@@ -0,0 +1,494 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::sync::watch;
+
+use crate::{
+    retry::Attempts, Cmd, Dependency, Exit, ExitResult, PoolEntry, Process, ProxyProcess, Restart,
+    Result, RetryPolicy,
+};
+
+/// A named group of entries of a [`ProcessPool`](crate::ProcessPool) with its own restart and shutdown policies,
+/// so a large stack can be organized as a supervision tree. Groups can be nested.
+///
+/// ```ignore
+/// ProcessPool::run_with_deps(pool![
+///     Group::new("backend", pool![db::run(), server::watch(), worker::watch()])
+///         .restart(Restart::OnFailure),
+///     Group::new("frontend", pool![client::watch(), docs::watch()])
+///         .shutdown(GroupShutdown::Together),
+/// ])
+/// .await
+/// ```
+pub struct Group<Loc> {
+    /// Name of a group, e.g. `frontend`.
+    pub name: &'static str,
+    /// Entries of a group, which can be groups as well.
+    pub entries: Vec<PoolEntry<Loc, dyn Dependency>>,
+    /// Restart policy of a group as a whole. When a process of a group exits on its own and the policy applies
+    /// to its exit, the rest of the group is stopped and all the processes of the group are started again.
+    /// If it doesn't apply, the process is restarted according to its own [`Process::restart`](Process::restart) policy.
+    ///
+    /// [`Restart::Never`](Restart::Never) by default, i.e. the processes are restarted individually.
+    pub restart: Restart,
+    /// Delays between restarts of a group and the max number of its starts. See [`RetryPolicy`](crate::RetryPolicy).
+    /// By default, a group is restarted in 1 second, without a limit.
+    pub backoff: RetryPolicy,
+    /// What happens to the rest of a group once one of its processes is stopped for good. See [`GroupShutdown`](GroupShutdown).
+    pub shutdown: GroupShutdown,
+}
+
+/// What happens to the rest of a [`Group`](Group) once one of its processes is stopped for good,
+/// i.e. it exited and is not going to be restarted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GroupShutdown {
+    /// The rest of the group keeps running.
+    #[default]
+    Independent,
+    /// The rest of the group is stopped as well.
+    Together,
+}
+
+impl<Loc> Group<Loc> {
+    /// Constructs a new group of entries.
+    pub fn new(
+        name: &'static str,
+        entries: impl IntoIterator<Item = impl Into<PoolEntry<Loc, dyn Dependency>>>,
+    ) -> Self {
+        Self {
+            name,
+            entries: entries.into_iter().map(Into::into).collect(),
+            restart: Restart::Never,
+            backoff: RetryPolicy::fixed(Restart::DELAY),
+            shutdown: GroupShutdown::Independent,
+        }
+    }
+
+    /// Sets a restart policy of the group as a whole. See [`Group::restart`](Group#structfield.restart).
+    pub fn restart(mut self, restart: Restart) -> Self {
+        self.restart = restart;
+        self
+    }
+
+    /// Sets delays between restarts of the group and the max number of its starts.
+    pub fn backoff(mut self, backoff: RetryPolicy) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sets what happens to the rest of the group once one of its processes is stopped for good.
+    pub fn shutdown(mut self, shutdown: GroupShutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+}
+
+/// A process of a pool along with its dependency and the innermost group it belongs to.
+/// The commands and the readiness check are set for [`Service`](crate::Service)s only,
+/// except for the setup command of [`PoolEntry::ProcessAfterCmd`](PoolEntry::ProcessAfterCmd).
+pub(crate) struct Member<Loc> {
+    pub(crate) process: Process<Loc>,
+    pub(crate) dependency: Option<Box<dyn Dependency>>,
+    pub(crate) group: Option<usize>,
+    pub(crate) setup: Option<Cmd<Loc>>,
+    pub(crate) readiness: Option<Arc<dyn Dependency>>,
+    pub(crate) teardown: Option<Cmd<Loc>>,
+}
+
+impl<Loc> Member<Loc> {
+    fn new(
+        process: Process<Loc>,
+        dependency: Option<Box<dyn Dependency>>,
+        group: Option<usize>,
+    ) -> Self {
+        Self {
+            process,
+            dependency,
+            group,
+            setup: None,
+            readiness: None,
+            teardown: None,
+        }
+    }
+}
+
+/// Command broadcast to the processes of a group.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum GroupCmd {
+    Run,
+    Restart(Duration),
+    Stop,
+}
+
+/// Groups of a running pool. Each group broadcasts its generation, which is bumped on every command,
+/// so a process can tell whether it was stopped by its group.
+pub(crate) struct Groups {
+    groups: Vec<GroupCtl>,
+    /// Serializes commands, so simultaneous exits of several processes of a group are handled once.
+    lock: Mutex<()>,
+}
+
+struct GroupCtl {
+    name: &'static str,
+    parent: Option<usize>,
+    restart: Restart,
+    shutdown: GroupShutdown,
+    attempts: Mutex<Attempts>,
+    tx: watch::Sender<(u64, GroupCmd)>,
+}
+
+pub(crate) type GroupRx = watch::Receiver<(u64, GroupCmd)>;
+
+impl Groups {
+    /// Flattens the entries of a pool into processes, proxies and groups. Proxies are not supervised by groups.
+    pub(crate) fn flatten<Loc>(
+        pool: Vec<PoolEntry<Loc, dyn Dependency>>,
+    ) -> (Vec<Member<Loc>>, Vec<ProxyProcess>, Self) {
+        let mut members = vec![];
+        let mut proxies = vec![];
+        let mut groups = vec![];
+        self::visit(pool, None, &mut members, &mut proxies, &mut groups);
+        let groups = Self {
+            groups,
+            lock: Mutex::new(()),
+        };
+        (members, proxies, groups)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Whether a process of the `innermost` group belongs to the group, directly or via nested groups.
+    pub(crate) fn contains(&self, group: usize, innermost: Option<usize>) -> bool {
+        innermost.is_some_and(|innermost| self.ancestors(innermost).any(|idx| idx == group))
+    }
+
+    pub(crate) fn name(&self, group: usize) -> &'static str {
+        self.groups[group].name
+    }
+
+    /// Receiver of the commands of the innermost group of a process.
+    pub(crate) fn subscribe(&self, group: Option<usize>) -> Option<GroupRx> {
+        group.map(|idx| self.groups[idx].tx.subscribe())
+    }
+
+    /// Handles an exit of a process that was spawned at the `generation` of its innermost group.
+    /// Returns the command of the group, if the process was stopped or has to be restarted by its group.
+    /// Otherwise, the process follows its own restart policy.
+    pub(crate) fn exited(
+        &self,
+        group: Option<usize>,
+        generation: u64,
+        tag: &str,
+        res: &Result<ExitResult>,
+    ) -> Option<GroupCmd> {
+        let innermost = group?;
+        let _lock = self.lock();
+        let (current, cmd) = *self.groups[innermost].tx.borrow();
+        if current != generation {
+            return Some(cmd);
+        }
+        let idx = self
+            .ancestors(innermost)
+            .find(|idx| self.groups[*idx].restart.applies(res))?;
+        let group = &self.groups[idx];
+        let delay = group
+            .attempts
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .fail();
+        let cmd = match delay {
+            Some(delay) => {
+                console!(
+                    "{}",
+                    crate::fmt::line(format_args!(
+                        "Process {} {}. Restarting {} group...",
+                        tag,
+                        Exit::from_result(res),
+                        crate::fmt::style(group.name).bold()
+                    ))
+                );
+                GroupCmd::Restart(delay)
+            }
+            None => {
+                console!(
+                    "{}",
+                    crate::fmt::line(format_args!(
+                        "Group {} reached the max number of restarts.",
+                        crate::fmt::style(group.name).bold()
+                    ))
+                );
+                GroupCmd::Stop
+            }
+        };
+        self.send(idx, cmd);
+        Some(cmd)
+    }
+
+    /// Handles a process that is stopped for good on its own: stops the innermost group that shuts down together.
+    pub(crate) fn stopped(&self, group: Option<usize>, generation: u64, tag: &str) {
+        let innermost = match group {
+            Some(innermost) => innermost,
+            None => return,
+        };
+        let _lock = self.lock();
+        // The process was stopped by its group
+        let (current, cmd) = *self.groups[innermost].tx.borrow();
+        if current != generation || cmd == GroupCmd::Stop {
+            return;
+        }
+        let idx = match self
+            .ancestors(innermost)
+            .find(|idx| self.groups[*idx].shutdown == GroupShutdown::Together)
+        {
+            Some(idx) => idx,
+            None => return,
+        };
+        console!(
+            "{}",
+            crate::fmt::line(format_args!(
+                "Process {} is stopped. Stopping {} group...",
+                tag,
+                crate::fmt::style(self.groups[idx].name).bold()
+            ))
+        );
+        self.send(idx, GroupCmd::Stop);
+    }
+
+    /// Whether any group of a process restarts as a whole, so a stopped process might be started again.
+    pub(crate) fn restarts(&self, group: Option<usize>) -> bool {
+        match group {
+            Some(innermost) => self
+                .ancestors(innermost)
+                .any(|idx| self.groups[idx].restart != Restart::Never),
+            None => false,
+        }
+    }
+
+    /// Sends a command to a group and its descendants.
+    fn send(&self, idx: usize, cmd: GroupCmd) {
+        for (child, group) in self.groups.iter().enumerate() {
+            if self.contains(idx, Some(child)) {
+                group.tx.send_modify(|(generation, current)| {
+                    *generation += 1;
+                    *current = cmd;
+                });
+            }
+        }
+    }
+
+    /// A group followed by its parents, innermost first.
+    fn ancestors(&self, idx: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(Some(idx), move |idx| self.groups[*idx].parent)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ()> {
+        self.lock.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Resolves once a group sends a new command. Never resolves for a process outside of groups.
+pub(crate) async fn changed(rx: &mut Option<GroupRx>) -> GroupCmd {
+    match rx {
+        Some(rx) => match rx.changed().await {
+            Ok(()) => rx.borrow().1,
+            Err(_) => std::future::pending().await,
+        },
+        None => std::future::pending().await,
+    }
+}
+
+fn visit<Loc>(
+    entries: Vec<PoolEntry<Loc, dyn Dependency>>,
+    parent: Option<usize>,
+    members: &mut Vec<Member<Loc>>,
+    proxies: &mut Vec<ProxyProcess>,
+    groups: &mut Vec<GroupCtl>,
+) {
+    for entry in entries {
+        match entry {
+            PoolEntry::Process(process) => members.push(Member::new(process, None, parent)),
+            PoolEntry::ProcessWithDep {
+                process,
+                dependency,
+            } => members.push(Member::new(process, Some(dependency), parent)),
+            PoolEntry::Service(service) => {
+                let service = *service;
+                members.push(Member {
+                    setup: service.setup,
+                    readiness: service.readiness.map(Arc::from),
+                    teardown: service.teardown,
+                    ..Member::new(service.process, service.dependency, parent)
+                })
+            }
+            PoolEntry::ProcessAfterCmd { process, cmd } => members.push(Member {
+                setup: Some(*cmd),
+                ..Member::new(process, None, parent)
+            }),
+            PoolEntry::Group(group) => {
+                let idx = groups.len();
+                groups.push(GroupCtl {
+                    name: group.name,
+                    parent,
+                    restart: group.restart,
+                    shutdown: group.shutdown,
+                    attempts: Mutex::new(group.backoff.start()),
+                    tx: watch::channel((0, GroupCmd::Run)).0,
+                });
+                self::visit(group.entries, Some(idx), members, proxies, groups);
+            }
+            PoolEntry::Proxy(proxy) => proxies.push(proxy),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{Env, Error, KillTimeout, Location};
+
+    struct Loc(PathBuf);
+
+    impl Location for Loc {
+        fn apex() -> Self {
+            Self(PathBuf::from("/"))
+        }
+
+        fn as_path(&self) -> &PathBuf {
+            &self.0
+        }
+    }
+
+    fn process(tag: &'static str) -> Process<Loc> {
+        let cmd = Cmd::new("true", Env::empty(), Loc::apex(), None);
+        Process::new(tag, cmd, KillTimeout::new(Duration::from_secs(1)))
+    }
+
+    /// stack (0): server
+    ///   backend (1), restarted on failure at most twice: api, worker
+    ///   frontend (2), shut down together: client
+    ///     assets (3): bundler
+    ///     docs (4): docs
+    fn stack() -> (Vec<Member<Loc>>, Groups) {
+        let pool: Vec<PoolEntry<Loc, dyn Dependency>> = vec![Group::new(
+            "stack",
+            [
+                PoolEntry::Process(process("server")),
+                Group::new("backend", [process("api"), process("worker")])
+                    .restart(Restart::OnFailure)
+                    .backoff(RetryPolicy::fixed(Restart::DELAY).max_attempts(2))
+                    .into(),
+                Group::new(
+                    "frontend",
+                    [
+                        PoolEntry::Process(process("client")),
+                        Group::new("assets", [process("bundler")]).into(),
+                        Group::new("docs", [process("docs")]).into(),
+                    ],
+                )
+                .shutdown(GroupShutdown::Together)
+                .into(),
+            ],
+        )
+        .into()];
+        let (members, proxies, groups) = Groups::flatten(pool);
+        assert!(proxies.is_empty());
+        (members, groups)
+    }
+
+    fn state(groups: &Groups, idx: usize) -> (u64, GroupCmd) {
+        *groups.groups[idx].tx.borrow()
+    }
+
+    fn failure() -> Result<ExitResult> {
+        Err(Error::ProcessDoesNotExist)
+    }
+
+    #[test]
+    fn flatten() {
+        let (members, groups) = stack();
+        let members = members
+            .iter()
+            .map(|x| (x.process.tag(), x.group))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            members,
+            [
+                ("server", Some(0)),
+                ("api", Some(1)),
+                ("worker", Some(1)),
+                ("client", Some(2)),
+                ("bundler", Some(3)),
+                ("docs", Some(4)),
+            ]
+        );
+        assert_eq!(groups.len(), 5);
+        assert_eq!(groups.name(3), "assets");
+        assert!(groups.contains(0, Some(3)));
+        assert!(groups.contains(2, Some(4)));
+        assert!(!groups.contains(1, Some(3)));
+        assert!(!groups.contains(0, None));
+        assert!(groups.restarts(Some(1)));
+        assert!(!groups.restarts(Some(3)));
+    }
+
+    #[test]
+    fn exited() {
+        let (_, groups) = stack();
+        let restart = GroupCmd::Restart(Restart::DELAY);
+
+        // Processes outside of restarting groups follow their own policies
+        assert_eq!(groups.exited(None, 0, "other", &failure()), None);
+        assert_eq!(groups.exited(Some(3), 0, "bundler", &failure()), None);
+
+        assert_eq!(groups.exited(Some(1), 0, "api", &failure()), Some(restart));
+        assert_eq!(state(&groups, 1), (1, restart));
+        // The worker spawned at the stale generation was stopped by the restart, so it just follows the group
+        assert_eq!(
+            groups.exited(Some(1), 0, "worker", &failure()),
+            Some(restart)
+        );
+        assert_eq!(state(&groups, 1), (1, restart));
+        // The restart doesn't affect the parent and the siblings
+        assert_eq!(state(&groups, 0), (0, GroupCmd::Run));
+        assert_eq!(state(&groups, 2), (0, GroupCmd::Run));
+
+        // The group is stopped once it reaches the max number of restarts
+        assert_eq!(
+            groups.exited(Some(1), 1, "api", &failure()),
+            Some(GroupCmd::Stop)
+        );
+        assert_eq!(state(&groups, 1), (2, GroupCmd::Stop));
+    }
+
+    #[test]
+    fn stopped() {
+        let (_, groups) = stack();
+
+        // Neither the backend nor the stack shut down together
+        groups.stopped(Some(1), 0, "api");
+        assert_eq!(state(&groups, 1), (0, GroupCmd::Run));
+
+        // The frontend is stopped along with its nested groups, but not its parent and siblings
+        groups.stopped(Some(3), 0, "bundler");
+        for idx in [2, 3, 4] {
+            assert_eq!(state(&groups, idx), (1, GroupCmd::Stop));
+        }
+        assert_eq!(state(&groups, 0), (0, GroupCmd::Run));
+        assert_eq!(state(&groups, 1), (0, GroupCmd::Run));
+
+        // Processes stopped by the group don't stop it again
+        groups.stopped(Some(4), 0, "docs");
+        groups.stopped(Some(2), 1, "client");
+        assert_eq!(state(&groups, 2), (1, GroupCmd::Stop));
+    }
+}
@@ -0,0 +1,658 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime},
+};
+
+use futures_core::Stream;
+use tokio::{
+    sync::{mpsc, watch},
+    task::JoinHandle,
+};
+
+use crate::{Error, ExitResult, Result, RunningProcess};
+
+/// A handle to a [`ProcessPool`](crate::ProcessPool) running in the background.
+/// See [`ProcessPool::start`](crate::ProcessPool::start).
+///
+/// ```ignore
+/// let pool = ProcessPool::start(pool![server::watch(), client::watch()], PoolOptions::default());
+///
+/// run_integration_tests().await;
+///
+/// for stats in pool.stats() {
+///     println!("{}: {} restarts", stats.tag, stats.restarts);
+/// }
+/// pool.shutdown().await?;
+/// ```
+pub struct PoolHandle {
+    state: Arc<PoolState>,
+    task: JoinHandle<Result<()>>,
+}
+
+impl PoolHandle {
+    pub(crate) fn new(state: Arc<PoolState>, task: JoinHandle<Result<()>>) -> Self {
+        Self { state, task }
+    }
+
+    /// Returns the statistics of the processes of the pool, in the order of the pool entries.
+    ///
+    /// It's cheap, so it can be polled to render the state of the processes, e.g. as a spinner per process
+    /// (see also [`set_console_writer`](crate::set_console_writer)):
+    ///
+    /// ```ignore
+    /// let bars = pool.stats().iter().map(|x| (x.tag, progress.add(ProgressBar::new_spinner()))).collect::<Vec<_>>();
+    /// loop {
+    ///     for (stats, (tag, bar)) in pool.stats().iter().zip(&bars) {
+    ///         bar.set_message(format!("{}: {}", tag, stats.status));
+    ///     }
+    ///     tokio::time::sleep(Duration::from_millis(100)).await;
+    /// }
+    /// ```
+    pub fn stats(&self) -> Vec<ProcessStats> {
+        self.state.stats()
+    }
+
+    /// Returns the statistics of a process by its tag.
+    pub fn process(&self, tag: &str) -> Option<ProcessStats> {
+        self.state.stats().into_iter().find(|x| x.tag == tag)
+    }
+
+    /// Returns a handle to a running process of the pool by its tag, e.g. to pause it.
+    pub fn process_handle(&self, tag: &str) -> Option<ProcessHandle> {
+        let idx = self.state.trackers().iter().position(|x| x.tag == tag)?;
+        Some(ProcessHandle {
+            state: self.state.clone(),
+            idx,
+        })
+    }
+
+    /// Returns the last exit of every process that has exited so far, by tag.
+    pub fn exits(&self) -> HashMap<&'static str, ProcessExit> {
+        self.state
+            .trackers()
+            .iter()
+            .filter_map(|x| x.last_exit.clone().map(|exit| (x.tag, exit)))
+            .collect()
+    }
+
+    /// Returns a stream of the exits of the processes of the pool, starting from the next one,
+    /// e.g. to alert on crashes of a specific process. The stream ends once the pool is shut down.
+    ///
+    /// ```ignore
+    /// let mut exits = pool.subscribe();
+    /// while let Some(exit) = exits.next().await {
+    ///     if exit.tag == "worker" && exit.status.is_failure() {
+    ///         alert(&exit).await;
+    ///     }
+    /// }
+    /// ```
+    pub fn subscribe(&self) -> ExitStream {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.state
+            .subscribers
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(tx);
+        ExitStream { rx }
+    }
+
+    /// Resolves once every process of the pool has started, i.e. its dependency is ready and it's spawned
+    /// (and its readiness check passed, for a [`Service`](crate::Service)), so the stack is usable, e.g. by integration tests. Fails with [`Error::PoolNotReady`](Error::PoolNotReady)
+    /// if a process is not going to start (e.g. its dependency errored) or the pool is shut down before that.
+    ///
+    /// ```ignore
+    /// let pool = ProcessPool::start(pool![server::watch(), client::watch()], PoolOptions::default());
+    /// pool.ready().await?;
+    ///
+    /// run_integration_tests().await;
+    /// ```
+    pub async fn ready(&self) -> Result<()> {
+        let mut rx = self.state.ready.subscribe();
+        let readiness = tokio::select! {
+            res = rx.wait_for(|x| x.pending == 0 || x.failure.is_some()) => match res {
+                Ok(readiness) => readiness.clone(),
+                Err(_) => return Err(Error::PoolNotReady { reason: "the pool is gone".to_string() }),
+            },
+            _ = self.state.shutting_down() => {
+                return Err(Error::PoolNotReady { reason: "the pool is shut down".to_string() })
+            }
+        };
+        match readiness.failure {
+            None => Ok(()),
+            Some(reason) => Err(Error::PoolNotReady { reason }),
+        }
+    }
+
+    /// Stops all the processes of the pool and waits until the pool is shut down.
+    /// Processes are asked to exit (via `SIGINT` on Unix) and killed if they don't exit within their timeouts.
+    pub async fn shutdown(self) -> Result<()> {
+        self.state.shutdown(Shutdown::Requested);
+        self.wait().await
+    }
+
+    /// Drains the pool: processes are not restarted and the ones waiting for their dependencies are not spawned,
+    /// while the running processes are given up to `deadline` to exit on their own, e.g. to finish in-flight jobs
+    /// before a deploy. The remaining processes are killed after the deadline.
+    pub async fn drain(self, deadline: Duration) -> Result<()> {
+        self.state.shutdown(Shutdown::Draining);
+        self.join(tokio::time::sleep(deadline), Shutdown::Forced)
+            .await
+    }
+
+    /// Waits until the pool is shut down, e.g. when a user presses `Ctrl+C`.
+    pub async fn wait(self) -> Result<()> {
+        self.wait_until(std::future::pending()).await
+    }
+
+    /// Waits until the pool is shut down, or shuts it down once `stop` resolves.
+    pub(crate) async fn wait_until(self, stop: impl Future<Output = ()>) -> Result<()> {
+        self.join(stop, Shutdown::Requested).await
+    }
+
+    async fn join(mut self, stop: impl Future<Output = ()>, reason: Shutdown) -> Result<()> {
+        let res = tokio::select! {
+            res = &mut self.task => res,
+            _ = stop => {
+                if reason == Shutdown::Forced && self.state.is_draining() {
+                    console!("⚠️  The pool is not drained in time. Killing the remaining processes.");
+                }
+                self.state.shutdown(reason);
+                (&mut self.task).await
+            }
+        };
+        match res {
+            Ok(res) => res,
+            Err(err) => Err(Error::IoError(std::io::Error::other(err))),
+        }
+    }
+}
+
+/// A handle to a process of a [`ProcessPool`](crate::ProcessPool) running in the background.
+/// See [`PoolHandle::process_handle`](PoolHandle::process_handle).
+///
+/// A paused process keeps its state (e.g. a warm cache of a watcher), but doesn't consume CPU,
+/// e.g. while benchmarks are running:
+///
+/// ```ignore
+/// let client = pool.process_handle("client").unwrap();
+/// client.pause()?;
+/// run_benchmarks().await;
+/// client.resume()?;
+/// ```
+///
+/// Pausing applies to the current run of a process: a restarted process runs as usual.
+/// A paused process is still stopped on shutdown of the pool, along with its descendants: they are resumed,
+/// asked to exit and killed if they are still running once the kill timeout of the process elapses.
+#[derive(Clone)]
+pub struct ProcessHandle {
+    state: Arc<PoolState>,
+    idx: usize,
+}
+
+impl ProcessHandle {
+    /// Tag of the process.
+    pub fn tag(&self) -> &'static str {
+        self.state.trackers()[self.idx].tag
+    }
+
+    /// Whether the process is paused at the moment.
+    pub fn is_paused(&self) -> bool {
+        self.state.trackers()[self.idx]
+            .paused
+            .load(Ordering::Relaxed)
+    }
+
+    /// Freezes the process along with its descendants (e.g. the commands run by its shell):
+    /// via `SIGSTOP` on Unix, or by suspending their threads on Windows.
+    /// Fails with [`Error::ProcessNotRunning`](Error::ProcessNotRunning) if it's not running at the moment.
+    pub fn pause(&self) -> Result<()> {
+        self.state.pause(self.idx, true)
+    }
+
+    /// Resumes the paused process along with its descendants: via `SIGCONT` on Unix, or by resuming their threads on Windows.
+    /// Fails with [`Error::ProcessNotRunning`](Error::ProcessNotRunning) if it's not running at the moment.
+    pub fn resume(&self) -> Result<()> {
+        self.state.pause(self.idx, false)
+    }
+}
+
+/// Statistics of a process of a [`ProcessPool`](crate::ProcessPool). See [`PoolHandle::stats`](PoolHandle::stats).
+///
+/// A process with a lot of restarts and a short uptime is likely flapping.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProcessStats {
+    /// Tag of a process.
+    pub tag: &'static str,
+    /// Whether a process is running at the moment.
+    pub running: bool,
+    /// Whether a process is paused at the moment. See [`ProcessHandle::pause`](ProcessHandle::pause).
+    pub paused: bool,
+    /// What a process is doing at the moment.
+    pub status: ProcessStatus,
+    /// Time of the last start of a process. `None` if it hasn't started yet (e.g. it's waiting for its dependency).
+    pub started_at: Option<SystemTime>,
+    /// Cumulative time a process has been running across all its (re)starts.
+    pub uptime: Duration,
+    /// Number of times a process has been restarted.
+    pub restarts: u32,
+    /// How a process exited last time. `None` if it hasn't exited yet.
+    pub last_exit: Option<Exit>,
+}
+
+/// What a process of a [`ProcessPool`](crate::ProcessPool) is doing at the moment. See [`ProcessStats`](ProcessStats).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProcessStatus {
+    /// A process hasn't started yet, e.g. it's waiting for its dependency.
+    Pending,
+    /// A process is spawned, but its readiness check hasn't passed yet. See [`Service`](crate::Service).
+    Starting,
+    /// A process is running.
+    Running,
+    /// A process is paused. See [`ProcessHandle::pause`](ProcessHandle::pause).
+    Paused,
+    /// A process has exited, and it's not running again yet (if ever).
+    Exited,
+}
+
+impl std::fmt::Display for ProcessStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::Starting => write!(f, "starting"),
+            Self::Running => write!(f, "running"),
+            Self::Paused => write!(f, "paused"),
+            Self::Exited => write!(f, "exited"),
+        }
+    }
+}
+
+/// An exit of a process of a [`ProcessPool`](crate::ProcessPool).
+/// See [`PoolHandle::exits`](PoolHandle::exits) and [`PoolHandle::subscribe`](PoolHandle::subscribe).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProcessExit {
+    /// Tag of a process.
+    pub tag: &'static str,
+    /// How a process exited.
+    pub status: Exit,
+    /// How long a process was running before it exited. Zero if it failed to spawn.
+    pub duration: Duration,
+    /// Whether a process was killed by the pool, since it didn't exit within its timeout.
+    pub killed: bool,
+}
+
+/// A stream of exits of the processes of a pool. See [`PoolHandle::subscribe`](PoolHandle::subscribe).
+pub struct ExitStream {
+    rx: mpsc::UnboundedReceiver<ProcessExit>,
+}
+
+impl Stream for ExitStream {
+    type Item = ProcessExit;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/// How a process of a [`ProcessPool`](crate::ProcessPool) exited.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Exit {
+    /// A process exited on its own with the code. The code might be absent on Unix systems when a process
+    /// was terminated by a signal.
+    Code(Option<i32>),
+    /// A process exited on shutdown of the pool.
+    Interrupted,
+    /// A process didn't exit on shutdown of the pool within its timeout and was killed.
+    Killed,
+    /// A process couldn't be spawned or waited for.
+    Failed(String),
+}
+
+impl Exit {
+    pub(crate) fn from_result(res: &Result<ExitResult>) -> Self {
+        match res {
+            Ok(ExitResult::Output(output)) => Self::Code(output.status.code()),
+            Ok(ExitResult::Interrupted) => Self::Interrupted,
+            Ok(ExitResult::Killed { pid: _ }) => Self::Killed,
+            Err(Error::NonZeroExitCode { code, output: _ }) => Self::Code(*code),
+            Err(err) => Self::Failed(err.to_string()),
+        }
+    }
+
+    /// Exit of a one-off command, which can't be interrupted or killed by a pool.
+    #[cfg(any(feature = "notify", feature = "otel"))]
+    pub(crate) fn from_cmd_result<T>(res: &Result<T>) -> Self {
+        match res {
+            Ok(_) => Self::Code(Some(0)),
+            Err(Error::NonZeroExitCode { code, output: _ }) => Self::Code(*code),
+            Err(err) => Self::Failed(err.to_string()),
+        }
+    }
+}
+
+impl Exit {
+    /// Returns `true` if a process failed on its own, i.e. not on shutdown of the pool.
+    pub fn is_failure(&self) -> bool {
+        match self {
+            Self::Code(Some(0)) | Self::Interrupted | Self::Killed => false,
+            Self::Code(_) | Self::Failed(_) => true,
+        }
+    }
+}
+
+impl std::fmt::Display for Exit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Code(Some(code)) => write!(f, "exited with code {}", code),
+            Self::Code(None) => write!(f, "terminated by signal"),
+            Self::Interrupted => write!(f, "interrupted"),
+            Self::Killed => write!(f, "killed due to timeout"),
+            Self::Failed(err) => write!(f, "failed: {}", err),
+        }
+    }
+}
+
+/// Shutdown state of a pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Shutdown {
+    /// The pool is running.
+    No,
+    /// A user pressed `Ctrl+C`, so the processes received `SIGINT` from the terminal.
+    CtrlC,
+    /// Shutdown is requested via a [`PoolHandle`](PoolHandle), so the processes must be stopped by the pool.
+    Requested,
+    /// The pool is drained via a [`PoolHandle`](PoolHandle): nothing is (re)started, and the processes exit on their own.
+    Draining,
+    /// The pool wasn't drained in time, so the remaining processes must be killed by the pool.
+    Forced,
+}
+
+/// State of a running pool shared between the pool, its processes and the handle.
+pub(crate) struct PoolState {
+    stats: Mutex<Vec<Tracker>>,
+    shutdown: watch::Sender<Shutdown>,
+    failure: Mutex<Option<Error>>,
+    ready: watch::Sender<Readiness>,
+    subscribers: Mutex<Vec<mpsc::UnboundedSender<ProcessExit>>>,
+}
+
+/// Progress of the first start of the processes of a pool. See [`PoolHandle::ready`](PoolHandle::ready).
+#[derive(Clone)]
+struct Readiness {
+    /// Number of processes that haven't started yet.
+    pending: usize,
+    /// Why the first process that is not going to start didn't.
+    failure: Option<String>,
+}
+
+struct Tracker {
+    tag: &'static str,
+    started: Option<(Instant, SystemTime)>,
+    running: bool,
+    pid: Option<u32>,
+    /// Whether a process leads its own process group.
+    group: bool,
+    /// Shared with the running process, so it's resumed on teardown. See [`RunningProcess::paused`](RunningProcess::paused).
+    paused: Arc<AtomicBool>,
+    uptime: Duration,
+    starts: u32,
+    last_exit: Option<ProcessExit>,
+    /// Whether a process counts as started only once its readiness check passes. See [`Service`](crate::Service).
+    checked: bool,
+    ready: bool,
+}
+
+impl Tracker {
+    fn status(&self) -> ProcessStatus {
+        match (
+            self.running,
+            self.paused.load(Ordering::Relaxed),
+            self.ready,
+        ) {
+            (true, true, _) => ProcessStatus::Paused,
+            (true, false, false) => ProcessStatus::Starting,
+            (true, false, true) => ProcessStatus::Running,
+            (false, _, _) if self.last_exit.is_some() => ProcessStatus::Exited,
+            (false, _, _) => ProcessStatus::Pending,
+        }
+    }
+}
+
+impl PoolState {
+    /// Constructs a state of processes by their tags, along with whether a process has a readiness check.
+    pub(crate) fn new(processes: impl IntoIterator<Item = (&'static str, bool)>) -> Self {
+        let stats = processes
+            .into_iter()
+            .map(|(tag, checked)| Tracker {
+                tag,
+                started: None,
+                running: false,
+                pid: None,
+                group: false,
+                paused: Default::default(),
+                uptime: Duration::ZERO,
+                starts: 0,
+                last_exit: None,
+                checked,
+                ready: false,
+            })
+            .collect::<Vec<_>>();
+        let readiness = Readiness {
+            pending: stats.len(),
+            failure: None,
+        };
+        Self {
+            stats: Mutex::new(stats),
+            shutdown: watch::channel(Shutdown::No).0,
+            failure: Mutex::new(None),
+            ready: watch::channel(readiness).0,
+            subscribers: Mutex::new(vec![]),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> Vec<ProcessStats> {
+        let now = Instant::now();
+        self.trackers()
+            .iter()
+            .map(|x| ProcessStats {
+                tag: x.tag,
+                running: x.running,
+                paused: x.paused.load(Ordering::Relaxed),
+                status: x.status(),
+                started_at: x.started.map(|(_, at)| at),
+                uptime: match (x.running, x.started) {
+                    (true, Some((started, _))) => x.uptime + now.duration_since(started),
+                    (_, _) => x.uptime,
+                },
+                restarts: x.starts.saturating_sub(1),
+                last_exit: x.last_exit.as_ref().map(|exit| exit.status.clone()),
+            })
+            .collect()
+    }
+
+    pub(crate) fn last_exit(&self, idx: usize) -> Option<Exit> {
+        self.trackers()[idx]
+            .last_exit
+            .as_ref()
+            .map(|exit| exit.status.clone())
+    }
+
+    pub(crate) fn started(&self, idx: usize, running: &RunningProcess) {
+        let mut trackers = self.trackers();
+        let tracker = &mut trackers[idx];
+        tracker.started = Some((Instant::now(), SystemTime::now()));
+        tracker.running = true;
+        tracker.pid = running.as_child().id();
+        tracker.group = running.detached;
+        tracker.paused = running.paused.clone();
+        tracker.starts += 1;
+        if !tracker.checked {
+            self.mark_ready(tracker);
+        }
+    }
+
+    /// Marks a process with a readiness check as started, once the check passes.
+    pub(crate) fn passed_check(&self, idx: usize) {
+        self.mark_ready(&mut self.trackers()[idx]);
+    }
+
+    /// Marks the pool as never getting ready, if the readiness check of a process failed before it ever passed.
+    pub(crate) fn failed_check(&self, idx: usize, reason: impl std::fmt::Display) {
+        let trackers = self.trackers();
+        let tracker = &trackers[idx];
+        if !tracker.ready {
+            self.not_started(tracker.tag, reason);
+        }
+    }
+
+    fn mark_ready(&self, tracker: &mut Tracker) {
+        if !tracker.ready {
+            tracker.ready = true;
+            self.ready.send_modify(|x| x.pending -= 1);
+        }
+    }
+
+    pub(crate) fn exited(&self, idx: usize, exit: Exit) {
+        let mut trackers = self.trackers();
+        let tracker = &mut trackers[idx];
+        let duration = match (tracker.running, tracker.started) {
+            (true, Some((started, _))) => started.elapsed(),
+            (_, _) => Duration::ZERO,
+        };
+        tracker.uptime += duration;
+        if tracker.starts == 0 {
+            self.not_started(tracker.tag, &exit);
+        }
+        tracker.running = false;
+        tracker.pid = None;
+        tracker.paused = Default::default();
+        let exit = ProcessExit {
+            tag: tracker.tag,
+            killed: exit == Exit::Killed,
+            status: exit,
+            duration,
+        };
+        tracker.last_exit = Some(exit.clone());
+        drop(trackers);
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .retain(|tx| tx.send(exit.clone()).is_ok());
+    }
+
+    /// Pauses or resumes a running process.
+    fn pause(&self, idx: usize, pause: bool) -> Result<()> {
+        let mut trackers = self.trackers();
+        let tracker = &mut trackers[idx];
+        let pid = match (tracker.running, tracker.pid) {
+            (true, Some(pid)) => pid,
+            (_, _) => {
+                return Err(Error::ProcessNotRunning {
+                    tag: tracker.tag.to_owned(),
+                })
+            }
+        };
+        if pause {
+            // Marked first, so a teardown racing with the pause resumes the process
+            tracker.paused.store(true, Ordering::Relaxed);
+            let res = RunningProcess::pause(pid, tracker.group);
+            if res.is_err() {
+                tracker.paused.store(false, Ordering::Relaxed);
+            }
+            res
+        } else {
+            RunningProcess::resume(pid, tracker.group)?;
+            tracker.paused.store(false, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    /// Ends the exit streams of the subscribers, since the pool is shut down.
+    pub(crate) fn close_exits(&self) {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clear();
+    }
+
+    /// Marks the pool as never getting ready, since a process is not going to start. Only the first reason is kept.
+    pub(crate) fn not_started(&self, tag: &str, reason: impl std::fmt::Display) {
+        self.ready.send_if_modified(|x| match x.failure {
+            Some(_) => false,
+            None => {
+                x.failure = Some(format!("{} is not started: {}", tag, reason));
+                true
+            }
+        });
+    }
+
+    pub(crate) fn shutdown(&self, reason: Shutdown) {
+        self.shutdown.send_if_modified(|state| match (*state, reason) {
+            (Shutdown::No, _)
+            | (Shutdown::Draining, Shutdown::CtrlC | Shutdown::Requested | Shutdown::Forced) => {
+                *state = reason;
+                true
+            }
+            (Shutdown::CtrlC | Shutdown::Requested | Shutdown::Draining | Shutdown::Forced, _) => {
+                false
+            }
+        });
+    }
+
+    /// Shuts the pool down due to a failure of a process. Only the first failure is kept.
+    pub(crate) fn fail(&self, tag: &str, reason: String) {
+        let mut failure = self.failure.lock().unwrap_or_else(|err| err.into_inner());
+        if failure.is_none() && !self.is_shutting_down() {
+            *failure = Some(Error::ProcessFailed {
+                tag: tag.to_owned(),
+                reason,
+            });
+            self.shutdown(Shutdown::Requested);
+        }
+    }
+
+    pub(crate) fn take_failure(&self) -> Option<Error> {
+        self.failure
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take()
+    }
+
+    pub(crate) fn is_shutting_down(&self) -> bool {
+        *self.shutdown.borrow() != Shutdown::No
+    }
+
+    pub(crate) fn is_draining(&self) -> bool {
+        *self.shutdown.borrow() == Shutdown::Draining
+    }
+
+    /// Resolves once shutdown of the pool starts for any reason.
+    pub(crate) async fn shutting_down(&self) {
+        let mut rx = self.shutdown.subscribe();
+        let _ = rx.wait_for(|x| *x != Shutdown::No).await;
+    }
+
+    /// Resolves once shutdown is requested via a handle, so the processes must be stopped by the pool.
+    pub(crate) async fn shutdown_requested(&self) {
+        let mut rx = self.shutdown.subscribe();
+        let _ = rx
+            .wait_for(|x| matches!(x, Shutdown::Requested | Shutdown::Forced))
+            .await;
+    }
+
+    /// Resolves once the pool wasn't drained in time, so the processes must be killed right away.
+    pub(crate) async fn forced(&self) {
+        let mut rx = self.shutdown.subscribe();
+        let _ = rx.wait_for(|x| *x == Shutdown::Forced).await;
+    }
+
+    fn trackers(&self) -> MutexGuard<'_, Vec<Tracker>> {
+        self.stats.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
@@ -0,0 +1,161 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use crate::{Cmd, Error, Location, Result};
+
+type HookFuture = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+/// An action that runs before or after a task of a [`Registry`](crate::Registry) or a [`ProcessPool`](crate::ProcessPool).
+/// See [`Hooks`](Hooks).
+pub struct Hook(Box<dyn Fn() -> HookFuture + Send + Sync>);
+
+impl Hook {
+    /// Constructs a hook that runs a command via [`Cmd::run`](Cmd::run).
+    pub fn cmd<Loc>(cmd: Cmd<Loc>) -> Self
+    where
+        Loc: Location + 'static,
+    {
+        let cmd = Arc::new(cmd);
+        Self(Box::new(move || {
+            let cmd = cmd.clone();
+            Box::pin(async move { cmd.run().await })
+        }))
+    }
+
+    /// Constructs a hook that runs an async function.
+    pub fn func<F, Fut>(f: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        Self(Box::new(move || Box::pin(f())))
+    }
+
+    async fn run(&self) -> Result<()> {
+        (self.0)().await
+    }
+}
+
+/// Setup and teardown hooks around a task of a [`Registry`](crate::Registry) or a [`ProcessPool`](crate::ProcessPool).
+///
+/// - `before` hooks run in order before a task. If one of them fails, the task is not run.
+/// - `after` hooks run in order after a task, whether it succeeded or failed.
+/// - `after_failure` hooks run in order after a task (or one of the `before` hooks) failed, before the `after` hooks.
+///
+/// All the `after` and `after_failure` hooks are run even if some of them fail.
+/// An error of a task is returned along with the errors of these hooks, see [`Error::Errors`](crate::Error::Errors).
+///
+/// ```ignore
+/// let opts = PoolOptions {
+///     hooks: Hooks::default()
+///         .before(Hook::cmd(docker::compose_up()))
+///         .after(Hook::cmd(docker::compose_down()))
+///         .after(Hook::func(|| async { Ok(std::fs::remove_dir_all(Loc::tmp().as_path())?) })),
+///     ..Default::default()
+/// };
+///
+/// ProcessPool::run_with_options(vec![server, client], opts).await
+/// ```
+#[derive(Default)]
+pub struct Hooks {
+    /// Hooks that run before a task.
+    pub before: Vec<Hook>,
+    /// Hooks that run after a task, whether it succeeded or failed.
+    pub after: Vec<Hook>,
+    /// Hooks that run after a task failed.
+    pub after_failure: Vec<Hook>,
+}
+
+impl Hooks {
+    /// Adds a hook that runs before a task.
+    pub fn before(mut self, hook: Hook) -> Self {
+        self.before.push(hook);
+        self
+    }
+
+    /// Adds a hook that runs after a task, whether it succeeded or failed.
+    pub fn after(mut self, hook: Hook) -> Self {
+        self.after.push(hook);
+        self
+    }
+
+    /// Adds a hook that runs after a task failed.
+    pub fn after_failure(mut self, hook: Hook) -> Self {
+        self.after_failure.push(hook);
+        self
+    }
+
+    /// Runs a task with the hooks around it.
+    pub(crate) async fn around(&self, task: impl Future<Output = Result<()>>) -> Result<()> {
+        let mut res = Ok(());
+        for hook in &self.before {
+            res = hook.run().await;
+            if res.is_err() {
+                break;
+            }
+        }
+        if res.is_ok() {
+            res = task.await;
+        }
+
+        let mut teardown = vec![];
+        if res.is_err() {
+            teardown.extend(&self.after_failure);
+        }
+        teardown.extend(&self.after);
+        let mut results = vec![res];
+        for hook in teardown {
+            results.push(hook.run().await);
+        }
+        Error::collect(results).map(drop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    fn hook(log: &Arc<Mutex<Vec<&'static str>>>, name: &'static str, ok: bool) -> Hook {
+        let log = log.clone();
+        Hook::func(move || {
+            log.lock().unwrap().push(name);
+            async move {
+                match ok {
+                    true => Ok(()),
+                    false => Err(Error::ProcessDoesNotExist),
+                }
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn around() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let hooks = Hooks::default()
+            .before(hook(&log, "before", true))
+            .after_failure(hook(&log, "after_failure", true))
+            .after(hook(&log, "after", true));
+        hooks.around(async { Ok(()) }).await.unwrap();
+        assert_eq!(*log.lock().unwrap(), ["before", "after"]);
+
+        // A failed `before` hook skips the task, while the teardown hooks run anyway and their errors are collected
+        let log = Arc::new(Mutex::new(vec![]));
+        let hooks = Hooks::default()
+            .before(hook(&log, "before", false))
+            .before(hook(&log, "skipped", true))
+            .after_failure(hook(&log, "after_failure", false))
+            .after(hook(&log, "after", true));
+        let res = hooks
+            .around(async {
+                log.lock().unwrap().push("task");
+                Ok(())
+            })
+            .await;
+        assert_eq!(*log.lock().unwrap(), ["before", "after_failure", "after"]);
+        match res {
+            Err(Error::Errors { errors, total }) => assert_eq!((errors.len(), total), (2, 3)),
+            res => panic!("Unexpected result: {:?}", res),
+        }
+    }
+}
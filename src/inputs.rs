@@ -0,0 +1,192 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Defines how a [`Task`](crate::Task) with declared inputs and outputs decides whether its outputs are up to date.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Freshness {
+    /// Outputs are up to date when all of them exist and are newer than every input.
+    #[default]
+    Mtime,
+    /// Outputs are up to date when all of them exist and the contents of the inputs didn't change since the last
+    /// successful run. The hash of the inputs is stored in the state directory of a [`Registry`](crate::Registry).
+    Hash,
+}
+
+/// Declared inputs and outputs of a task.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Inputs {
+    pub(crate) inputs: Vec<String>,
+    pub(crate) outputs: Vec<PathBuf>,
+    pub(crate) freshness: Freshness,
+}
+
+impl Inputs {
+    pub(crate) fn is_declared(&self) -> bool {
+        !self.inputs.is_empty() || !self.outputs.is_empty()
+    }
+
+    /// Returns `true` if the outputs are up to date. `stamp` is a file with the hash of the inputs.
+    pub(crate) fn is_fresh(&self, stamp: &Path) -> io::Result<bool> {
+        let mut oldest_output = None;
+        for output in &self.outputs {
+            match fs::metadata(output) {
+                Ok(meta) => {
+                    let mtime = meta.modified()?;
+                    oldest_output = Some(oldest_output.map_or(mtime, |x: SystemTime| x.min(mtime)));
+                }
+                Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+                Err(err) => return Err(err),
+            }
+        }
+
+        match self.freshness {
+            Freshness::Mtime => {
+                let oldest_output = match oldest_output {
+                    Some(mtime) => mtime,
+                    // Without outputs, there is nothing to compare inputs with
+                    None => return Ok(false),
+                };
+                for input in self.files()? {
+                    if fs::metadata(input)?.modified()? > oldest_output {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            Freshness::Hash => match fs::read_to_string(stamp) {
+                Ok(hash) => Ok(hash == self.hash()?),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+                Err(err) => Err(err),
+            },
+        }
+    }
+
+    /// Records the state of the inputs after a successful run.
+    pub(crate) fn commit(&self, stamp: &Path) -> io::Result<()> {
+        match self.freshness {
+            Freshness::Mtime => Ok(()),
+            Freshness::Hash => {
+                if let Some(dir) = stamp.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(stamp, self.hash()?)
+            }
+        }
+    }
+
+    fn files(&self) -> io::Result<Vec<PathBuf>> {
+        self::files(&self.inputs)
+    }
+
+    /// FNV-1a hash of the paths and the contents of the input files. Unlike `std` hashers, it is stable across builds.
+    fn hash(&self) -> io::Result<String> {
+        const OFFSET: u64 = 0xcbf29ce484222325;
+        const PRIME: u64 = 0x100000001b3;
+
+        let mut hash = OFFSET;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(PRIME);
+            }
+        };
+        for file in self.files()? {
+            write(file.to_string_lossy().as_bytes());
+            write(&[0]);
+            write(&fs::read(&file)?);
+            write(&[0]);
+        }
+        Ok(format!("{:016x}", hash))
+    }
+}
+
+/// Files that match the globs, sorted and de-duplicated.
+pub(crate) fn files(globs: &[String]) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for pattern in globs {
+        let paths =
+            glob::glob(pattern).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        for path in paths {
+            let path = path.map_err(io::Error::from)?;
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// State of the files that match the globs. Two snapshots differ if a file was added, removed or modified.
+#[derive(PartialEq, Eq)]
+pub(crate) struct Snapshot(Vec<(PathBuf, Option<SystemTime>, u64)>);
+
+impl Snapshot {
+    pub(crate) fn take(globs: &[String]) -> io::Result<Self> {
+        let files = self::files(globs)?;
+        let mut state = Vec::with_capacity(files.len());
+        for file in files {
+            // A file might be removed in between
+            match fs::metadata(&file) {
+                Ok(meta) => state.push((file, meta.modified().ok(), meta.len())),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(Self(state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn freshness() {
+        let dir = std::env::temp_dir().join(format!("steward-inputs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (input, output, stamp) = (dir.join("in.txt"), dir.join("out.txt"), dir.join("stamp"));
+        fs::write(&input, "a").unwrap();
+        let mut inputs = Inputs {
+            inputs: vec![dir.join("*.txt").display().to_string()],
+            outputs: vec![output.clone()],
+            freshness: Freshness::Mtime,
+        };
+        assert!(inputs.is_declared());
+        // A missing output is never fresh
+        assert!(!inputs.is_fresh(&stamp).unwrap());
+
+        // The output itself matches the glob, so its mtime is compared with itself
+        fs::write(&output, "b").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&output)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        assert!(inputs.is_fresh(&stamp).unwrap());
+        fs::write(&input, "c").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&input)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(120))
+            .unwrap();
+        assert!(!inputs.is_fresh(&stamp).unwrap());
+
+        inputs.freshness = Freshness::Hash;
+        assert!(!inputs.is_fresh(&stamp).unwrap());
+        inputs.commit(&stamp).unwrap();
+        assert!(inputs.is_fresh(&stamp).unwrap());
+        fs::write(&input, "d").unwrap();
+        assert!(!inputs.is_fresh(&stamp).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
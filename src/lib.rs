@@ -8,7 +8,8 @@
 //! It's like [`foreman`](https://github.com/ddollar/foreman) but more low-level, with Rust API
 //! and more flexibility & features.
 //!
-//! Works great with [`clap`](https://github.com/clap-rs/clap)!
+//! Works great with [`clap`](https://github.com/clap-rs/clap)! With `clap` feature enabled,
+//! tasks of a [`Registry`](Registry) can be turned into clap subcommands.
 //!
 //! ## Examples
 //!
@@ -87,6 +88,13 @@
 //! ### Async runtimes
 //! Tokio only.
 
+/// Prints a line of steward output like `eprintln!`, but via the writer set by [`set_console_writer`](crate::set_console_writer).
+macro_rules! console {
+    ($($arg:tt)*) => {
+        $crate::fmt::write_line(format_args!($($arg)*))
+    };
+}
+
 /// Base building block of the crate.
 ///
 /// ```ignore
@@ -151,7 +159,8 @@ pub mod process;
 /// ```
 ///
 /// You can use provided [`TcpService`](crate::TcpService), [`HttpService`](crate::HttpService),
-/// and [`FsEntry`](crate::FsEntry). Or implement your own
+/// [`WsDep`](crate::WsDep), [`FsEntry`](crate::FsEntry), [`GlobDep`](crate::GlobDep), [`FsStableDep`](crate::FsStableDep),
+/// and [`BinDep`](crate::BinDep). Or implement your own
 /// (you would need [`async_trait`](https://docs.rs/async-trait/latest/async_trait/)).
 ///
 /// ## Process pool
@@ -169,18 +178,19 @@ pub mod process;
 ///          PoolEntry::Process(server::watch()),
 ///          PoolEntry::ProcessWithDep {
 ///              process: client::watch(),
-///              dependency: Box::new(HttpService {
-///                  tag: "server".to_string(),
-///                  addr: format!(
-///                      "http://{host}:{port}",
-///                      host = Config::SERVER_HOST(),
-///                      port = Config::SERVER_PORT()
+///              dependency: Box::new(
+///                  HttpService::new(
+///                      "server",
+///                      Config::SERVER_HOST(),
+///                      Config::SERVER_PORT(),
+///                      "/",
+///                      false,
+///                      HttpMethod::GET,
+///                      Duration::from_secs(30),
 ///                  )
-///                  .parse()
-///                  .unwrap(),
-///                  method: HttpMethod::GET,
-///                  timeout: Duration::from_secs(30),
-///              }),
+///                  .unwrap()
+///                  .hint("is the server running? Try `cargo run -p server`"),
+///              ),
 ///          },
 ///      ])
 ///      .await
@@ -193,23 +203,104 @@ pub mod env;
 pub mod fs;
 /// Network related types.
 pub mod net;
+/// Cleanup of processes that are not managed by steward. See [`os::terminate`](os::terminate).
+pub mod os;
+/// Named tasks of a project. See [`Registry`](Registry).
+pub mod registry;
 /// [`Result`](Result) and [`Error`](Error) types of this crate.
 pub mod result;
 
+mod backend;
+mod budget;
+#[cfg(all(target_os = "linux", feature = "cgroup"))]
+mod cgroup;
+mod checkpoint;
+mod crashes;
+#[cfg(feature = "notify")]
+mod desktop;
+mod duration;
+mod executor;
 #[macro_use]
 mod fmt;
 mod fun;
+mod group;
+mod handle;
+mod hook;
+mod inputs;
 mod loc;
+mod memory;
+mod notify;
+#[cfg(feature = "otel")]
+mod otel;
+mod pidfile;
+mod proxy;
+#[cfg(target_os = "linux")]
+mod reaper;
+mod retry;
+mod route;
+#[cfg(target_os = "linux")]
+mod sandbox;
+mod script;
+mod service;
+mod stagger;
+mod stream;
+#[cfg(all(unix, feature = "syslog"))]
+mod syslog;
 
-pub use cmd::{Cmd, KillTimeout, SpawnOptions};
+pub use backend::Backend;
+pub use budget::Budget;
+#[cfg(all(target_os = "linux", feature = "cgroup"))]
+pub use cgroup::CgroupLimits;
+pub use checkpoint::Checkpoint;
+#[cfg(unix)]
+pub use cmd::RunAs;
+pub use cmd::{
+    set_concurrency_limit, set_default_kill_timeout, shquote, Cmd, CmdOutput, KillTimeout,
+    ProcessGroup, Shell, SpawnOptions, StopSignal,
+};
+pub use crashes::CrashLoop;
 pub use dep::{Dependency, DependencyWaitError};
-pub use env::Env;
-pub use fmt::print;
-pub use fs::FsEntry;
+pub use duration::parse_duration;
+#[cfg(feature = "json")]
+pub use env::JsonFile;
+pub use env::{DotenvFile, Env, EnvSource, ParentEnv};
+pub use executor::{with_executor, Executor, Invocation, MockExecutor, OutputMode, SystemExecutor};
+pub use fmt::{
+    print, set_console_writer, ColorMode, ConsoleWriter, HeadlineOrder, LongLines, PoolFormat,
+    Theme,
+};
+pub use fs::{BinDep, FsEntry, FsStableDep, GlobDep};
 pub use fun::{run, run_mut, run_once};
+pub use group::{Group, GroupShutdown};
+pub use handle::{
+    Exit, ExitStream, PoolHandle, ProcessExit, ProcessHandle, ProcessStats, ProcessStatus,
+};
+pub use hook::{Hook, Hooks};
+pub use inputs::Freshness;
 pub use loc::Location;
-pub use net::{HttpMethod, HttpService, TcpService};
-pub use process::{PoolEntry, Process, ProcessPool, RunningProcess};
+pub use net::{free_port, HttpMethod, HttpService, Ports, Redirects, TcpService, WsDep};
+pub use notify::{PoolEvent, Webhook};
+#[cfg(feature = "otel")]
+pub use otel::Otlp;
+pub use process::{
+    DepFailure, LazyCmd, PoolEntry, PoolOptions, Process, ProcessCmd, ProcessPool, Restart,
+    RunningProcess, Stdin,
+};
+pub use proxy::ProxyProcess;
+#[cfg(target_os = "linux")]
+pub use reaper::enable_subreaper;
+pub use regex::Regex;
+pub use registry::{CompletionShell, Registry, Task, TaskArgs};
 pub use result::{Error, Result};
+pub use retry::{Backoff, RetryPolicy};
+pub use route::{LineCallback, Route};
+#[cfg(target_os = "linux")]
+pub use sandbox::Sandbox;
+pub use script::{Script, ScriptDialect};
+pub use service::Service;
+pub use stagger::Stagger;
+pub use stream::{OutputLine, OutputStream};
+#[cfg(all(unix, feature = "syslog"))]
+pub use syslog::LogSink;
 
 pub(crate) use process::ExitResult;
@@ -1,4 +1,7 @@
-use std::path::PathBuf;
+use std::{
+    io,
+    path::{Component, Path, PathBuf},
+};
 
 /// A location of file or directory of a project.
 ///
@@ -28,4 +31,97 @@ pub trait Location: Sized + Send + Sync {
         };
         path.display().to_string()
     }
+
+    /// Returns a path within the location, e.g. `Loc::client().join("package.json")`.
+    fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.as_path().join(path)
+    }
+
+    /// Returns `true` if the location exists.
+    fn exists(&self) -> bool {
+        self.as_path().exists()
+    }
+
+    /// Returns `true` if the location exists and is a directory.
+    fn is_dir(&self) -> bool {
+        self.as_path().is_dir()
+    }
+
+    /// Returns paths within the location that match a glob pattern, e.g. `dist/*.js`, sorted.
+    /// Special characters of the location itself are not interpreted.
+    fn glob(&self, pattern: &str) -> io::Result<Vec<PathBuf>> {
+        let base = self.as_path().to_string_lossy();
+        let pattern = Path::new(&glob::Pattern::escape(&base)).join(pattern);
+        let paths = glob::glob(&pattern.to_string_lossy())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let mut res = paths
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(io::Error::from)?;
+        res.sort();
+        Ok(res)
+    }
+
+    /// Returns a path of the location relative to another location, e.g. `../client/dist`.
+    /// Returns `None` if it can't be expressed, e.g. when the locations are on different drives.
+    fn relative_to<L: Location>(&self, other: &L) -> Option<PathBuf> {
+        self::relative(self.as_path(), other.as_path())
+    }
+}
+
+fn relative(path: &Path, base: &Path) -> Option<PathBuf> {
+    let mut path = path.components().filter(|x| *x != Component::CurDir);
+    let mut base = base.components().filter(|x| *x != Component::CurDir);
+    let mut res = PathBuf::new();
+    loop {
+        match (path.next(), base.next()) {
+            (None, None) => break,
+            (Some(x), None) => {
+                res.push(x);
+                res.extend(path.by_ref());
+                break;
+            }
+            (None, Some(_)) => {
+                res.push("..");
+                res.extend(base.by_ref().map(|_| ".."));
+                break;
+            }
+            (Some(x), Some(y)) if x == y => (),
+            (Some(x), Some(y)) => {
+                match y {
+                    Component::Normal(_) => res.push(".."),
+                    // Different roots or prefixes, or `..` in the base
+                    Component::Prefix(_)
+                    | Component::RootDir
+                    | Component::CurDir
+                    | Component::ParentDir => return None,
+                }
+                res.extend(base.by_ref().map(|_| ".."));
+                match x {
+                    Component::Normal(_) | Component::ParentDir => res.push(x),
+                    Component::Prefix(_) | Component::RootDir | Component::CurDir => return None,
+                }
+                res.extend(path.by_ref());
+                break;
+            }
+        }
+    }
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_path() {
+        let rel = |path: &str, base: &str| relative(Path::new(path), Path::new(base));
+        assert_eq!(rel("/app/client/dist", "/app"), Some("client/dist".into()));
+        assert_eq!(rel("/app", "/app/client/dist"), Some("../..".into()));
+        assert_eq!(
+            rel("/app/client/dist", "/app/server"),
+            Some("../client/dist".into())
+        );
+        assert_eq!(rel("/app", "/app"), Some("".into()));
+        assert_eq!(rel("app", "/app"), None);
+    }
 }
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use tokio::{task, time};
+
+/// How often the memory usage of a process is sampled.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Resolves with the resident set size of a process (including its descendants, e.g. when it's run via a shell)
+/// once it exceeds `max` bytes. Never resolves if there's no limit or the usage can't be sampled on this platform.
+pub(crate) async fn exceeded(pid: Option<u32>, max: Option<u64>) -> u64 {
+    let (pid, max) = match (pid, max) {
+        (Some(pid), Some(max)) => (pid, max),
+        (_, _) => return std::future::pending().await,
+    };
+    loop {
+        time::sleep(SAMPLE_INTERVAL).await;
+        match task::spawn_blocking(move || self::rss(pid)).await {
+            Ok(Some(rss)) if rss > max => return rss,
+            Ok(Some(_)) => (),
+            Ok(None) | Err(_) => return std::future::pending().await,
+        }
+    }
+}
+
+/// Resident set size of a process tree in bytes.
+#[cfg(target_os = "linux")]
+fn rss(pid: u32) -> Option<u64> {
+    use std::fs;
+
+    let procs = crate::os::processes()?;
+    let mut total = 0;
+    for pid in crate::os::tree(pid, &procs) {
+        // A process might be gone by now
+        let status = match fs::read_to_string(format!("/proc/{}/status", pid)) {
+            Ok(status) => status,
+            Err(_) => continue,
+        };
+        let kb = status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))
+            .and_then(|rss| rss.trim().trim_end_matches("kB").trim().parse::<u64>().ok());
+        total += kb.unwrap_or(0) * 1024;
+    }
+    Some(total)
+}
+
+/// Resident set size of a process tree in bytes.
+#[cfg(target_os = "macos")]
+fn rss(pid: u32) -> Option<u64> {
+    let output = std::process::Command::new("ps")
+        .args(["-A", "-o", "pid=,ppid=,rss="])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let mut procs = vec![];
+    let mut usage = std::collections::HashMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let fields = line
+            .split_whitespace()
+            .filter_map(|x| x.parse::<u64>().ok())
+            .collect::<Vec<_>>();
+        if let [pid, ppid, kb] = fields[..] {
+            procs.push((pid as u32, ppid as u32));
+            usage.insert(pid as u32, kb * 1024);
+        }
+    }
+    Some(
+        crate::os::tree(pid, &procs)
+            .iter()
+            .filter_map(|pid| usage.get(pid))
+            .sum(),
+    )
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn rss(_pid: u32) -> Option<u64> {
+    None
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn process_rss() {
+        assert!(rss(std::process::id()).is_some_and(|rss| rss > 0));
+    }
+}
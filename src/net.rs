@@ -1,20 +1,24 @@
 use std::{
     error::Error as StdError,
-    fmt,
-    net::{AddrParseError, SocketAddr},
+    fmt, io,
+    net::{AddrParseError, Ipv4Addr, SocketAddr, TcpListener},
     time::{Duration, Instant},
 };
 
 use async_trait::async_trait;
-use hyper::{client::HttpConnector, http::uri::InvalidUri, Body, Client, Request, Response, Uri};
-use tokio::{io::AsyncWriteExt, net::TcpStream, time};
+use hyper::{
+    client::HttpConnector, header, http::uri::InvalidUri, Body, Client, Request, Response, Uri,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    time,
+};
 
-use crate::{Dependency, DependencyWaitError};
+use crate::{Dependency, DependencyWaitError, Env, RetryPolicy};
 
 pub use hyper::Method as HttpMethod;
 
-const ITER_GAP: Duration = Duration::from_millis(250); // ms
-
 /// Error returned from a network [`Dependency::wait`](Dependency::wait) method.
 #[derive(thiserror::Error, Debug)]
 enum NetServiceWaitError {
@@ -41,6 +45,8 @@ pub struct TcpService {
     pub timeout: Duration,
     /// Optional wait time after a successful response from the TCP service.
     pub warm_up: Option<Duration>,
+    /// A hint printed when the service doesn't accept connections in time, e.g. `is docker compose up?`.
+    pub hint: Option<String>,
 }
 
 impl TcpService {
@@ -59,8 +65,26 @@ impl TcpService {
             addr,
             timeout,
             warm_up,
+            hint: None,
         })
     }
+
+    /// Constructs new TcpService that waits for a port on the local machine.
+    pub fn local(tag: impl Into<String>, port: u16, timeout: Duration) -> Self {
+        Self {
+            tag: tag.into(),
+            addr: SocketAddr::from((Ipv4Addr::LOCALHOST, port)),
+            timeout,
+            warm_up: None,
+            hint: None,
+        }
+    }
+
+    /// Sets a [hint](TcpService#structfield.hint) printed when the service doesn't accept connections in time.
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
 }
 
 #[async_trait]
@@ -69,6 +93,17 @@ impl Dependency for TcpService {
         &self.tag
     }
 
+    fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    fn key(&self) -> Option<String> {
+        Some(format!(
+            "tcp:{} {:?} {:?}",
+            self.addr, self.timeout, self.warm_up
+        ))
+    }
+
     async fn check(&self) -> Result<(), ()> {
         match TcpStream::connect(&self.addr).await {
             Ok(_) => Ok(()),
@@ -76,19 +111,24 @@ impl Dependency for TcpService {
         }
     }
 
-    async fn wait(&self) -> Result<(), Box<dyn DependencyWaitError>> {
-        let start = Instant::now();
+    async fn wait_with_deadline(
+        &self,
+        deadline: Instant,
+    ) -> Result<(), Box<dyn DependencyWaitError>> {
+        let mut attempts = RetryPolicy::poll(self.timeout)
+            .start()
+            .until(Some(deadline));
 
         loop {
             match time::timeout(
-                self.timeout - start.elapsed(),
+                attempts.remaining().unwrap_or(self.timeout),
                 TcpStream::connect(&self.addr),
             )
             .await
             {
                 Ok(Ok(mut stream)) => {
                     if let Err(error) = stream.shutdown().await {
-                        eprintln!("Failed to close socket: {}", error);
+                        console!("Failed to close socket: {}", error);
                     };
 
                     if let Some(duration) = self.warm_up {
@@ -103,15 +143,99 @@ impl Dependency for TcpService {
                 }
             }
 
-            if start.elapsed() >= self.timeout {
+            if !attempts.next().await {
                 return Err(Box::new(NetServiceWaitError::Timeout));
             }
-
-            time::sleep(ITER_GAP).await;
         }
     }
 }
 
+/// Returns a port that is currently available on the local machine.
+///
+/// The port is picked by the OS and released right away, so it is available to a process that is spawned next.
+/// To get several ports, use [`Ports::reserve`](Ports::reserve), since this function might return the same port twice.
+pub fn free_port() -> io::Result<u16> {
+    Ok(bind()?.local_addr()?.port())
+}
+
+fn bind() -> io::Result<TcpListener> {
+    TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))
+}
+
+/// A set of distinct ports that are currently available on the local machine.
+///
+/// Useful to run several instances of a dev stack side by side without collisions on hard-coded ports.
+///
+/// ```ignore
+/// let ports = Ports::reserve(2)?;
+///
+/// let server = process! {
+///     tag: "server",
+///     cmd: cmd! { "cargo run", env: ports.env(["PORT"]), pwd: Loc::server() },
+/// };
+/// let client = process! {
+///     tag: "client",
+///     cmd: cmd! {
+///         "yarn start",
+///         env: ports.env(["SERVER_PORT", "PORT"]),
+///         pwd: Loc::client(),
+///     },
+/// };
+///
+/// ProcessPool::run_with_deps(vec![
+///     PoolEntry::Process(server),
+///     PoolEntry::ProcessWithDep {
+///         process: client,
+///         dependency: Box::new(ports.tcp_service(0, "server", Duration::from_secs(30))),
+///     },
+/// ]).await
+/// ```
+#[derive(Clone, Debug)]
+pub struct Ports(Vec<u16>);
+
+impl Ports {
+    /// Reserves `n` distinct available ports. The ports are released right away,
+    /// so they are available to processes that are spawned next.
+    pub fn reserve(n: usize) -> io::Result<Self> {
+        // Listeners are held until all the ports are picked, so the OS doesn't return the same port twice
+        let listeners = (0..n).map(|_| bind()).collect::<io::Result<Vec<_>>>()?;
+        let ports = listeners
+            .iter()
+            .map(|listener| listener.local_addr().map(|addr| addr.port()))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(Self(ports))
+    }
+
+    /// Returns a port by its index. Panics if the index is out of bounds.
+    pub fn get(&self, idx: usize) -> u16 {
+        self.0[idx]
+    }
+
+    /// Returns an iterator over the ports.
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        self.0.iter().copied()
+    }
+
+    /// Returns an [`Env`](Env) where the keys are assigned to the ports in order,
+    /// i.e. the first key gets the first port and so on. Panics if there are more keys than ports.
+    ///
+    /// ```ignore
+    /// ports.env(["PORT", "SERVER_PORT"])
+    /// ```
+    pub fn env<K: ToString>(&self, keys: impl IntoIterator<Item = K>) -> Env {
+        keys.into_iter()
+            .enumerate()
+            .fold(Env::empty(), |env, (idx, key)| {
+                env.insert(key, self.get(idx))
+            })
+    }
+
+    /// Returns a [`TcpService`](TcpService) dependency that waits for a port by its index on the local machine.
+    pub fn tcp_service(&self, idx: usize, tag: impl Into<String>, timeout: Duration) -> TcpService {
+        TcpService::local(tag, self.get(idx), timeout)
+    }
+}
+
 /// HTTP service.
 pub struct HttpService {
     /// A tag used as an identificator of the dependency in the output.
@@ -122,6 +246,34 @@ pub struct HttpService {
     pub method: HttpMethod,
     /// Service wait timeout.
     pub timeout: Duration,
+    /// What to do when the service responds with a redirect. See [`Redirects`](Redirects).
+    pub redirects: Redirects,
+    /// A hint printed when the service doesn't respond successfully in time, e.g. `is the server running?`.
+    pub hint: Option<String>,
+}
+
+/// Defines how an [`HttpService`](HttpService) handles redirects (`3xx` responses).
+///
+/// Many dev servers respond to `/` with a redirect, e.g. to `/login`, so by default redirects are followed.
+///
+/// ```ignore
+/// HttpService::new("app", "localhost", 3000, "/", false, HttpMethod::GET, Duration::from_secs(30))?
+///     .redirects(Redirects::Accept)
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Redirects {
+    /// Follows up to the given number of redirects. The service is available when the final response is successful.
+    Follow(usize),
+    /// Any redirect means the service is available.
+    Accept,
+    /// Any redirect means the service is not available, as any other unsuccessful response.
+    Fail,
+}
+
+impl Default for Redirects {
+    fn default() -> Self {
+        Self::Follow(10)
+    }
 }
 
 impl HttpService {
@@ -133,11 +285,6 @@ impl HttpService {
     fn https_connector() -> tls::HttpsConnector<HttpConnector> {
         tls::HttpsConnector::new()
     }
-
-    #[cfg(not(feature = "tls"))]
-    fn https_connector() -> HttpConnector {
-        unreachable!("Cannot use https_connector method without tls feature");
-    }
 }
 
 #[derive(Debug)]
@@ -186,19 +333,59 @@ impl HttpService {
             addr,
             method,
             timeout,
+            redirects: Redirects::default(),
+            hint: None,
         })
     }
 
-    pub(crate) fn build_req(&self) -> Request<Body> {
+    /// Sets how the service handles redirects. See [`Redirects`](Redirects).
+    pub fn redirects(mut self, redirects: Redirects) -> Self {
+        self.redirects = redirects;
+        self
+    }
+
+    /// Sets a [hint](HttpService#structfield.hint) printed when the service doesn't respond successfully in time.
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    pub(crate) fn build_req(&self, uri: &Uri) -> Request<Body> {
         Request::builder()
             .method(&self.method)
-            .uri(&self.addr)
+            .uri(uri)
             .body(Body::default())
             .expect("Failed to build HTTP request")
     }
 
-    fn handle_res(res: Response<Body>) -> Result<(), Box<dyn DependencyWaitError>> {
-        if res.status().is_success() {
+    async fn send(&self, uri: &Uri) -> Result<Response<Body>, RequestError> {
+        self::request(self.build_req(uri)).await
+    }
+
+    /// Sends a request, following redirects according to the policy.
+    async fn fetch(&self) -> Result<Response<Body>, RequestError> {
+        let mut uri = self.addr.clone();
+        let mut redirects = 0;
+        loop {
+            let res = self.send(&uri).await?;
+            match self.redirects {
+                Redirects::Follow(max) if res.status().is_redirection() && redirects < max => {
+                    match self::redirect_location(&uri, &res) {
+                        Some(location) => {
+                            uri = location;
+                            redirects += 1;
+                        }
+                        None => return Ok(res),
+                    }
+                }
+                Redirects::Follow(_) | Redirects::Accept | Redirects::Fail => return Ok(res),
+            }
+        }
+    }
+
+    fn handle_res(&self, res: Response<Body>) -> Result<(), Box<dyn DependencyWaitError>> {
+        let status = res.status();
+        if status.is_success() || (status.is_redirection() && self.redirects == Redirects::Accept) {
             Ok(())
         } else {
             Err(Box::new(NetServiceWaitError::Rejection {
@@ -208,75 +395,371 @@ impl HttpService {
     }
 }
 
+/// Resolves the `Location` header of a redirect against the requested URI.
+fn redirect_location(uri: &Uri, res: &Response<Body>) -> Option<Uri> {
+    let location = res.headers().get(hyper::header::LOCATION)?.to_str().ok()?;
+    if location.contains("://") {
+        return location.parse().ok();
+    }
+    let path = if location.starts_with('/') {
+        location.to_string()
+    } else {
+        let base = uri.path();
+        let dir = &base[..base.rfind('/').map_or(0, |idx| idx + 1)];
+        format!("{}{}", dir, location)
+    };
+    Uri::builder()
+        .scheme(uri.scheme()?.clone())
+        .authority(uri.authority()?.clone())
+        .path_and_query(path)
+        .build()
+        .ok()
+}
+
 #[async_trait]
 impl Dependency for HttpService {
     fn tag(&self) -> &str {
         &self.tag
     }
 
+    fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    fn key(&self) -> Option<String> {
+        Some(format!(
+            "http:{:?} {} {:?} {:?}",
+            self.method, self.addr, self.timeout, self.redirects
+        ))
+    }
+
     async fn check(&self) -> Result<(), ()> {
-        match self.addr.scheme_str() {
-            Some("https") => {
-                let connector = Self::https_connector();
-                let client = Client::builder().build(connector);
-                let req = self.build_req();
-                let res = client.request(req).await.map_err(|_| ())?;
-                Self::handle_res(res).map_err(|_| ())
+        let res = self.fetch().await.map_err(|_| ())?;
+        self.handle_res(res).map_err(|_| ())
+    }
+
+    async fn wait_with_deadline(
+        &self,
+        deadline: Instant,
+    ) -> Result<(), Box<dyn DependencyWaitError>> {
+        let mut attempts = RetryPolicy::poll(self.timeout)
+            .start()
+            .until(Some(deadline));
+
+        loop {
+            let remaining = attempts.remaining().unwrap_or(self.timeout);
+            match time::timeout(remaining, self.fetch()).await {
+                Ok(Ok(res)) => return self.handle_res(res),
+                Ok(Err(error @ RequestError::TlsDisabled)) => {
+                    return Err(Box::new(NetServiceWaitError::Rejection {
+                        error: Box::new(error),
+                    }))
+                }
+                Ok(Err(RequestError::Http(_))) => (),
+                Err(_) => return Err(Box::new(NetServiceWaitError::Timeout)),
             }
-            Some(_) | None => {
-                let connector = Self::http_connector();
-                let client = Client::builder().build(connector);
-                let req = self.build_req();
-                let res = client.request(req).await.map_err(|_| ())?;
-                Self::handle_res(res).map_err(|_| ())
+
+            if !attempts.next().await {
+                return Err(Box::new(NetServiceWaitError::Timeout));
             }
         }
     }
+}
 
-    async fn wait(&self) -> Result<(), Box<dyn DependencyWaitError>> {
-        let start = Instant::now();
+/// Error of a request sent via [`request`](request).
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum RequestError {
+    #[cfg_attr(feature = "tls", allow(dead_code))]
+    #[error("https requires the `tls` feature")]
+    TlsDisabled,
+    #[error(transparent)]
+    Http(#[from] hyper::Error),
+}
 
-        match self.addr.scheme_str() {
-            Some("https") => {
-                let connector = Self::https_connector();
-                let client = Client::builder().build(connector);
+/// Sends a request with a client that supports the scheme of the request URI.
+pub(crate) async fn request(req: Request<Body>) -> Result<Response<Body>, RequestError> {
+    match req.uri().scheme_str() {
+        Some("https") => self::https(req).await,
+        Some(_) | None => {
+            let client = Client::builder().build(HttpService::http_connector());
+            Ok(client.request(req).await?)
+        }
+    }
+}
 
-                loop {
-                    let req = self.build_req();
+#[cfg(feature = "tls")]
+async fn https(req: Request<Body>) -> Result<Response<Body>, RequestError> {
+    let client = Client::builder().build(HttpService::https_connector());
+    Ok(client.request(req).await?)
+}
 
-                    match time::timeout(self.timeout - start.elapsed(), client.request(req)).await {
-                        Ok(Ok(res)) => return Self::handle_res(res),
-                        Ok(Err(_)) => (),
-                        Err(_) => return Err(Box::new(NetServiceWaitError::Timeout)),
-                    }
+#[cfg(not(feature = "tls"))]
+async fn https(_req: Request<Body>) -> Result<Response<Body>, RequestError> {
+    Err(RequestError::TlsDisabled)
+}
 
-                    if start.elapsed() >= self.timeout {
-                        return Err(Box::new(NetServiceWaitError::Timeout));
-                    }
+/// WebSocket endpoint. The endpoint is available once it accepts a WebSocket handshake,
+/// and optionally responds to a ping frame with a pong frame.
+///
+/// Useful when the critical readiness signal is a websocket endpoint (hot-reload servers, realtime APIs),
+/// since plain HTTP might be served before it.
+///
+/// ```ignore
+/// let hmr = WsDep::new("hmr", "ws://localhost:3000/_next/webpack-hmr", Duration::from_secs(60))?.ping(true);
+/// ```
+pub struct WsDep {
+    /// A tag used as an identificator of the dependency in the output.
+    pub tag: String,
+    /// Endpoint address with either `ws` or `wss` scheme.
+    pub addr: Uri,
+    /// Endpoint wait timeout.
+    pub timeout: Duration,
+    /// Whether to send a ping frame after the handshake and wait for a pong frame.
+    pub ping: bool,
+    /// A hint printed when the endpoint doesn't accept a handshake in time, e.g. `is hot reloading enabled?`.
+    pub hint: Option<String>,
+}
+
+#[derive(thiserror::Error, Debug)]
+enum WsError {
+    #[error("Unsupported scheme of a WebSocket address: {0}")]
+    Scheme(String),
+    #[error("Handshake was rejected with {0}")]
+    Rejected(hyper::StatusCode),
+    #[error("Connection was closed without a pong frame")]
+    Closed,
+    #[error(transparent)]
+    Request(#[from] RequestError),
+    #[error(transparent)]
+    Http(#[from] hyper::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl WsDep {
+    /// Client payload of a ping frame.
+    const PING: &'static [u8] = b"steward";
 
-                    time::sleep(ITER_GAP).await;
+    /// Constructs new WsDep.
+    pub fn new(
+        tag: impl Into<String>,
+        addr: impl AsRef<str>,
+        timeout: Duration,
+    ) -> Result<Self, InvalidUri> {
+        Ok(Self {
+            tag: tag.into(),
+            addr: addr.as_ref().parse()?,
+            timeout,
+            ping: false,
+            hint: None,
+        })
+    }
+
+    /// Sets whether to send a ping frame after the handshake and wait for a pong frame.
+    pub fn ping(mut self, ping: bool) -> Self {
+        self.ping = ping;
+        self
+    }
+
+    /// Sets a [hint](WsDep#structfield.hint) printed when the endpoint doesn't accept a handshake in time.
+    pub fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    async fn handshake(&self) -> Result<(), WsError> {
+        let scheme = match self.addr.scheme_str() {
+            Some("ws") => "http",
+            Some("wss") => "https",
+            scheme => return Err(WsError::Scheme(scheme.unwrap_or_default().to_string())),
+        };
+        let mut uri = self.addr.clone().into_parts();
+        uri.scheme = Some(scheme.parse().expect("Failed to parse HTTP scheme"));
+        let uri =
+            Uri::from_parts(uri).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        let key = self::base64(&rand::random::<[u8; 16]>());
+        let req = Request::get(uri)
+            .header(header::CONNECTION, "Upgrade")
+            .header(header::UPGRADE, "websocket")
+            .header(header::SEC_WEBSOCKET_VERSION, "13")
+            .header(header::SEC_WEBSOCKET_KEY, key)
+            .body(Body::empty())
+            .expect("Failed to build WebSocket handshake request");
+
+        let res = self::request(req).await?;
+        if res.status() != hyper::StatusCode::SWITCHING_PROTOCOLS {
+            return Err(WsError::Rejected(res.status()));
+        }
+        if !self.ping {
+            return Ok(());
+        }
+
+        let mut io = hyper::upgrade::on(res).await?;
+        io.write_all(&self::client_frame(0x9, Self::PING)).await?;
+        loop {
+            let mut head = [0; 2];
+            io.read_exact(&mut head).await?;
+            let opcode = head[0] & 0x0f;
+            let len = match head[1] & 0x7f {
+                126 => {
+                    let mut len = [0; 2];
+                    io.read_exact(&mut len).await?;
+                    u16::from_be_bytes(len) as u64
+                }
+                127 => {
+                    let mut len = [0; 8];
+                    io.read_exact(&mut len).await?;
+                    u64::from_be_bytes(len)
+                }
+                len => len as u64,
+            };
+            let masked = head[1] & 0x80 != 0;
+            let len = if masked { len + 4 } else { len };
+            // Payload is not needed, only the type of a frame
+            tokio::io::copy(&mut (&mut io).take(len), &mut tokio::io::sink()).await?;
+            match opcode {
+                0xa => {
+                    io.write_all(&self::client_frame(0x8, &[])).await.ok();
+                    return Ok(());
                 }
+                0x8 => return Err(WsError::Closed),
+                _ => (),
             }
-            Some(_) | None => {
-                let connector = Self::http_connector();
-                let client = Client::builder().build(connector);
+        }
+    }
+}
 
-                loop {
-                    let req = self.build_req();
+/// Encodes a frame sent by a client. Such frames must be masked.
+fn client_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    // Control frames are at most 125 bytes long, so the length fits the header
+    debug_assert!(payload.len() <= 125);
+    let mask = rand::random::<[u8; 4]>();
+    let mut frame = vec![0x80 | opcode, 0x80 | payload.len() as u8];
+    frame.extend_from_slice(&mask);
+    frame.extend(
+        payload
+            .iter()
+            .enumerate()
+            .map(|(idx, byte)| byte ^ mask[idx % 4]),
+    );
+    frame
+}
 
-                    match time::timeout(self.timeout - start.elapsed(), client.request(req)).await {
-                        Ok(Ok(res)) => return Self::handle_res(res),
-                        Ok(Err(_)) => (),
-                        Err(_) => return Err(Box::new(NetServiceWaitError::Timeout)),
-                    }
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (idx, byte)| n | (*byte as u32) << (16 - idx * 8));
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - idx * 6)) as usize & 0x3f] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
 
-                    if start.elapsed() >= self.timeout {
-                        return Err(Box::new(NetServiceWaitError::Timeout));
-                    }
+#[async_trait]
+impl Dependency for WsDep {
+    fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    fn key(&self) -> Option<String> {
+        Some(format!("ws:{} {:?} {}", self.addr, self.timeout, self.ping))
+    }
 
-                    time::sleep(ITER_GAP).await;
+    async fn check(&self) -> Result<(), ()> {
+        self.handshake().await.map_err(|_| ())
+    }
+
+    async fn wait_with_deadline(
+        &self,
+        deadline: Instant,
+    ) -> Result<(), Box<dyn DependencyWaitError>> {
+        let mut attempts = RetryPolicy::poll(self.timeout)
+            .start()
+            .until(Some(deadline));
+
+        loop {
+            let remaining = attempts.remaining().unwrap_or(self.timeout);
+            match time::timeout(remaining, self.handshake()).await {
+                Ok(Ok(())) => return Ok(()),
+                Ok(Err(
+                    error @ (WsError::Scheme(_)
+                    | WsError::Rejected(_)
+                    | WsError::Request(RequestError::TlsDisabled)),
+                )) => {
+                    return Err(Box::new(NetServiceWaitError::Rejection {
+                        error: Box::new(error),
+                    }))
                 }
+                Ok(Err(
+                    WsError::Closed
+                    | WsError::Request(RequestError::Http(_))
+                    | WsError::Http(_)
+                    | WsError::Io(_),
+                )) => (),
+                Err(_) => return Err(Box::new(NetServiceWaitError::Timeout)),
+            }
+
+            if !attempts.next().await {
+                return Err(Box::new(NetServiceWaitError::Timeout));
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64() {
+        assert_eq!(super::base64(b"steward"), "c3Rld2FyZA==");
+        assert_eq!(super::base64(b"ws"), "d3M=");
+        assert_eq!(super::base64(b"dep"), "ZGVw");
+    }
+
+    #[cfg(not(feature = "tls"))]
+    #[tokio::test]
+    async fn https_without_tls() {
+        let req = Request::post("https://hooks.slack.com/services/T0")
+            .body(Body::empty())
+            .unwrap();
+        assert!(matches!(
+            super::request(req).await,
+            Err(RequestError::TlsDisabled)
+        ));
+    }
+
+    #[test]
+    fn redirect_location() {
+        let uri: Uri = "http://localhost:3000/app/index".parse().unwrap();
+        let location = |value: &str| {
+            let res = Response::builder()
+                .status(302)
+                .header(hyper::header::LOCATION, value)
+                .body(Body::empty())
+                .unwrap();
+            super::redirect_location(&uri, &res).unwrap().to_string()
+        };
+        assert_eq!(location("/login"), "http://localhost:3000/login");
+        assert_eq!(
+            location("login?next=1"),
+            "http://localhost:3000/app/login?next=1"
+        );
+        assert_eq!(location("https://auth.test/"), "https://auth.test/");
+    }
+}
@@ -0,0 +1,182 @@
+use std::{fmt, time::Duration};
+
+use hyper::{header, Body, Request, Uri};
+use tokio::time;
+
+/// A lifecycle event of a [`ProcessPool`](crate::ProcessPool) that is worth notifying about.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PoolEvent {
+    /// A process exited with an error or failed to spawn.
+    Crashed {
+        /// Tag of the process.
+        tag: String,
+        /// How the process exited, e.g. `exited with code 1`.
+        reason: String,
+    },
+    /// A process didn't exit on shutdown of the pool within its timeout and was killed.
+    Killed {
+        /// Tag of the process.
+        tag: String,
+    },
+    /// The pool is shut down.
+    Shutdown,
+}
+
+impl PoolEvent {
+    /// Name of the event: `crashed`, `killed` or `shutdown`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Crashed { .. } => "crashed",
+            Self::Killed { .. } => "killed",
+            Self::Shutdown => "shutdown",
+        }
+    }
+
+    /// Tag of the process the event is about, if any.
+    pub fn tag(&self) -> Option<&str> {
+        match self {
+            Self::Crashed { tag, .. } | Self::Killed { tag } => Some(tag),
+            Self::Shutdown => None,
+        }
+    }
+}
+
+impl fmt::Display for PoolEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Crashed { tag, reason } => write!(f, "Process {} crashed: {}", tag, reason),
+            Self::Killed { tag } => write!(f, "Process {} was killed due to timeout", tag),
+            Self::Shutdown => write!(f, "Process pool is shut down"),
+        }
+    }
+}
+
+/// Posts JSON to a URL on lifecycle events of a [`ProcessPool`](crate::ProcessPool), e.g. to a Slack channel
+/// of a shared staging supervisor. See [`PoolEvent`](PoolEvent).
+///
+/// The payload is rendered from a template with the following placeholders, which are replaced
+/// with JSON-escaped values (without quotes):
+///
+/// - `{text}`: a human-readable description of the event, e.g. `Process server crashed: exited with code 1`
+/// - `{event}`: name of the event, see [`PoolEvent::name`](PoolEvent::name)
+/// - `{tag}`: tag of the process, or an empty string
+///
+/// The default template is a Slack-compatible `{"text": "{text}"}`.
+///
+/// ```ignore
+/// let opts = PoolOptions {
+///     webhook: Some(Webhook::new(env::var("SLACK_WEBHOOK_URL")?.parse()?)),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Debug)]
+pub struct Webhook {
+    /// URL to post to.
+    pub url: Uri,
+    /// Payload template.
+    pub template: String,
+}
+
+impl Webhook {
+    /// Max time to wait for a response.
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Constructs a webhook with the default Slack-compatible template.
+    pub fn new(url: Uri) -> Self {
+        Self {
+            url,
+            template: r#"{"text": "{text}"}"#.to_string(),
+        }
+    }
+
+    /// Sets the payload template.
+    pub fn template(mut self, template: impl Into<String>) -> Self {
+        self.template = template.into();
+        self
+    }
+
+    /// Renders the payload of an event.
+    pub(crate) fn payload(&self, event: &PoolEvent) -> String {
+        let values = [
+            ("{text}", self::json_escape(&event.to_string())),
+            ("{event}", self::json_escape(event.name())),
+            ("{tag}", self::json_escape(event.tag().unwrap_or_default())),
+        ];
+        let mut payload = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+        while let Some(idx) = rest.find('{') {
+            payload.push_str(&rest[..idx]);
+            rest = &rest[idx..];
+            match values.iter().find(|(key, _)| rest.starts_with(key)) {
+                Some((key, value)) => {
+                    payload.push_str(value);
+                    rest = &rest[key.len()..];
+                }
+                None => {
+                    payload.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        payload.push_str(rest);
+        payload
+    }
+
+    /// Posts an event. Failures are printed, since a notification must not break the pool.
+    pub(crate) async fn send(&self, event: &PoolEvent) {
+        let req = Request::post(&self.url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(self.payload(event)))
+            .expect("Failed to build webhook request");
+        let error = match time::timeout(Self::TIMEOUT, crate::net::request(req)).await {
+            Ok(Ok(res)) if res.status().is_success() => return,
+            Ok(Ok(res)) => res.status().to_string(),
+            Ok(Err(err)) => err.to_string(),
+            Err(_) => "Timeout".to_string(),
+        };
+        console!(
+            "⚠️  Failed to post {} event to webhook: {}",
+            event.name(),
+            error
+        );
+    }
+}
+
+pub(crate) fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhook_payload() {
+        let webhook = Webhook::new("http://localhost/hook".parse().unwrap());
+        let event = PoolEvent::Crashed {
+            tag: "server".to_string(),
+            reason: "failed: \"oops\"".to_string(),
+        };
+        assert_eq!(
+            webhook.payload(&event),
+            r#"{"text": "Process server crashed: failed: \"oops\""}"#
+        );
+        let webhook = webhook.template(r#"{"event": "{event}", "tag": "{tag}"}"#);
+        assert_eq!(
+            webhook.payload(&PoolEvent::Shutdown),
+            r#"{"event": "shutdown", "tag": ""}"#
+        );
+    }
+}
@@ -0,0 +1,362 @@
+use std::{io, time::Duration};
+
+use tokio::task;
+
+use crate::{Error, Result, StopSignal};
+
+/// How a process is terminated by [`terminate`](terminate).
+///
+/// ```ignore
+/// // Asks a dev server recorded in a stale pidfile to exit, and kills it along with its children in 5 seconds
+/// os::terminate(pid, TerminateMode::graceful(Duration::from_secs(5)).tree(true)).await?;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TerminateMode {
+    /// Signal a process is asked to exit with. Only used if the termination is graceful.
+    pub signal: StopSignal,
+    /// If set, a process is asked to exit first and killed only if it's still running after this long.
+    /// Otherwise, it's killed right away. There are no signals on Windows, so a process is always killed right away.
+    pub grace: Option<Duration>,
+    /// Whether the descendants of a process are terminated as well, e.g. when it's run via a shell.
+    pub tree: bool,
+}
+
+impl TerminateMode {
+    /// A process is sent `SIGTERM` and killed if it's still running after the grace period.
+    pub fn graceful(grace: Duration) -> Self {
+        Self {
+            signal: StopSignal::Terminate,
+            grace: Some(grace),
+            tree: false,
+        }
+    }
+
+    /// A process is killed right away.
+    pub fn forceful() -> Self {
+        Self {
+            signal: StopSignal::Terminate,
+            grace: None,
+            tree: false,
+        }
+    }
+
+    /// Sets a signal a process is asked to exit with.
+    pub fn signal(mut self, signal: StopSignal) -> Self {
+        self.signal = signal;
+        self
+    }
+
+    /// Sets whether the descendants of a process are terminated as well.
+    pub fn tree(mut self, tree: bool) -> Self {
+        self.tree = tree;
+        self
+    }
+}
+
+/// Terminates a process that is not managed by steward, e.g. one recorded in a stale pidfile.
+///
+/// Returns [`Error::NoSuchProcess`](crate::Error::NoSuchProcess) if the process is not running,
+/// and [`Error::TerminateFailed`](crate::Error::TerminateFailed) with the OS error if it can't be terminated,
+/// e.g. due to insufficient permissions. The errors are the same on every platform.
+pub async fn terminate(pid: u32, mode: TerminateMode) -> Result<()> {
+    let pids = if mode.tree {
+        task::spawn_blocking(move || match self::processes() {
+            Some(procs) => self::tree(pid, &procs),
+            None => vec![pid],
+        })
+        .await
+        .unwrap_or_else(|_| vec![pid])
+    } else {
+        vec![pid]
+    };
+
+    if !self::is_running(pid) {
+        return Err(Error::NoSuchProcess { pid });
+    }
+
+    #[cfg(unix)]
+    if let Some(grace) = mode.grace {
+        let signal = mode.signal.to_nix();
+        for x in &pids {
+            match imp::signal(*x, signal) {
+                Ok(()) => (),
+                Err(error) if *x == pid => return Err(Error::TerminateFailed { pid, error }),
+                // A descendant might be gone by now
+                Err(_) => (),
+            }
+            // A paused process handles the signal only once it's resumed
+            imp::signal(*x, nix::sys::signal::Signal::SIGCONT).ok();
+        }
+        let deadline = tokio::time::Instant::now() + grace;
+        while pids.iter().any(|x| self::is_running(*x)) && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    for x in pids.into_iter().filter(|x| self::is_running(*x)) {
+        match self::kill(x) {
+            Ok(()) => (),
+            Err(error) if x == pid => return Err(Error::TerminateFailed { pid, error }),
+            Err(_) => (),
+        }
+    }
+    Ok(())
+}
+
+/// Kills a process right away.
+pub(crate) fn kill(pid: u32) -> io::Result<()> {
+    imp::kill(pid)
+}
+
+/// Whether a process with the pid exists.
+pub(crate) fn is_running(pid: u32) -> bool {
+    imp::is_running(pid)
+}
+
+/// A process followed by its descendants.
+pub(crate) fn tree(pid: u32, procs: &[(u32, u32)]) -> Vec<u32> {
+    let mut tree = vec![pid];
+    let mut idx = 0;
+    while let Some(parent) = tree.get(idx).copied() {
+        tree.extend(
+            procs
+                .iter()
+                .filter(|(pid, ppid)| *ppid == parent && *pid != parent)
+                .map(|(pid, _)| *pid),
+        );
+        idx += 1;
+    }
+    tree
+}
+
+/// Pairs of `(pid, ppid)` of all the processes. `None` if they can't be listed on this platform.
+#[cfg(target_os = "linux")]
+pub(crate) fn processes() -> Option<Vec<(u32, u32)>> {
+    use std::fs;
+
+    let mut procs = vec![];
+    for entry in fs::read_dir("/proc").ok()? {
+        let pid = match entry.ok()?.file_name().to_str().map(str::parse::<u32>) {
+            Some(Ok(pid)) => pid,
+            Some(Err(_)) | None => continue,
+        };
+        // The name of a process is in parens and might contain spaces, so the ppid is the second field after it
+        let ppid = fs::read_to_string(format!("/proc/{}/stat", pid))
+            .ok()
+            .and_then(|stat| {
+                let (_, rest) = stat.rsplit_once(')')?;
+                rest.split_whitespace().nth(1)?.parse::<u32>().ok()
+            });
+        if let Some(ppid) = ppid {
+            procs.push((pid, ppid));
+        }
+    }
+    Some(procs)
+}
+
+/// Pairs of `(pid, ppid)` of all the processes. `None` if they can't be listed on this platform.
+#[cfg(target_os = "macos")]
+pub(crate) fn processes() -> Option<Vec<(u32, u32)>> {
+    let output = std::process::Command::new("ps")
+        .args(["-A", "-o", "pid=,ppid="])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let procs = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (pid, ppid) = line.trim().split_once(char::is_whitespace)?;
+            Some((pid.parse().ok()?, ppid.trim().parse().ok()?))
+        })
+        .collect();
+    Some(procs)
+}
+
+/// Pairs of `(pid, ppid)` of all the processes. `None` if they can't be listed on this platform.
+#[cfg(windows)]
+pub(crate) fn processes() -> Option<Vec<(u32, u32)>> {
+    use std::mem;
+
+    use winapi::{
+        shared::minwindef::{DWORD, FALSE},
+        um::{
+            handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
+            tlhelp32::{
+                CreateToolhelp32Snapshot, Process32First, Process32Next, PROCESSENTRY32,
+                TH32CS_SNAPPROCESS,
+            },
+        },
+    };
+
+    unsafe {
+        // https://docs.microsoft.com/en-us/windows/win32/api/tlhelp32/nf-tlhelp32-createtoolhelp32snapshot
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return None;
+        }
+
+        let mut entry: PROCESSENTRY32 = mem::zeroed();
+        entry.dwSize = mem::size_of::<PROCESSENTRY32>() as DWORD;
+        let mut procs = vec![];
+        let mut more = Process32First(snapshot, &mut entry) != FALSE;
+        while more {
+            procs.push((entry.th32ProcessID, entry.th32ParentProcessID));
+            more = Process32Next(snapshot, &mut entry) != FALSE;
+        }
+        CloseHandle(snapshot);
+        Some(procs)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub(crate) fn processes() -> Option<Vec<(u32, u32)>> {
+    None
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::io;
+
+    use nix::{
+        errno::Errno,
+        sys::signal::{self, Signal},
+        unistd::Pid,
+    };
+
+    pub(super) fn signal(pid: u32, signal: Signal) -> io::Result<()> {
+        signal::kill(self::pid(pid)?, signal).map_err(self::to_io_error)
+    }
+
+    pub(super) fn kill(pid: u32) -> io::Result<()> {
+        self::signal(pid, Signal::SIGKILL)
+    }
+
+    pub(super) fn is_running(pid: u32) -> bool {
+        match self::pid(pid).map(|pid| signal::kill(pid, None)) {
+            Ok(Ok(())) => true,
+            // The process exists, but belongs to another user
+            Ok(Err(error)) => error.as_errno() == Some(Errno::EPERM),
+            Err(_) => false,
+        }
+    }
+
+    /// Zero and the pids that don't fit into `pid_t` would address process groups, e.g. `-1` is every process.
+    fn pid(pid: u32) -> io::Result<Pid> {
+        match i32::try_from(pid) {
+            Ok(pid) if pid > 0 => Ok(Pid::from_raw(pid)),
+            Ok(_) | Err(_) => Err(io::Error::from_raw_os_error(Errno::ESRCH as i32)),
+        }
+    }
+
+    fn to_io_error(error: nix::Error) -> io::Error {
+        match error.as_errno() {
+            Some(errno) => io::Error::from_raw_os_error(errno as i32),
+            None => io::Error::other(error),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::io;
+
+    use winapi::{
+        shared::minwindef::{BOOL, DWORD, FALSE, UINT},
+        um::{
+            handleapi::CloseHandle,
+            processthreadsapi::{GetExitCodeProcess, OpenProcess, TerminateProcess},
+            winnt::{PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_TERMINATE},
+        },
+    };
+
+    // Exit code of a process that is still running
+    const STILL_ACTIVE: DWORD = 259;
+
+    pub(super) fn kill(pid: u32) -> io::Result<()> {
+        // for some reason windows doesn't have any exit codes,
+        // you just use what ever you want?
+        //
+        // so we're using exit code `0` then
+        const EXIT_CODE: UINT = 0;
+
+        unsafe {
+            // https://docs.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-openprocess
+            let handle = OpenProcess(PROCESS_TERMINATE, FALSE, pid);
+            if handle.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+
+            // https://docs.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-terminateprocess
+            let terminate_result: BOOL = TerminateProcess(handle, EXIT_CODE);
+            let res = if terminate_result == FALSE {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            };
+
+            // https://docs.microsoft.com/en-us/windows/win32/api/handleapi/nf-handleapi-closehandle
+            CloseHandle(handle);
+            res
+        }
+    }
+
+    pub(super) fn is_running(pid: u32) -> bool {
+        unsafe {
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid);
+            if handle.is_null() {
+                // The process exists, but belongs to another user
+                return io::Error::last_os_error().kind() == io::ErrorKind::PermissionDenied;
+            }
+            let mut code: DWORD = 0;
+            let res = GetExitCodeProcess(handle, &mut code);
+            CloseHandle(handle);
+            res != FALSE && code == STILL_ACTIVE
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    /// Whether a process is gone, or is a zombie waiting to be reaped.
+    fn is_gone(pid: u32) -> bool {
+        std::fs::read_to_string(format!("/proc/{}/stat", pid))
+            .map_or(true, |stat| stat.contains(") Z "))
+    }
+
+    #[test]
+    fn process_tree() {
+        let procs = [(1, 0), (10, 1), (11, 10), (12, 10), (20, 1), (21, 11)];
+        assert_eq!(tree(10, &procs), vec![10, 11, 12, 21]);
+        assert!(processes().is_some_and(|procs| procs.iter().any(|x| x.0 == std::process::id())));
+    }
+
+    #[tokio::test]
+    async fn terminate_tree() {
+        let mut child = std::process::Command::new("sh")
+            .args(["-c", "sleep 30 & sleep 30"])
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let procs = processes().unwrap();
+        let descendants = tree(pid, &procs);
+        assert_eq!(descendants.len(), 3);
+
+        terminate(
+            pid,
+            TerminateMode::graceful(Duration::from_secs(1)).tree(true),
+        )
+        .await
+        .unwrap();
+        child.wait().unwrap();
+        assert!(descendants.iter().all(|x| is_gone(*x)));
+        assert!(matches!(
+            terminate(pid, TerminateMode::forceful()).await,
+            Err(Error::NoSuchProcess { .. })
+        ));
+    }
+}
@@ -0,0 +1,324 @@
+use std::{
+    env,
+    fmt::Write as _,
+    mem,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use hyper::{header, Body, Request, Uri};
+use once_cell::sync::OnceCell;
+use tokio::time;
+
+use crate::{handle::Exit, notify::json_escape};
+
+static EXPORTER: OnceCell<Exporter> = OnceCell::new();
+
+/// Exports spans of [`Cmd::run`](crate::Cmd::run), dependency waits and process lifetimes to an OpenTelemetry collector
+/// via OTLP/HTTP (JSON encoding), so the timing of dev/CI orchestration can be analyzed in a tracing backend.
+///
+/// All spans of the current program belong to the same trace. Spans are exported in batches, so call
+/// [`Otlp::flush`](Otlp::flush) before the program exits. A [`ProcessPool`](crate::ProcessPool) flushes on its own
+/// once it's shut down.
+///
+/// ```ignore
+/// #[tokio::main]
+/// async fn main() -> steward::Result<()> {
+///     if let Some(otlp) = Otlp::from_env() {
+///         otlp.service_name("dev").install();
+///     }
+///     server::build().run().await?;
+///     Otlp::flush().await;
+///     Ok(())
+/// }
+/// ```
+///
+/// Span attributes:
+///
+/// - `steward.exe`, `steward.cwd`: command and its working directory
+/// - `steward.tag`: tag of a process or a dependency
+/// - `steward.exit_code`: exit code, if a command exited on its own
+/// - `steward.duration_ms`: duration of a span in milliseconds
+#[derive(Clone, Debug)]
+pub struct Otlp {
+    /// Traces endpoint of a collector, e.g. `http://localhost:4318/v1/traces`.
+    pub endpoint: Uri,
+    /// Value of the `service.name` resource attribute.
+    pub service_name: String,
+}
+
+impl Otlp {
+    /// Number of ended spans that triggers an export.
+    const BATCH_SIZE: usize = 64;
+    /// Max time to wait for a response of a collector.
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Constructs an exporter with the `steward` service name.
+    pub fn new(endpoint: Uri) -> Self {
+        Self {
+            endpoint,
+            service_name: "steward".to_string(),
+        }
+    }
+
+    /// Constructs an exporter from the standard `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`, `OTEL_EXPORTER_OTLP_ENDPOINT`
+    /// and `OTEL_SERVICE_NAME` environment variables. Returns `None` if no endpoint is set or it's invalid.
+    pub fn from_env() -> Option<Self> {
+        let endpoint = match env::var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT") {
+            Ok(endpoint) => endpoint,
+            Err(_) => format!(
+                "{}/v1/traces",
+                env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                    .ok()?
+                    .trim_end_matches('/')
+            ),
+        };
+        let otlp = Self::new(endpoint.parse().ok()?);
+        Some(match env::var("OTEL_SERVICE_NAME") {
+            Ok(name) => otlp.service_name(name),
+            Err(_) => otlp,
+        })
+    }
+
+    /// Sets the service name.
+    pub fn service_name(mut self, name: impl Into<String>) -> Self {
+        self.service_name = name.into();
+        self
+    }
+
+    /// Starts exporting spans of the current program. Returns `false` if an exporter is already installed.
+    pub fn install(self) -> bool {
+        EXPORTER
+            .set(Exporter {
+                otlp: self,
+                trace_id: rand::random(),
+                spans: Mutex::new(Vec::new()),
+            })
+            .is_ok()
+    }
+
+    /// Exports the pending spans. Does nothing if no exporter is installed.
+    pub async fn flush() {
+        if let Some(exporter) = EXPORTER.get() {
+            exporter.export(exporter.take()).await;
+        }
+    }
+}
+
+struct Exporter {
+    otlp: Otlp,
+    trace_id: [u8; 16],
+    spans: Mutex<Vec<Span>>,
+}
+
+impl Exporter {
+    fn push(&'static self, span: Span) {
+        let mut spans = self.spans.lock().unwrap_or_else(|err| err.into_inner());
+        spans.push(span);
+        if spans.len() >= Otlp::BATCH_SIZE {
+            let spans = mem::take(&mut *spans);
+            // Spans might end outside of a runtime, e.g. on drop after its shutdown
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                runtime.spawn(self.export(spans));
+            }
+        }
+    }
+
+    fn take(&self) -> Vec<Span> {
+        mem::take(&mut *self.spans.lock().unwrap_or_else(|err| err.into_inner()))
+    }
+
+    /// Exports spans. Failures are printed, since tracing must not break a run.
+    async fn export(&self, spans: Vec<Span>) {
+        if spans.is_empty() {
+            return;
+        }
+        let req = Request::post(&self.otlp.endpoint)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(self.payload(&spans)))
+            .expect("Failed to build OTLP request");
+        let error = match time::timeout(Otlp::TIMEOUT, crate::net::request(req)).await {
+            Ok(Ok(res)) if res.status().is_success() => return,
+            Ok(Ok(res)) => res.status().to_string(),
+            Ok(Err(err)) => err.to_string(),
+            Err(_) => "Timeout".to_string(),
+        };
+        console!("⚠️  Failed to export {} spans: {}", spans.len(), error);
+    }
+
+    fn payload(&self, spans: &[Span]) -> String {
+        let trace_id = self::hex(&self.trace_id);
+        let spans = spans
+            .iter()
+            .map(|span| span.json(&trace_id))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            r#"{{"resourceSpans":[{{"resource":{{"attributes":[{}]}},"scopeSpans":[{{"scope":{{"name":"steward","version":"{}"}},"spans":[{}]}}]}}]}}"#,
+            Attr::Str(self.otlp.service_name.clone()).json("service.name"),
+            env!("CARGO_PKG_VERSION"),
+            spans
+        )
+    }
+}
+
+/// A span of a command, a dependency wait or a process lifetime. Exported once ended, if an exporter is installed.
+pub(crate) struct Span {
+    name: String,
+    id: [u8; 8],
+    parent: Option<[u8; 8]>,
+    started_at: SystemTime,
+    ended_at: SystemTime,
+    clock: Instant,
+    attrs: Vec<(&'static str, Attr)>,
+    error: Option<String>,
+}
+
+/// Id of a span, to start its children in other tasks.
+#[derive(Clone, Copy)]
+pub(crate) struct SpanId([u8; 8]);
+
+enum Attr {
+    Str(String),
+    Int(i64),
+}
+
+impl Span {
+    pub(crate) fn start(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            id: rand::random(),
+            parent: None,
+            started_at: SystemTime::now(),
+            ended_at: SystemTime::now(),
+            clock: Instant::now(),
+            attrs: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub(crate) fn id(&self) -> SpanId {
+        SpanId(self.id)
+    }
+
+    pub(crate) fn parent(mut self, parent: SpanId) -> Self {
+        self.parent = Some(parent.0);
+        self
+    }
+
+    pub(crate) fn str(mut self, key: &'static str, value: impl Into<String>) -> Self {
+        self.attrs.push((key, Attr::Str(value.into())));
+        self
+    }
+
+    pub(crate) fn int(mut self, key: &'static str, value: i64) -> Self {
+        self.attrs.push((key, Attr::Int(value)));
+        self
+    }
+
+    /// Ends the span with an exit of a command or a process.
+    pub(crate) fn exit(self, exit: &Exit) {
+        let span = match exit {
+            Exit::Code(Some(code)) => self.int("steward.exit_code", *code as i64),
+            Exit::Code(None) | Exit::Interrupted | Exit::Killed | Exit::Failed(_) => self,
+        };
+        if exit.is_failure() {
+            span.end(Some(exit.to_string()))
+        } else {
+            span.end(None)
+        }
+    }
+
+    /// Ends the span, with an error if it failed.
+    pub(crate) fn end(mut self, error: Option<String>) {
+        if let Some(exporter) = EXPORTER.get() {
+            let duration = self.clock.elapsed();
+            self.ended_at = self.started_at + duration;
+            self.error = error;
+            let span = self.int("steward.duration_ms", duration.as_millis() as i64);
+            exporter.push(span);
+        }
+    }
+
+    fn json(&self, trace_id: &str) -> String {
+        let mut json = format!(
+            r#"{{"traceId":"{}","spanId":"{}","name":"{}","kind":1,"startTimeUnixNano":"{}","endTimeUnixNano":"{}""#,
+            trace_id,
+            self::hex(&self.id),
+            json_escape(&self.name),
+            self::unix_nanos(self.started_at),
+            self::unix_nanos(self.ended_at),
+        );
+        if let Some(parent) = &self.parent {
+            write!(json, r#","parentSpanId":"{}""#, self::hex(parent)).unwrap();
+        }
+        let attrs = self
+            .attrs
+            .iter()
+            .map(|(key, value)| value.json(key))
+            .collect::<Vec<_>>()
+            .join(",");
+        write!(json, r#","attributes":[{}]"#, attrs).unwrap();
+        match &self.error {
+            None => json.push_str(r#","status":{"code":1}}"#),
+            Some(error) => write!(
+                json,
+                r#","status":{{"code":2,"message":"{}"}}}}"#,
+                json_escape(error)
+            )
+            .unwrap(),
+        }
+        json
+    }
+}
+
+impl Attr {
+    fn json(&self, key: &str) -> String {
+        let value = match self {
+            Self::Str(value) => format!(r#"{{"stringValue":"{}"}}"#, json_escape(value)),
+            // 64-bit integers are strings in OTLP/JSON
+            Self::Int(value) => format!(r#"{{"intValue":"{}"}}"#, value),
+        };
+        format!(r#"{{"key":"{}","value":{}}}"#, json_escape(key), value)
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        write!(hex, "{:02x}", byte).unwrap();
+        hex
+    })
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn span_json() {
+        let mut span = Span::start("cargo \"build\"")
+            .str("steward.exe", "cargo build")
+            .int("steward.exit_code", 1);
+        span.id = [0, 1, 2, 3, 4, 5, 6, 255];
+        span.parent = Some([1; 8]);
+        span.started_at = UNIX_EPOCH + Duration::from_secs(1);
+        span.ended_at = UNIX_EPOCH + Duration::from_secs(2);
+        span.error = Some("exited with code 1".to_string());
+        assert_eq!(
+            span.json("00"),
+            concat!(
+                r#"{"traceId":"00","spanId":"00010203040506ff","name":"cargo \"build\"","kind":1,"#,
+                r#""startTimeUnixNano":"1000000000","endTimeUnixNano":"2000000000","parentSpanId":"0101010101010101","#,
+                r#""attributes":[{"key":"steward.exe","value":{"stringValue":"cargo build"}},"#,
+                r#"{"key":"steward.exit_code","value":{"intValue":"1"}}],"#,
+                r#""status":{"code":2,"message":"exited with code 1"}}"#
+            )
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Outcome of [`Pidfile::claim`].
+pub(crate) enum Claim {
+    Claimed,
+    /// The pidfile points at a running process.
+    Running(u32),
+}
+
+/// Pidfile of a process of a [`ProcessPool`](crate::ProcessPool). See [`PoolOptions::pid_dir`](crate::PoolOptions::pid_dir).
+pub(crate) struct Pidfile {
+    path: PathBuf,
+}
+
+impl Pidfile {
+    pub(crate) fn new(dir: &Path, tag: &str) -> Self {
+        Self {
+            path: dir.join(format!("{}.pid", tag)),
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Pid recorded in the pidfile, if the process is still running, e.g. it was left behind by a crashed pool.
+    pub(crate) fn live_pid(&self) -> Option<u32> {
+        let pid = fs::read_to_string(&self.path).ok()?.trim().parse().ok()?;
+        crate::os::is_running(pid).then_some(pid)
+    }
+
+    /// Creates the pidfile exclusively, so that two pools sharing a directory can't both start the process.
+    /// Until the process is spawned, the pidfile holds the pid of the pool. A stale pidfile is replaced.
+    pub(crate) fn claim(&self) -> io::Result<Claim> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&self.path)
+            {
+                Ok(mut file) => {
+                    writeln!(file, "{}", std::process::id())?;
+                    return Ok(Claim::Claimed);
+                }
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                    if let Some(pid) = self.live_pid() {
+                        return Ok(Claim::Running(pid));
+                    }
+                    match fs::remove_file(&self.path) {
+                        Ok(()) => (),
+                        // Replaced by another pool in the meantime
+                        Err(error) if error.kind() == io::ErrorKind::NotFound => (),
+                        Err(error) => return Err(error),
+                    }
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Records the pid of the spawned process in the claimed pidfile.
+    pub(crate) fn write(&self, pid: u32) -> io::Result<()> {
+        fs::write(&self.path, format!("{}\n", pid))
+    }
+
+    pub(crate) fn remove(&self) {
+        // The pidfile might be removed by hand
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn live_pid() {
+        let dir = std::env::temp_dir().join(format!("steward-pids-{}", std::process::id()));
+        let pidfile = Pidfile::new(&dir, "server");
+        assert_eq!(pidfile.live_pid(), None);
+        assert!(matches!(pidfile.claim().unwrap(), Claim::Claimed));
+        assert_eq!(pidfile.live_pid(), Some(std::process::id()));
+        assert!(
+            matches!(pidfile.claim().unwrap(), Claim::Running(pid) if pid == std::process::id())
+        );
+        pidfile.write(u32::MAX / 2).unwrap();
+        assert_eq!(pidfile.live_pid(), None);
+        // A stale pidfile is replaced
+        assert!(matches!(pidfile.claim().unwrap(), Claim::Claimed));
+        pidfile.remove();
+        assert!(!pidfile.path().exists());
+        fs::remove_dir(&dir).unwrap();
+    }
+}
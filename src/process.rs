@@ -1,35 +1,265 @@
 use std::{
+    borrow::Cow,
+    future::Future,
     io,
-    process::{Output, Stdio},
+    ops::Deref,
+    path::PathBuf,
+    process::{ExitStatus, Output, Stdio},
     sync::{
         atomic::{AtomicBool, AtomicUsize, Ordering},
-        Arc,
+        Arc, Mutex,
     },
     time::{Duration, Instant},
 };
 
 use console::Color;
+use once_cell::sync::OnceCell;
+use regex::Regex;
 use tokio::{
-    io::{AsyncBufReadExt, BufReader},
-    process::{Child, ChildStderr, ChildStdout},
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader},
+    process::{Child, ChildStderr, ChildStdin, ChildStdout},
     signal, task, time,
 };
 
-use crate::{Cmd, Dependency, Error, KillTimeout, Location, Result, SpawnOptions};
+#[cfg(all(target_os = "linux", feature = "cgroup"))]
+use crate::cgroup::TransientCgroup;
+use crate::{
+    dep::SharedDeps,
+    fmt::TagColumn,
+    group::{self, GroupCmd, Groups, Member},
+    handle::{PoolState, Shutdown},
+    pidfile::{Claim, Pidfile},
+    route::Outlet,
+    stagger::{Slot, Starts},
+    Cmd, ColorMode, CrashLoop, Dependency, DependencyWaitError, Env, Error, Exit, Group, Hooks,
+    KillTimeout, Location, PoolEvent, PoolFormat, PoolHandle, ProcessStats, ProxyProcess, Result,
+    RetryPolicy, Route, Service, SpawnOptions, Stagger, StopSignal, Webhook,
+};
 
-/// Long running process. Can be constructed via [`Process::new`](Process::new) or convenience [`process!`](crate::process!) macro.
+/// Long running process. Can be constructed via [`Process::new`](Process::new) or convenience [`process!`](crate::process!) macro,
+/// and configured via its builder methods.
+#[non_exhaustive]
 pub struct Process<Loc> {
     /// Tag used as an identificator in output when process runs as a part of a [`ProcessPool`](ProcessPool).
     pub tag: &'static str,
-    /// [Command](Cmd) to run a process.
-    pub cmd: Cmd<Loc>,
+    /// [Command](Cmd) to run a process. See [`ProcessCmd`](ProcessCmd).
+    pub cmd: ProcessCmd<Loc>,
     /// Amount of time to wait before killing hanged process. See [`KillTimeout`](crate::KillTimeout).
     pub timeout: KillTimeout,
+    /// If set, only lines matching this pattern are displayed in the output of a [`ProcessPool`](ProcessPool).
+    pub filter: Option<Regex>,
+    /// If set, lines matching this pattern are not displayed in the output of a [`ProcessPool`](ProcessPool).
+    pub exclude: Option<Regex>,
+    /// Strip ANSI escape sequences from the lines displayed in the output of a [`ProcessPool`](ProcessPool).
+    pub strip_ansi: bool,
+    /// Restart policy of a process when it runs as a part of a [`ProcessPool`](ProcessPool). See [`Restart`](Restart).
+    pub restart: Restart,
+    /// Delays between restarts of a process and the max number of its starts. See [`RetryPolicy`](crate::RetryPolicy).
+    /// By default, a process is restarted in 1 second, without a limit.
+    pub backoff: RetryPolicy,
+    /// Stdin of a process when it runs as a part of a [`ProcessPool`](ProcessPool). See [`Stdin`](Stdin).
+    pub stdin: Stdin,
+    /// Where stdout of a process goes when it runs as a part of a [`ProcessPool`](ProcessPool). See [`Route`](crate::Route).
+    pub stdout: Route,
+    /// Where stderr of a process goes when it runs as a part of a [`ProcessPool`](ProcessPool). See [`Route`](crate::Route).
+    pub stderr: Route,
+    /// If set, a process that runs as a part of a [`ProcessPool`](ProcessPool) is gracefully stopped and started again
+    /// once it's been running for this long, e.g. to recycle a watcher that leaks memory.
+    pub restart_every: Option<Duration>,
+    /// If set, a process that runs as a part of a [`ProcessPool`](ProcessPool) is gracefully restarted once
+    /// its resident set size (including its child processes) exceeds this number of bytes.
+    /// The usage is sampled every few seconds on Linux and macOS.
+    pub max_rss: Option<u64>,
+    /// If set, how long a process that runs as a part of a [`ProcessPool`](ProcessPool) waits for its dependency,
+    /// regardless of the timeout of the dependency itself. What happens next is defined by [`Process::on_dep_failure`](Process#structfield.on_dep_failure).
+    pub dep_timeout: Option<Duration>,
+    /// What happens when the dependency of a process errors or isn't ready within [`Process::dep_timeout`](Process#structfield.dep_timeout).
+    /// See [`DepFailure`](DepFailure).
+    pub on_dep_failure: DepFailure,
+    /// Resource limits of a process when it runs as a part of a [`ProcessPool`](ProcessPool). See [`CgroupLimits`](crate::CgroupLimits).
+    #[cfg(all(target_os = "linux", feature = "cgroup"))]
+    pub cgroup: Option<crate::CgroupLimits>,
+}
+
+/// Stdin of a process that runs as a part of a [`ProcessPool`](ProcessPool).
+///
+/// By default, processes of a pool don't read from the terminal, so an interactive process can't steal
+/// keystrokes (including `Ctrl+C`) meant for steward.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Stdin {
+    /// Stdin is connected to the null device, so a process reads EOF right away.
+    #[default]
+    Null,
+    /// Stdin is inherited from the current process, i.e. a process reads from the terminal.
+    Inherit,
+    /// Stdin is an open pipe that never receives any input. For processes that exit on EOF, e.g. some watchers.
+    Piped,
+}
+
+impl Stdin {
+    pub(crate) fn stdio(&self) -> Stdio {
+        match self {
+            Self::Null => Stdio::null(),
+            Self::Inherit => Stdio::inherit(),
+            Self::Piped => Stdio::piped(),
+        }
+    }
+}
+
+/// A source of a [`Cmd`](Cmd) of a [`Process`](Process).
+///
+/// Usually, a process is constructed from a ready-made command. But if its environment or working directory
+/// might change between restarts of the process (e.g. credentials get rotated or a dotenv file gets regenerated),
+/// the command can be constructed by a factory on every (re)start of the process.
+///
+/// ```ignore
+/// process! {
+///   tag: "server",
+///   cmd: ProcessCmd::factory(|| cmd! {
+///     "cargo watch",
+///     env: Config::load().to_env(),
+///     pwd: Loc::root(),
+///   }),
+///   timeout: KillTimeout::default(),
+/// }
+/// .restart(Restart::OnFailure)
+/// ```
+pub enum ProcessCmd<Loc> {
+    /// The same command is used on every (re)start of a process.
+    Static(Cmd<Loc>),
+    /// The command is constructed on every (re)start of a process.
+    Factory(Box<dyn Fn() -> Cmd<Loc> + Send + Sync>),
+    /// The command is constructed once, right before the first start of a process
+    /// (e.g. after its dependencies are resolved), and then reused on restarts.
+    Lazy(LazyCmd<Loc>),
+}
+
+type CmdInit<Loc> = Box<dyn FnOnce() -> Cmd<Loc> + Send>;
+
+/// A command that is constructed once, on the first access. See [`ProcessCmd::lazy`](ProcessCmd::lazy).
+pub struct LazyCmd<Loc> {
+    init: Mutex<Option<CmdInit<Loc>>>,
+    cmd: OnceCell<Cmd<Loc>>,
+}
+
+impl<Loc> ProcessCmd<Loc>
+where
+    Loc: Location,
+{
+    /// Constructs a command factory that is called on every (re)start of a process.
+    pub fn factory(f: impl Fn() -> Cmd<Loc> + Send + Sync + 'static) -> Self {
+        Self::Factory(Box::new(f))
+    }
+
+    /// Constructs a command that is built only when and if a process actually starts.
+    /// Useful when construction is expensive, e.g. requires allocating a free port or fetching a token.
+    ///
+    /// ```ignore
+    /// PoolEntry::ProcessWithDep {
+    ///     process: process! {
+    ///         tag: "client",
+    ///         cmd: ProcessCmd::lazy(|| cmd! {
+    ///             "npm start",
+    ///             env: Env::one("API_TOKEN", fetch_token()),
+    ///             pwd: Loc::client(),
+    ///         }),
+    ///     },
+    ///     dependency: Box::new(server),
+    /// }
+    /// ```
+    pub fn lazy(f: impl FnOnce() -> Cmd<Loc> + Send + 'static) -> Self {
+        Self::Lazy(LazyCmd {
+            init: Mutex::new(Some(Box::new(f))),
+            cmd: OnceCell::new(),
+        })
+    }
+
+    /// Returns a command to (re)start a process with.
+    pub(crate) fn get(&self) -> CmdRef<'_, Loc> {
+        match self {
+            Self::Static(cmd) => CmdRef::Borrowed(cmd),
+            Self::Factory(f) => CmdRef::Owned(Box::new(f())),
+            Self::Lazy(LazyCmd { init, cmd }) => CmdRef::Borrowed(cmd.get_or_init(|| {
+                let init = init
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .take()
+                    .expect("Lazy command is initialized only once");
+                init()
+            })),
+        }
+    }
+}
+
+impl<Loc> From<Cmd<Loc>> for ProcessCmd<Loc> {
+    fn from(cmd: Cmd<Loc>) -> Self {
+        Self::Static(cmd)
+    }
+}
+
+pub(crate) enum CmdRef<'a, Loc> {
+    Borrowed(&'a Cmd<Loc>),
+    Owned(Box<Cmd<Loc>>),
+}
+
+impl<Loc> Deref for CmdRef<'_, Loc> {
+    type Target = Cmd<Loc>;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Borrowed(cmd) => cmd,
+            Self::Owned(cmd) => cmd,
+        }
+    }
+}
+
+/// What happens to a process of a [`ProcessPool`](ProcessPool) when its dependency errors
+/// or isn't ready within [`Process::dep_timeout`](Process#structfield.dep_timeout).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DepFailure {
+    /// The process is not executed, while the rest of the pool keeps running.
+    #[default]
+    Skip,
+    /// The pool is shut down and returns [`Error::ProcessFailed`](crate::Error::ProcessFailed).
+    FailPool,
+    /// Once the timeout passes, a warning is printed and the process keeps waiting for its dependency in the background.
+    /// If the dependency errors on its own, the process is not executed.
+    KeepWaiting,
+}
+
+/// Restart policy of a process that runs as a part of a [`ProcessPool`](ProcessPool).
+///
+/// A process is never restarted when the pool is shutting down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Restart {
+    /// A process is not restarted.
+    #[default]
+    Never,
+    /// A process is restarted when it exits with an error.
+    OnFailure,
+    /// A process is restarted whenever it exits.
+    Always,
+}
+
+impl Restart {
+    /// Delay before restarting a process.
+    pub(crate) const DELAY: Duration = Duration::from_secs(1);
+
+    pub(crate) fn applies(&self, res: &Result<ExitResult>) -> bool {
+        match (self, res) {
+            (Self::Never, _) => false,
+            (_, Ok(ExitResult::Interrupted | ExitResult::Killed { .. })) => false,
+            (Self::OnFailure, Ok(ExitResult::Output(_))) => false,
+            (Self::OnFailure, Err(_)) => true,
+            (Self::Always, Ok(ExitResult::Output(_)) | Err(_)) => true,
+        }
+    }
 }
 
 enum TeardownReason {
     CtrlC,
-    ProcessFinished(io::Result<Output>),
+    Stop,
+    ProcessFinished(io::Result<ExitStatus>),
 }
 
 enum CtrlCResult {
@@ -48,8 +278,144 @@ where
     Loc: Location,
 {
     /// Constructs a new process.
-    pub fn new(tag: &'static str, cmd: Cmd<Loc>, timeout: KillTimeout) -> Self {
-        Self { tag, cmd, timeout }
+    pub fn new(tag: &'static str, cmd: impl Into<ProcessCmd<Loc>>, timeout: KillTimeout) -> Self {
+        Self {
+            tag,
+            cmd: cmd.into(),
+            timeout,
+            filter: None,
+            exclude: None,
+            strip_ansi: false,
+            restart: Restart::default(),
+            backoff: RetryPolicy::fixed(Restart::DELAY),
+            stdin: Stdin::default(),
+            stdout: Route::default(),
+            stderr: Route::default(),
+            restart_every: None,
+            max_rss: None,
+            dep_timeout: None,
+            on_dep_failure: DepFailure::default(),
+            #[cfg(all(target_os = "linux", feature = "cgroup"))]
+            cgroup: None,
+        }
+    }
+
+    /// Displays only lines that match the pattern when the process runs as a part of a [`ProcessPool`](ProcessPool).
+    /// The pattern is matched against a line with ANSI escape codes removed.
+    ///
+    /// ```ignore
+    /// process! { tag: "client", cmd: cmd! { ... } }.filter(Regex::new("(?i)error|warning").unwrap())
+    /// ```
+    pub fn filter(mut self, pattern: Regex) -> Self {
+        self.filter = Some(pattern);
+        self
+    }
+
+    /// Hides lines that match the pattern when the process runs as a part of a [`ProcessPool`](ProcessPool).
+    /// The pattern is matched against a line with ANSI escape codes removed.
+    ///
+    /// ```ignore
+    /// process! { tag: "client", cmd: cmd! { ... } }.exclude(Regex::new(r"^\s+asset ").unwrap())
+    /// ```
+    pub fn exclude(mut self, pattern: Regex) -> Self {
+        self.exclude = Some(pattern);
+        self
+    }
+
+    /// Strips ANSI escape sequences (colors, cursor movements etc.) and carriage returns
+    /// from the output of the process when it runs as a part of a [`ProcessPool`](ProcessPool).
+    /// Useful for processes that redraw their output in place and mess up the interleaved output of the pool.
+    pub fn strip_ansi(mut self, strip: bool) -> Self {
+        self.strip_ansi = strip;
+        self
+    }
+
+    /// Sets a restart policy of the process. See [`Restart`](Restart).
+    pub fn restart(mut self, restart: Restart) -> Self {
+        self.restart = restart;
+        self
+    }
+
+    /// Sets delays between restarts of the process. See [`RetryPolicy`](crate::RetryPolicy).
+    ///
+    /// ```ignore
+    /// process! { tag: "worker", cmd: cmd! { ... } }
+    ///     .restart(Restart::OnFailure)
+    ///     .backoff(RetryPolicy::exponential(Duration::from_secs(1), Duration::from_secs(30)).max_attempts(10))
+    /// ```
+    pub fn backoff(mut self, policy: RetryPolicy) -> Self {
+        self.backoff = policy;
+        self
+    }
+
+    /// Sets stdin of the process. See [`Stdin`](Stdin).
+    pub fn stdin(mut self, stdin: Stdin) -> Self {
+        self.stdin = stdin;
+        self
+    }
+
+    /// Sets where stdout of the process goes. See [`Route`](crate::Route).
+    pub fn stdout(mut self, route: Route) -> Self {
+        self.stdout = route;
+        self
+    }
+
+    /// Sets where stderr of the process goes. See [`Route`](crate::Route).
+    pub fn stderr(mut self, route: Route) -> Self {
+        self.stderr = route;
+        self
+    }
+
+    /// Restarts the process once it's been running for the given duration. The process is stopped
+    /// the same way as on shutdown of the pool, and the restart doesn't count towards its [`backoff`](Process::backoff).
+    ///
+    /// ```ignore
+    /// process! { tag: "client", cmd: cmd! { "webpack --watch", ... } }.restart_every(Duration::from_secs(4 * 60 * 60))
+    /// ```
+    pub fn restart_every(mut self, interval: Duration) -> Self {
+        self.restart_every = Some(interval);
+        self
+    }
+
+    /// Restarts the process once its memory usage exceeds the given number of bytes,
+    /// so a single process can't swallow all the memory during a long session.
+    /// The restart doesn't count towards its [`backoff`](Process::backoff).
+    ///
+    /// ```ignore
+    /// process! { tag: "client", cmd: cmd! { "webpack --watch", ... } }.max_rss(4 * 1024 * 1024 * 1024)
+    /// ```
+    pub fn max_rss(mut self, bytes: u64) -> Self {
+        self.max_rss = Some(bytes);
+        self
+    }
+
+    /// Sets how long the process waits for its dependency when it runs as a part of a [`ProcessPool`](ProcessPool),
+    /// and what happens if the dependency isn't ready by then. See [`DepFailure`](DepFailure).
+    ///
+    /// ```ignore
+    /// PoolEntry::ProcessWithDep {
+    ///     process: client::watch().dep_timeout(Duration::from_secs(60), DepFailure::KeepWaiting),
+    ///     dependency: Box::new(server::http()),
+    /// }
+    /// ```
+    pub fn dep_timeout(mut self, timeout: Duration, on_failure: DepFailure) -> Self {
+        self.dep_timeout = Some(timeout);
+        self.on_dep_failure = on_failure;
+        self
+    }
+
+    /// Sets what happens when the dependency of the process errors. See [`DepFailure`](DepFailure).
+    pub fn on_dep_failure(mut self, on_failure: DepFailure) -> Self {
+        self.on_dep_failure = on_failure;
+        self
+    }
+
+    /// Confines the process within a transient cgroup with the given limits when it runs as a part of a [`ProcessPool`](ProcessPool).
+    /// See [`CgroupLimits`](crate::CgroupLimits).
+    #[cfg(all(target_os = "linux", feature = "cgroup"))]
+    pub fn cgroup(mut self, limits: crate::CgroupLimits) -> Self {
+        self.cgroup = Some(limits);
+        self
     }
 
     /// Returns a tag of a process.
@@ -58,7 +424,7 @@ where
     }
 
     /// Returns a command of a process.
-    pub fn cmd(&self) -> &Cmd<Loc> {
+    pub fn cmd(&self) -> &ProcessCmd<Loc> {
         &self.cmd
     }
 
@@ -69,8 +435,8 @@ where
 
     /// Spawns a process and returns a [`RunningProcess`](RunningProcess),
     /// which includes a [`Child`](tokio::process::Child).
-    pub async fn spawn(&self, opts: SpawnOptions) -> io::Result<RunningProcess> {
-        self.cmd().spawn(opts)
+    pub async fn spawn(&self, opts: SpawnOptions) -> Result<RunningProcess> {
+        self.cmd().get().spawn(opts)
     }
 }
 
@@ -124,6 +490,12 @@ macro_rules! process {
 pub struct RunningProcess {
     pub(crate) process: Child,
     pub(crate) timeout: KillTimeout,
+    pub(crate) stop_signal: StopSignal,
+    /// Whether the process is in its own process group, so it doesn't receive `Ctrl+C` from the terminal.
+    pub(crate) detached: bool,
+    /// Whether the process is paused via [`ProcessHandle::pause`](crate::ProcessHandle::pause),
+    /// so it's resumed along with its descendants on teardown.
+    pub(crate) paused: Arc<AtomicBool>,
 }
 
 impl RunningProcess {
@@ -137,6 +509,12 @@ impl RunningProcess {
         self.process
     }
 
+    /// Takes stdin of the process, if it was spawned with the piped stdin.
+    /// Once the handle is dropped, the process reads EOF.
+    pub fn stdin(&mut self) -> Option<ChildStdin> {
+        self.process.stdin.take()
+    }
+
     pub(crate) fn stdout(&mut self) -> Option<ChildStdout> {
         self.process.stdout.take()
     }
@@ -145,222 +523,543 @@ impl RunningProcess {
         self.process.stderr.take()
     }
 
-    pub(crate) async fn wait(self) -> Result<ExitResult> {
-        let process = self.process;
+    /// Waits for a process to exit. Once `stop` resolves, the process is asked to exit (via `SIGINT` on Unix)
+    /// and killed if it doesn't exit within its timeout.
+    pub(crate) async fn wait_until(self, stop: impl Future<Output = ()>) -> Result<ExitResult> {
+        self.wait_until_or_kill(stop, std::future::pending()).await
+    }
+
+    /// Same as [`RunningProcess::wait_until`](RunningProcess::wait_until), but once `kill` resolves,
+    /// the interrupted process is killed without waiting for its timeout.
+    pub(crate) async fn wait_until_or_kill(
+        self,
+        stop: impl Future<Output = ()>,
+        kill: impl Future<Output = ()>,
+    ) -> Result<ExitResult> {
+        // The child is owned (and reaped) here only, so its pid can't be reused by another process
+        // until it's waited for, and signals never hit an unrelated process
+        let mut process = self.process;
 
         let pid = match process.id() {
             Some(pid) => pid,
             None => return Err(Error::ProcessDoesNotExist),
         };
 
-        let process_exited = Arc::new(AtomicBool::new(false));
-
-        let exit_reason = {
-            let process_exited = process_exited.clone();
-
-            let process_task = task::spawn(async move {
-                let res = process.wait_with_output().await;
-                process_exited.store(true, Ordering::SeqCst);
-                res
-            });
-
-            tokio::select! {
-                result =
-                  process_task =>
-                    TeardownReason::ProcessFinished(
-                      result.unwrap_or_else(|err| Err(io::Error::new(io::ErrorKind::Other, err)))
-                    ),
-                _ = signal::ctrl_c() => TeardownReason::CtrlC,
-            }
+        // Pipes are read concurrently, so a process doesn't block on a full pipe
+        let stdout = process
+            .stdout
+            .take()
+            .map(|pipe| task::spawn(self::read_to_end(pipe)));
+        let stderr = process
+            .stderr
+            .take()
+            .map(|pipe| task::spawn(self::read_to_end(pipe)));
+
+        let exit_reason = tokio::select! {
+            status = process.wait() => TeardownReason::ProcessFinished(status),
+            _ = signal::ctrl_c() => TeardownReason::CtrlC,
+            _ = stop => TeardownReason::Stop,
         };
 
         match exit_reason {
-            TeardownReason::ProcessFinished(result) => {
-                let output = result?;
+            TeardownReason::ProcessFinished(status) => {
+                let output = Output {
+                    status: status?,
+                    stdout: crate::executor::collect(stdout).await,
+                    stderr: crate::executor::collect(stderr).await,
+                };
                 if output.status.success() {
                     Ok(ExitResult::Output(output))
                 } else {
                     Err(output.into())
                 }
             }
-            TeardownReason::CtrlC => {
-                let res = {
-                    let process_exited = process_exited.clone();
-                    let exit_checker = task::spawn(async move {
-                        loop {
-                            if process_exited.load(Ordering::SeqCst) {
-                                break;
-                            }
-                        }
-                    });
-                    tokio::select! {
-                        _ = exit_checker => CtrlCResult::ProcessExited,
-                        _ = time::sleep(*self.timeout) => CtrlCResult::Timeout,
+            TeardownReason::CtrlC | TeardownReason::Stop => {
+                // The process might have exited right as the teardown started
+                if let Ok(Some(_)) = process.try_wait() {
+                    return Ok(ExitResult::Interrupted);
+                }
+                // Descendants of a paused process are frozen as well, so they are torn down along with it.
+                // Otherwise, they'd be left stopped with their signals pending once the process exits.
+                let descendants = if self.paused.load(Ordering::Relaxed) {
+                    let descendants = Self::descendants(pid);
+                    Self::resume(pid, self.detached)?;
+                    descendants
+                } else {
+                    vec![]
+                };
+                // On Ctrl+C, the process receives SIGINT from the terminal on its own, unless it's detached
+                let signal = match exit_reason {
+                    TeardownReason::Stop => Some(self.stop_signal),
+                    _ if self.detached => Some(StopSignal::Interrupt),
+                    _ => None,
+                };
+                if let Some(signal) = signal {
+                    Self::interrupt(pid, signal)?;
+                    for x in &descendants {
+                        // A descendant might be gone by now
+                        Self::interrupt(*x, signal).ok();
                     }
+                }
+                let deadline = time::Instant::now() + *self.timeout;
+                tokio::pin!(kill);
+                let res = tokio::select! {
+                    _ = process.wait() => CtrlCResult::ProcessExited,
+                    _ = time::sleep_until(deadline) => CtrlCResult::Timeout,
+                    _ = &mut kill => CtrlCResult::Timeout,
                 };
 
-                match res {
+                let res = match res {
                     CtrlCResult::ProcessExited => Ok(ExitResult::Interrupted),
-                    CtrlCResult::Timeout => match Self::kill(pid) {
-                        Ok(()) => Ok(ExitResult::Killed { pid }),
-                        Err(err) => Err(err),
+                    CtrlCResult::Timeout => match process.try_wait() {
+                        // The process exited right as the timeout elapsed
+                        Ok(Some(_)) => Ok(ExitResult::Interrupted),
+                        Ok(None) | Err(_) => {
+                            Self::kill(pid)?;
+                            // Reaps the killed process
+                            let _ = process.wait().await;
+                            Ok(ExitResult::Killed { pid })
+                        }
                     },
+                };
+                if !descendants.is_empty() {
+                    Self::kill_survivors(descendants, deadline, kill).await;
                 }
+                res
+            }
+        }
+    }
+
+    /// Waits for the descendants of a torn down process to exit until the deadline, and kills the remaining ones.
+    async fn kill_survivors(
+        mut descendants: Vec<u32>,
+        deadline: time::Instant,
+        kill: impl Future<Output = ()>,
+    ) {
+        tokio::pin!(kill);
+        loop {
+            descendants.retain(|x| crate::os::is_running(*x));
+            if descendants.is_empty() {
+                return;
+            }
+            tokio::select! {
+                _ = time::sleep(Duration::from_millis(50)) => (),
+                _ = time::sleep_until(deadline) => break,
+                _ = &mut kill => break,
             }
         }
+        for x in descendants {
+            // A descendant might be gone by now
+            crate::os::kill(x).ok();
+        }
     }
 
     /// Tries to safely terminate a running process. If the termination didn't succeed, tries to kill it.
     #[cfg(unix)]
     pub async fn stop(mut self) -> Result<()> {
-        use nix::{
-            sys::signal::{self, Signal},
-            unistd::Pid,
-        };
+        use nix::{sys::signal, unistd::Pid};
 
+        let stop_signal = self.stop_signal.to_nix();
         match self.process.id() {
             None => Err(Error::ProcessDoesNotExist),
-            Some(pid) => match signal::kill(Pid::from_raw(pid as i32), Signal::SIGINT) {
-                Ok(()) => {
-                    let process = &mut self.process;
-
-                    let res = tokio::select! {
-                        res = process.wait() => Some(res),
-                        _ = time::sleep(*self.timeout) => None,
-                    };
-
-                    match res {
-                        Some(Ok(_)) => Ok(()),
-                        Some(Err(error)) => {
-                            eprintln!("⚠️ IO error on SIGINT: {error}. Killing the process {pid}.");
-                            Self::kill(pid)
-                        }
-                        None => {
-                            eprintln!("⚠️ SIGINT timeout. Killing the process {pid}.");
-                            Self::kill(pid)
+            Some(pid) => {
+                match signal::kill(Pid::from_raw(pid as i32), stop_signal) {
+                    Ok(()) => {
+                        let process = &mut self.process;
+
+                        let res = tokio::select! {
+                            res = process.wait() => Some(res),
+                            _ = time::sleep(*self.timeout) => None,
+                        };
+
+                        match res {
+                            Some(Ok(_)) => Ok(()),
+                            Some(Err(error)) => {
+                                console!("⚠️ IO error on {stop_signal}: {error}. Killing the process {pid}.");
+                                Self::kill(pid)
+                            }
+                            None => {
+                                console!("⚠️ {stop_signal} timeout. Killing the process {pid}.");
+                                Self::kill(pid)
+                            }
                         }
                     }
+                    Err(error) => {
+                        console!("⚠️ Failed to terminate the process {pid}. {error}. Killing it.");
+                        Self::kill(pid)
+                    }
                 }
-                Err(error) => {
-                    eprintln!("⚠️ Failed to terminate the process {pid}. {error}. Killing it.");
-                    Self::kill(pid)
-                }
-            },
+            }
         }
     }
 
     // TODO: Implemetn RunningProcess::stop for windows
 
+    /// Asks a process to exit.
     #[cfg(unix)]
-    pub(crate) fn kill(pid: u32) -> Result<()> {
-        use nix::{
-            sys::signal::{self, Signal},
-            unistd::Pid,
-        };
+    pub(crate) fn interrupt(pid: u32, signal: StopSignal) -> Result<()> {
+        Self::signal(pid, signal.to_nix())?;
+        // A paused process handles the signal only once it's resumed
+        Self::signal(pid, nix::sys::signal::Signal::SIGCONT)
+    }
 
-        signal::kill(Pid::from_raw(pid as i32), Signal::SIGKILL)
-            .map_err(|err| Error::Zombie { pid, err })
+    /// Freezes a process along with its descendants, e.g. the actual workload of a compound shell command.
+    /// A process that leads its own process `group` is frozen via the group. See [`ProcessHandle::pause`](crate::ProcessHandle::pause).
+    #[cfg(unix)]
+    pub(crate) fn pause(pid: u32, group: bool) -> Result<()> {
+        Self::signal_tree(pid, group, nix::sys::signal::Signal::SIGSTOP)
     }
 
-    #[cfg(windows)]
-    pub(crate) fn kill(pid: u32) -> Result<()> {
-        use winapi::{
-            shared::{
-                minwindef::{BOOL, DWORD, FALSE, UINT},
-                ntdef::NULL,
-            },
-            um::{
-                errhandlingapi::GetLastError,
-                handleapi::CloseHandle,
-                processthreadsapi::{OpenProcess, TerminateProcess},
-                winnt::{HANDLE, PROCESS_TERMINATE},
-            },
-        };
+    /// Resumes a frozen process along with its descendants. See [`ProcessHandle::resume`](crate::ProcessHandle::resume).
+    #[cfg(unix)]
+    pub(crate) fn resume(pid: u32, group: bool) -> Result<()> {
+        Self::signal_tree(pid, group, nix::sys::signal::Signal::SIGCONT)
+    }
 
-        // since we only wish to kill the process
-        const DESIRED_ACCESS: DWORD = PROCESS_TERMINATE;
+    #[cfg(unix)]
+    fn signal_tree(pid: u32, group: bool, signal: nix::sys::signal::Signal) -> Result<()> {
+        if group {
+            return nix::sys::signal::killpg(nix::unistd::Pid::from_raw(pid as i32), signal)
+                .map_err(|err| Error::IoError(io::Error::other(err)));
+        }
+        // The process is signaled first, so a frozen one doesn't spawn more descendants while they are looked up
+        Self::signal(pid, signal)?;
+        for x in Self::descendants(pid) {
+            // A descendant might be gone by now
+            Self::signal(x, signal).ok();
+        }
+        Ok(())
+    }
 
-        const INHERIT_HANDLE: BOOL = FALSE;
+    fn descendants(pid: u32) -> Vec<u32> {
+        match crate::os::processes() {
+            Some(procs) => crate::os::tree(pid, &procs).split_off(1),
+            None => vec![],
+        }
+    }
 
-        // for some reason windows doesn't have any exit codes,
-        // you just use what ever you want?
-        //
-        // so we're using exit code `0` then
-        const EXIT_CODE: UINT = 0;
+    #[cfg(unix)]
+    fn signal(pid: u32, signal: nix::sys::signal::Signal) -> Result<()> {
+        nix::sys::signal::kill(nix::unistd::Pid::from_raw(pid as i32), signal)
+            .map_err(|err| Error::IoError(io::Error::other(err)))
+    }
 
-        // windows being window you have to call this a lot
-        // so i just extracted it to its own function
-        unsafe fn get_error(pid: u32) -> Result<()> {
-            // https://docs.microsoft.com/en-us/windows/win32/api/errhandlingapi/nf-errhandlingapi-getlasterror
-            let err: DWORD = GetLastError();
+    /// Freezes a process along with its descendants by suspending their threads.
+    #[cfg(windows)]
+    pub(crate) fn pause(pid: u32, _group: bool) -> Result<()> {
+        Self::threads_tree(pid, |thread| unsafe {
+            winapi::um::processthreadsapi::SuspendThread(thread) != u32::MAX
+        })
+    }
 
-            Err(Error::Zombie { pid, err })
+    /// Resumes a frozen process along with its descendants by resuming their threads.
+    #[cfg(windows)]
+    pub(crate) fn resume(pid: u32, _group: bool) -> Result<()> {
+        Self::threads_tree(pid, |thread| unsafe {
+            winapi::um::processthreadsapi::ResumeThread(thread) != u32::MAX
+        })
+    }
+
+    #[cfg(windows)]
+    fn threads_tree(pid: u32, f: impl Fn(winapi::um::winnt::HANDLE) -> bool) -> Result<()> {
+        Self::threads(pid, &f)?;
+        for x in Self::descendants(pid) {
+            // A descendant might be gone by now
+            Self::threads(x, &f).ok();
         }
+        Ok(())
+    }
 
-        unsafe {
-            // https://docs.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-openprocess
-            let handle: HANDLE = OpenProcess(DESIRED_ACCESS, INHERIT_HANDLE, pid);
-            if handle == NULL {
-                get_error(pid)?;
-            }
+    /// Applies `f` to every thread of a process. There is no documented API to suspend a process as a whole,
+    /// so its threads are enumerated via a snapshot.
+    #[cfg(windows)]
+    fn threads(pid: u32, f: impl Fn(winapi::um::winnt::HANDLE) -> bool) -> Result<()> {
+        use std::mem;
+
+        use winapi::{
+            shared::minwindef::{DWORD, FALSE},
+            um::{
+                handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
+                processthreadsapi::OpenThread,
+                tlhelp32::{
+                    CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD,
+                    THREADENTRY32,
+                },
+                winnt::THREAD_SUSPEND_RESUME,
+            },
+        };
 
-            // https://docs.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-terminateprocess
-            let terminate_result: BOOL = TerminateProcess(handle, EXIT_CODE);
-            if terminate_result == FALSE {
-                get_error(pid)?;
+        unsafe {
+            // https://docs.microsoft.com/en-us/windows/win32/api/tlhelp32/nf-tlhelp32-createtoolhelp32snapshot
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+            if snapshot == INVALID_HANDLE_VALUE {
+                return Err(Error::IoError(io::Error::last_os_error()));
             }
 
-            // https://docs.microsoft.com/en-us/windows/win32/api/handleapi/nf-handleapi-closehandle
-            let close_result: BOOL = CloseHandle(handle);
-            if close_result == FALSE {
-                get_error(pid)?;
+            let mut entry: THREADENTRY32 = mem::zeroed();
+            entry.dwSize = mem::size_of::<THREADENTRY32>() as DWORD;
+            let mut res = Ok(());
+            let mut more = Thread32First(snapshot, &mut entry) != FALSE;
+            while more {
+                if entry.th32OwnerProcessID == pid {
+                    let thread = OpenThread(THREAD_SUSPEND_RESUME, FALSE, entry.th32ThreadID);
+                    if thread.is_null() || !f(thread) {
+                        res = Err(Error::IoError(io::Error::last_os_error()));
+                    }
+                    if !thread.is_null() {
+                        CloseHandle(thread);
+                    }
+                }
+                more = Thread32Next(snapshot, &mut entry) != FALSE;
             }
+            CloseHandle(snapshot);
+            res
         }
+    }
 
-        Ok(())
+    /// Asks a process to exit. There are no signals on Windows, so the process is killed right away.
+    #[cfg(windows)]
+    pub(crate) fn interrupt(pid: u32, _signal: StopSignal) -> Result<()> {
+        Self::kill(pid)
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn kill(pid: u32) -> Result<()> {
+        use nix::errno::Errno;
+
+        crate::os::kill(pid).map_err(|err| Error::Zombie {
+            pid,
+            err: nix::Error::Sys(Errno::from_i32(err.raw_os_error().unwrap_or_default())),
+        })
+    }
+
+    #[cfg(windows)]
+    pub(crate) fn kill(pid: u32) -> Result<()> {
+        crate::os::kill(pid).map_err(|err| Error::Zombie {
+            pid,
+            err: err.raw_os_error().unwrap_or_default() as u32,
+        })
     }
 }
 
 /// Entry of a [`ProcessPool`](ProcessPool) when some of the processes depend on something.
 /// It is used as an input to the [`ProcessPool::run_with_deps`](ProcessPool::run_with_deps) method.
 /// See [`dep`](crate::dep) module documentation.
+///
+/// More kinds of entries might be added, so a `match` on it needs a wildcard arm.
+#[non_exhaustive]
 pub enum PoolEntry<Loc, Dep: ?Sized> {
     /// An indipendent long-running process.
     Process(Process<Loc>),
     /// A long-running process that depends on some other thing.
     ProcessWithDep {
-        /// The process.
+        /// The process. Its own timeout of waiting for the dependency is set via [`Process::dep_timeout`](Process::dep_timeout).
         process: Process<Loc>,
         /// The dependency. See [`Dependency`](Dependency).
         dependency: Box<Dep>,
     },
+    /// A named group of entries with its own restart and shutdown policies. See [`Group`](crate::Group).
+    Group(Group<Loc>),
+    /// A TCP proxy that runs within the pool. See [`ProxyProcess`](crate::ProxyProcess).
+    Proxy(ProxyProcess),
+    /// A process with setup and teardown commands and a readiness check. See [`Service`](crate::Service).
+    Service(Box<Service<Loc>>),
+    /// A long-running process that starts only after a one-off command completes successfully within the same run,
+    /// e.g. a server after DB migrations. The output of the command goes under the tag of the process.
+    /// If the command fails, the process is not executed.
+    ///
+    /// ```ignore
+    /// PoolEntry::ProcessAfterCmd {
+    ///     process: server::watch(),
+    ///     cmd: Box::new(db::migrate()),
+    /// }
+    /// ```
+    ProcessAfterCmd {
+        /// The process.
+        process: Process<Loc>,
+        /// The command.
+        cmd: Box<Cmd<Loc>>,
+    },
 }
 
-impl<Loc> PoolEntry<Loc, dyn Dependency>
+impl<Loc, Dep: ?Sized> From<Process<Loc>> for PoolEntry<Loc, Dep> {
+    fn from(process: Process<Loc>) -> Self {
+        Self::Process(process)
+    }
+}
+
+impl<Loc, Dep: ?Sized> From<Group<Loc>> for PoolEntry<Loc, Dep> {
+    fn from(group: Group<Loc>) -> Self {
+        Self::Group(group)
+    }
+}
+
+impl<Loc, Dep: ?Sized> From<ProxyProcess> for PoolEntry<Loc, Dep> {
+    fn from(proxy: ProxyProcess) -> Self {
+        Self::Proxy(proxy)
+    }
+}
+
+impl<Loc, Dep: ?Sized> From<Service<Loc>> for PoolEntry<Loc, Dep> {
+    fn from(service: Service<Loc>) -> Self {
+        Self::Service(Box::new(service))
+    }
+}
+
+/// Convenience macro for assembling a pool of [`PoolEntry`](PoolEntry)es from processes
+/// and entries with dependencies. Conditional entries can be added via `Option`s.
+///
+/// ```ignore
+/// ProcessPool::run_with_deps(pool![
+///     server::watch(),
+///     PoolEntry::ProcessWithDep {
+///         process: client::watch(),
+///         dependency: Box::new(server::http()),
+///     },
+///     if with_docs { Some(docs::watch()) } else { None },
+/// ])
+/// .await
+/// ```
+#[macro_export]
+macro_rules! pool {
+    [$($entry:expr),*$(,)?] => {{
+        let mut pool: ::std::vec::Vec<$crate::PoolEntry<_, dyn $crate::Dependency>> = ::std::vec::Vec::new();
+        $($crate::process::PoolExtend::extend_pool($entry, &mut pool);)*
+        pool
+    }};
+}
+
+/// Helper trait used by the [`pool!`](crate::pool!) macro to add an entry to a pool.
+#[doc(hidden)]
+pub trait PoolExtend<Loc> {
+    fn extend_pool(self, pool: &mut Vec<PoolEntry<Loc, dyn Dependency>>);
+}
+
+impl<Loc> PoolExtend<Loc> for Process<Loc> {
+    fn extend_pool(self, pool: &mut Vec<PoolEntry<Loc, dyn Dependency>>) {
+        pool.push(PoolEntry::Process(self))
+    }
+}
+
+impl<Loc> PoolExtend<Loc> for Group<Loc> {
+    fn extend_pool(self, pool: &mut Vec<PoolEntry<Loc, dyn Dependency>>) {
+        pool.push(PoolEntry::Group(self))
+    }
+}
+
+impl<Loc> PoolExtend<Loc> for ProxyProcess {
+    fn extend_pool(self, pool: &mut Vec<PoolEntry<Loc, dyn Dependency>>) {
+        pool.push(PoolEntry::Proxy(self))
+    }
+}
+
+impl<Loc> PoolExtend<Loc> for Service<Loc> {
+    fn extend_pool(self, pool: &mut Vec<PoolEntry<Loc, dyn Dependency>>) {
+        pool.push(PoolEntry::Service(Box::new(self)))
+    }
+}
+
+impl<Loc> PoolExtend<Loc> for PoolEntry<Loc, dyn Dependency> {
+    fn extend_pool(self, pool: &mut Vec<PoolEntry<Loc, dyn Dependency>>) {
+        pool.push(self)
+    }
+}
+
+impl<Loc, Dep> PoolExtend<Loc> for PoolEntry<Loc, Dep>
 where
-    Loc: Location + 'static,
+    Dep: Dependency + 'static,
 {
-    fn process(&self) -> &Process<Loc> {
-        match self {
-            Self::Process(process) => process,
+    fn extend_pool(self, pool: &mut Vec<PoolEntry<Loc, dyn Dependency>>) {
+        pool.push(match self {
+            Self::Process(process) => PoolEntry::Process(process),
             Self::ProcessWithDep {
                 process,
-                dependency: _,
-            } => process,
+                dependency,
+            } => PoolEntry::ProcessWithDep {
+                process,
+                dependency,
+            },
+            Self::Group(group) => PoolEntry::Group(group),
+            Self::Proxy(proxy) => PoolEntry::Proxy(proxy),
+            Self::ProcessAfterCmd { process, cmd } => PoolEntry::ProcessAfterCmd { process, cmd },
+            Self::Service(service) => PoolEntry::Service(service),
+        })
+    }
+}
+
+impl<Loc, T> PoolExtend<Loc> for Option<T>
+where
+    T: PoolExtend<Loc>,
+{
+    fn extend_pool(self, pool: &mut Vec<PoolEntry<Loc, dyn Dependency>>) {
+        if let Some(entry) = self {
+            entry.extend_pool(pool)
         }
     }
+}
 
-    fn take(self) -> (Process<Loc>, Option<Box<dyn Dependency>>) {
-        match self {
-            Self::Process(process) => (process, None),
-            Self::ProcessWithDep {
-                process,
-                dependency,
-            } => (process, Some(dependency)),
+impl<Loc, T> PoolExtend<Loc> for Vec<T>
+where
+    T: PoolExtend<Loc>,
+{
+    fn extend_pool(self, pool: &mut Vec<PoolEntry<Loc, dyn Dependency>>) {
+        for entry in self {
+            entry.extend_pool(pool)
         }
     }
 }
 
+/// Options of a [`ProcessPool`](ProcessPool).
+#[derive(Default)]
+pub struct PoolOptions {
+    /// Formatting of the pool output. See [`PoolFormat`](PoolFormat).
+    pub format: PoolFormat,
+    /// Setup and teardown hooks around the pool run. See [`Hooks`](crate::Hooks).
+    pub hooks: Hooks,
+    /// Crash-loop detection of the processes. Disabled by default. See [`CrashLoop`](crate::CrashLoop).
+    pub crash_loop: Option<CrashLoop>,
+    /// Whether to shut the pool down once any process fails, i.e. fails to spawn or exits with an error
+    /// and is not going to be restarted. The pool then returns [`Error::ProcessFailed`](crate::Error::ProcessFailed).
+    /// By default, the rest of the pool keeps running.
+    pub fail_fast: bool,
+    /// Webhook that is notified when a process crashes, gets killed due to timeout, or the pool shuts down.
+    /// See [`Webhook`](crate::Webhook).
+    pub webhook: Option<Webhook>,
+    /// Overall deadline of waiting for the dependencies, counted from the start of the pool.
+    /// Once it passes, the processes whose dependencies are still pending are not executed,
+    /// regardless of the timeouts of the dependencies. Unlimited by default.
+    pub deps_deadline: Option<Duration>,
+    /// Environment shared by the processes, e.g. `RUST_LOG`. Values set in the environment of a process take precedence.
+    pub env: Env,
+    /// Kill timeout of the processes that don't have a specific one, i.e. use [`KillTimeout::default`](crate::KillTimeout::default).
+    /// If not set, the default is resolved once the pool starts. See [`KillTimeout`](crate::KillTimeout).
+    pub default_timeout: Option<Duration>,
+    /// If set, a pidfile of every process is written to this directory as `<tag>.pid` (e.g. `.steward/pids/server.pid`)
+    /// while the process is running. The pidfile is created exclusively right before the process is spawned:
+    /// a process whose pidfile points at a running process, e.g. one left behind by a crashed pool or started by another
+    /// pool sharing the directory, is not executed. See also [`os::terminate`](crate::os::terminate).
+    pub pid_dir: Option<PathBuf>,
+    /// Staggered startup of the processes, so they don't all start at once. Disabled by default.
+    /// See [`Stagger`](crate::Stagger).
+    pub stagger: Option<Stagger>,
+    /// System log that output of the processes is forwarded to, in addition to the console.
+    /// See [`LogSink`](crate::LogSink).
+    #[cfg(all(unix, feature = "syslog"))]
+    pub log_sink: Option<crate::LogSink>,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Deadline of the dependencies exceeded")]
+struct DepsDeadlineError;
+
+impl DependencyWaitError for DepsDeadlineError {}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Not ready within {}", crate::fmt::duration(*.0))]
+struct DepTimeoutError(Duration);
+
+impl DependencyWaitError for DepTimeoutError {}
+
 /// Struct to run a pool of long-running processes.
 ///
 /// ```ignore
@@ -369,36 +1068,118 @@ where
 pub struct ProcessPool;
 
 impl ProcessPool {
-    /// Runs a pool of long-running processes.
-    pub async fn run<Loc>(pool: Vec<Process<Loc>>) -> Result<()>
+    /// Runs a pool of long-running processes until a user presses `Ctrl+C`.
+    pub async fn run<Loc>(pool: impl IntoIterator<Item = Process<Loc>>) -> Result<()>
     where
         Loc: Location + 'static,
     {
-        let pool = pool.into_iter().map(|p| PoolEntry::Process(p)).collect();
-        ProcessPool::runner::<Loc>(pool).await
+        ProcessPool::start(pool, PoolOptions::default())
+            .wait()
+            .await
     }
 
     /// Runs a pool of long-running processes, some of which depend on something,
     /// such as an HTTP service being available or a file existing.
     /// See [`dep`](crate::dep) module documentation.
-    pub async fn run_with_deps<Loc>(pool: Vec<PoolEntry<Loc, dyn Dependency>>) -> Result<()>
+    ///
+    /// Accepts anything that can be converted into a [`PoolEntry`](PoolEntry), including plain [`Process`](Process)es.
+    /// See also [`pool!`](crate::pool!) macro.
+    pub async fn run_with_deps<Loc>(
+        pool: impl IntoIterator<Item = impl Into<PoolEntry<Loc, dyn Dependency>>>,
+    ) -> Result<()>
     where
         Loc: Location + 'static,
     {
-        ProcessPool::runner(pool).await
+        ProcessPool::start(pool, PoolOptions::default())
+            .wait()
+            .await
     }
 
-    async fn runner<Loc>(pool: Vec<PoolEntry<Loc, dyn Dependency>>) -> Result<()>
+    /// Same as [`ProcessPool::run_with_deps`](ProcessPool::run_with_deps) but with the provided [`PoolOptions`](PoolOptions).
+    /// Hooks of the options run around the whole pool run, i.e. `after` hooks run once the pool is shut down.
+    ///
+    /// ```ignore
+    /// let opts = PoolOptions {
+    ///     format: PoolFormat {
+    ///         max_tag_width: Some(8),
+    ///         long_lines: LongLines::Wrap,
+    ///         ..Default::default()
+    ///     },
+    ///     ..Default::default()
+    /// };
+    ///
+    /// ProcessPool::run_with_options(vec![server, client], opts).await
+    /// ```
+    pub async fn run_with_options<Loc>(
+        pool: impl IntoIterator<Item = impl Into<PoolEntry<Loc, dyn Dependency>>>,
+        opts: PoolOptions,
+    ) -> Result<()>
+    where
+        Loc: Location + 'static,
+    {
+        ProcessPool::start(pool, opts).wait().await
+    }
+
+    /// Starts a pool in the background and returns a [`PoolHandle`](crate::PoolHandle) to it,
+    /// which provides statistics of the processes and can shut the pool down.
+    /// The pool is also shut down when a user presses `Ctrl+C`. Must be called within a Tokio runtime.
+    ///
+    /// If several processes share a tag or a tag is empty, the pool is not started,
+    /// and waiting for it fails with [`Error::InvalidPool`](crate::Error::InvalidPool).
+    pub fn start<Loc>(
+        pool: impl IntoIterator<Item = impl Into<PoolEntry<Loc, dyn Dependency>>>,
+        opts: PoolOptions,
+    ) -> PoolHandle
+    where
+        Loc: Location + 'static,
+    {
+        let pool: Vec<PoolEntry<Loc, dyn Dependency>> = pool.into_iter().map(Into::into).collect();
+        let (mut pool, proxies, groups) = Groups::flatten(pool);
+        let state = Arc::new(PoolState::new(
+            pool.iter()
+                .map(|x| (x.process.tag(), x.readiness.is_some())),
+        ));
+        let tags = pool
+            .iter()
+            .map(|x| x.process.tag())
+            .chain(proxies.iter().map(|x| x.tag));
+        let valid = self::validate_tags(tags)
+            .and_then(|()| self::resolve_timeouts(&mut pool, opts.default_timeout));
+        if let Err(error) = valid {
+            state.shutdown(Shutdown::Requested);
+            return PoolHandle::new(state, task::spawn(async move { Err(error) }));
+        }
+        let task = {
+            let state = state.clone();
+            task::spawn(async move {
+                opts.hooks
+                    .around(ProcessPool::runner(pool, proxies, groups, &opts, state))
+                    .await
+            })
+        };
+        PoolHandle::new(state, task)
+    }
+
+    async fn runner<Loc>(
+        pool: Vec<Member<Loc>>,
+        proxies: Vec<ProxyProcess>,
+        groups: Groups,
+        opts: &PoolOptions,
+        state: Arc<PoolState>,
+    ) -> Result<()>
     where
         Loc: Location + 'static,
     {
         let pool_size = pool.len();
         let exited_processes = Arc::new(AtomicUsize::new(0));
 
+        #[cfg(feature = "otel")]
+        let pool_span = crate::otel::Span::start("pool").int("steward.processes", pool_size as i64);
+
         let (tag_col_length, timeout) =
             pool.iter()
-                .fold((0, Duration::default()), |(len, timeout), entry| {
-                    let process = entry.process();
+                .fold((0, Duration::default()), |(len, timeout), member| {
+                    let process = &member.process;
                     let len = {
                         let tag_len = process.tag().len();
                         if tag_len > len {
@@ -414,160 +1195,642 @@ impl ProcessPool {
                     };
                     (len, timeout)
                 });
+        let tag_col_length = proxies
+            .iter()
+            .map(|proxy| proxy.tag.len())
+            .fold(tag_col_length, usize::max);
+        let tag_col_length = match opts.format.max_tag_width {
+            Some(max) if max < tag_col_length => max,
+            Some(_) | None => tag_col_length,
+        };
 
-        let colors = colors::make(pool_size as u8);
-        let processes: Vec<(PoolEntry<Loc, dyn Dependency>, Color)> =
-            pool.into_iter().zip(colors).collect();
-
-        let processes_list = processes.iter().fold(String::new(), |acc, (entry, color)| {
-            let process = entry.process();
-            let styled = console::style(process.tag().to_string()).fg(*color).bold();
-            if acc.is_empty() {
-                styled.to_string()
-            } else {
-                format!("{}, {}", acc, styled)
-            }
-        });
+        let mut colors = colors::make(pool_size + proxies.len());
+        let proxies: Vec<(ProxyProcess, Color)> = proxies
+            .into_iter()
+            .zip(colors.split_off(pool_size))
+            .collect();
+        let processes: Vec<(Member<Loc>, Color)> = pool.into_iter().zip(colors).collect();
+
+        let processes_list = processes
+            .iter()
+            .map(|(member, color)| (member.process.tag(), color))
+            .chain(proxies.iter().map(|(proxy, color)| (proxy.tag, color)))
+            .fold(String::new(), |acc, (tag, color)| {
+                let styled = crate::fmt::style(tag.to_string()).fg(*color).bold();
+                if acc.is_empty() {
+                    styled.to_string()
+                } else {
+                    format!("{}, {}", acc, styled)
+                }
+            });
 
-        eprintln!("❯ {} {}", console::style("Running:").bold(), processes_list);
+        console!(
+            "{}",
+            crate::fmt::line(format_args!(
+                "{} {}",
+                crate::fmt::style("Running:").bold(),
+                processes_list
+            ))
+        );
+        if !groups.is_empty() {
+            console!(
+                "{}",
+                crate::fmt::line(format_args!(
+                    "{} {}",
+                    crate::fmt::style("Groups:").bold(),
+                    self::groups_list(&processes, &groups)
+                ))
+            );
+        }
+        let groups = Arc::new(groups);
+
+        // Proxies don't exit on their own, so they aren't counted as processes of the pool
+        for (proxy, color) in proxies {
+            let col = Arc::new(TagColumn::new(
+                proxy.tag,
+                color,
+                tag_col_length,
+                &opts.format,
+            ));
+            let state = state.clone();
+            task::spawn(async move { proxy.run(col, state.shutting_down()).await });
+        }
 
-        for (entry, color) in processes {
+        // Dependencies that are being waited for, as `(process index, dependency tag, process tag)`
+        let pending_deps = Arc::new(Mutex::new(Vec::<(usize, String, &'static str)>::new()));
+        let deps_deadline = opts.deps_deadline.map(|x| time::Instant::now() + x);
+        // Equivalent dependencies are waited for once
+        let shared_deps = Arc::new(SharedDeps::default());
+
+        if let (Some(deadline), Some(limit)) = (deps_deadline, opts.deps_deadline) {
+            let pending_deps = pending_deps.clone();
+            let state = state.clone();
+            task::spawn(async move {
+                tokio::select! {
+                    _ = time::sleep_until(deadline) => (),
+                    _ = state.shutting_down() => return,
+                }
+                let pending = pending_deps
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .iter()
+                    .map(|(_, dep, process)| {
+                        format!("{} (of {})", crate::fmt::style(dep).bold(), process)
+                    })
+                    .collect::<Vec<_>>();
+                if !pending.is_empty() {
+                    console!(
+                        "❗️ Dependencies are not ready within {}. Still pending: {}",
+                        crate::fmt::duration(limit),
+                        pending.join(", ")
+                    );
+                }
+            });
+        }
+
+        let starts = opts.stagger.map(Starts::new);
+
+        for (idx, (member, color)) in processes.into_iter().enumerate() {
             let exited_processes = exited_processes.clone();
+            let groups = groups.clone();
+            let state = state.clone();
+            let format = opts.format.clone();
+            let crash_loop = opts.crash_loop.clone();
+            let fail_fast = opts.fail_fast;
+            let base_env = opts.env.clone();
+            let pid_dir = opts.pid_dir.clone();
+            let starts = starts.clone();
+            let webhook = opts.webhook.clone();
+            #[cfg(all(unix, feature = "syslog"))]
+            let log_sink = opts.log_sink;
+            let pending_deps = pending_deps.clone();
+            let shared_deps = shared_deps.clone();
+            #[cfg(feature = "otel")]
+            let pool_span = pool_span.id();
 
             task::spawn(async move {
-                let (process, dependency) = entry.take();
+                let Member {
+                    process,
+                    dependency,
+                    group,
+                    setup,
+                    readiness,
+                    teardown,
+                } = member;
                 let tag = process.tag();
-                let cmd = process.cmd();
                 let timeout = process.timeout();
-                let colored_tag = console::style(tag.to_owned()).fg(color).bold();
-                let colored_tag_col = {
-                    let len = tag.len();
-                    let pad = " ".repeat(if len < tag_col_length {
-                        tag_col_length - len + 2
-                    } else {
-                        2
-                    });
-                    console::style(format!(
-                        "{tag}{pad}{pipe}",
-                        tag = colored_tag,
-                        pad = pad,
-                        pipe = console::style("|").fg(color).bold()
-                    ))
+                let line_opts = LineOptions {
+                    filter: process.filter.clone(),
+                    exclude: process.exclude.clone(),
+                    strip_ansi: process.strip_ansi,
+                    #[cfg(all(unix, feature = "syslog"))]
+                    sink: log_sink.and_then(|sink| crate::syslog::Sink::connect(sink, tag)),
                 };
+                let colored_tag = crate::fmt::style(tag.to_owned()).fg(color).bold();
+                let colored_tag_col = Arc::new(TagColumn::new(tag, color, tag_col_length, &format));
+
+                let pidfile = pid_dir.as_deref().map(|dir| Pidfile::new(dir, tag));
 
                 let dep_res = match dependency {
                     None => Ok(()),
                     Some(dependency) => {
-                        let dep_tag = console::style(dependency.tag()).bold();
+                        let dep_tag = crate::fmt::style(dependency.tag()).bold();
 
-                        eprintln!(
+                        console!(
                             "{col} {process} is waiting for its {dep} dependency...",
                             col = colored_tag_col,
                             dep = dep_tag,
                             process = colored_tag
                         );
 
-                        let res = dependency.wait().await;
+                        pending_deps
+                            .lock()
+                            .unwrap_or_else(|err| err.into_inner())
+                            .push((idx, dependency.tag().to_owned(), tag));
+                        let waiting_since = Instant::now();
+                        #[cfg(feature = "otel")]
+                        let span =
+                            crate::otel::Span::start(format!("{} dependency", dependency.tag()))
+                                .parent(pool_span)
+                                .str("steward.tag", dependency.tag());
+                        let wait = shared_deps
+                            .wait(&*dependency, deps_deadline.map(time::Instant::into_std));
+                        let report = self::report_waiting(&colored_tag_col, &colored_tag, &dep_tag);
+                        tokio::pin!(wait, report);
+                        let mut dep_timeout = process.dep_timeout.map(|x| time::Instant::now() + x);
+                        let res = loop {
+                            tokio::select! {
+                                res = &mut wait => break res,
+                                never = &mut report => match never {},
+                                _ = self::deadline(dep_timeout) => {
+                                    let timeout = process.dep_timeout.unwrap_or_default();
+                                    if process.on_dep_failure != DepFailure::KeepWaiting {
+                                        break Err(Box::new(DepTimeoutError(timeout)) as Box<dyn DependencyWaitError>);
+                                    }
+                                    console!(
+                                        "{col} ⚠️  {dep} dependency of {process} is not ready within {timeout}. Waiting in the background...",
+                                        col = colored_tag_col,
+                                        dep = dep_tag,
+                                        process = colored_tag,
+                                        timeout = crate::fmt::duration(timeout)
+                                    );
+                                    dep_timeout = None;
+                                }
+                                _ = self::deadline(deps_deadline) => break Err(Box::new(DepsDeadlineError)),
+                                _ = state.shutting_down() => {
+                                    exited_processes.fetch_add(1, Ordering::Relaxed);
+                                    return;
+                                }
+                            }
+                        };
+                        // The process is no longer waiting, whether the dependency is ready or the process gave up on it
+                        pending_deps
+                            .lock()
+                            .unwrap_or_else(|err| err.into_inner())
+                            .retain(|x| x.0 != idx);
+                        #[cfg(feature = "otel")]
+                        span.end(res.as_ref().err().map(|error| error.to_string()));
+                        if res.is_ok() {
+                            console!(
+                                "{col} {dep} dependency of {process} is ready in {elapsed}.",
+                                col = colored_tag_col,
+                                dep = dep_tag,
+                                process = colored_tag,
+                                elapsed = crate::fmt::duration(waiting_since.elapsed())
+                            );
+                        }
                         if let Err(error) = &res {
-                            eprintln!(
-                                "{col} ❗️ {dep} dependency of {process} errored: {error}\nNot executing {process}.",
+                            console!(
+                                "{col} ❗️ {dep} dependency of {process} errored: {error}",
                                 col = colored_tag_col,
                                 dep = dep_tag,
                                 process = colored_tag,
                                 error = error
                             );
+                            if let Some(hint) = dependency.hint() {
+                                console!(
+                                    "{col} 💡 {hint}",
+                                    col = colored_tag_col,
+                                    hint = crate::fmt::style(hint).yellow()
+                                );
+                            }
+                            console!(
+                                "{col} Not executing {process}.",
+                                col = colored_tag_col,
+                                process = colored_tag
+                            );
+                            state.not_started(
+                                tag,
+                                format_args!("its {} dependency errored", dependency.tag()),
+                            );
+                            if process.on_dep_failure == DepFailure::FailPool {
+                                state.fail(
+                                    tag,
+                                    format!(
+                                        "is not started: its {} dependency errored",
+                                        dependency.tag()
+                                    ),
+                                );
+                            }
                         }
                         res
                     }
                 };
 
-                if let Ok(()) = dep_res {
-                    eprintln!(
-                        "{tag} {headline}",
-                        tag = colored_tag_col,
-                        headline = crate::headline!(cmd),
-                    );
+                if let Some(crash_loop) = &crash_loop {
+                    match crash_loop.history(tag) {
+                        Ok(crashes) if crash_loop.is_looping(crashes.len()) => console!(
+                            "{col} ⚠️  Process {process} crashed {n} times within the last {window} in the previous runs.",
+                            col = colored_tag_col,
+                            process = colored_tag,
+                            n = crashes.len(),
+                            window = crate::fmt::duration(crash_loop.window),
+                        ),
+                        Ok(_) => (),
+                        Err(error) => console!(
+                            "{col} ⚠️  Unable to read crash history of {process}: {error}",
+                            col = colored_tag_col,
+                            process = colored_tag,
+                            error = error
+                        ),
+                    }
+                }
 
-                    let opts = SpawnOptions {
-                        stdout: Stdio::piped(),
-                        stderr: Stdio::piped(),
-                        timeout: timeout.to_owned(),
-                    };
+                // Setup of a service runs once its dependency is ready, and its teardown only if the setup succeeded
+                let setup_res = match (&dep_res, &setup) {
+                    (Ok(()), Some(setup)) => {
+                        let res = self::run_setup(
+                            setup,
+                            &state,
+                            colored_tag_col.clone(),
+                            line_opts.clone(),
+                            &base_env,
+                        )
+                        .await;
+                        match res {
+                            Ok(ExitResult::Output(_)) => true,
+                            // The pool is shutting down
+                            Ok(ExitResult::Interrupted | ExitResult::Killed { .. }) => false,
+                            Err(_) => {
+                                console!(
+                                    "{col} ❗️ Setup of {process} {exit}.\n{col} Not executing {process}.",
+                                    col = colored_tag_col,
+                                    process = colored_tag,
+                                    exit = Exit::from_result(&res)
+                                );
+                                state.not_started(tag, "its setup failed");
+                                false
+                            }
+                        }
+                    }
+                    (res, _) => res.is_ok(),
+                };
 
-                    let mut process = process.spawn(opts).await.unwrap_or_else(|err| {
-                        panic!("Failed to spawn {} process. {}", colored_tag, err)
-                    });
+                if setup_res && !state.is_shutting_down() {
+                    let mut restarts = process.backoff.start();
+                    let mut group_rx = groups.subscribe(group);
+                    let mut generation = 0;
+                    'group: loop {
+                        loop {
+                            if let Some(rx) = &mut group_rx {
+                                let (current, cmd) = *rx.borrow_and_update();
+                                generation = current;
+                                if cmd == GroupCmd::Stop {
+                                    break;
+                                }
+                            }
 
-                    match process.stdout() {
-                        None => eprintln!(
-                            "{} Unable to read from {} stdout",
-                            colored_tag_col, colored_tag
-                        ),
-                        Some(stdout) => {
-                            let mut reader = BufReader::new(stdout).lines();
-                            task::spawn({
-                                let tag = colored_tag_col.clone();
-                                async move {
-                                    while let Some(line) = reader.next_line().await.unwrap() {
-                                        eprintln!("{} {}", tag, line);
+                            // Freed once the process is started or exits
+                            let slot = match &starts {
+                                None => None,
+                                Some(starts) => tokio::select! {
+                                    slot = starts.acquire() => Some(slot),
+                                    _ = state.shutting_down() => break 'group,
+                                },
+                            };
+
+                            let cmd = process.cmd().get();
+
+                            // Removed once the process exits
+                            #[cfg(all(target_os = "linux", feature = "cgroup"))]
+                            let cgroup = match &process.cgroup {
+                                None => None,
+                                Some(limits) => match TransientCgroup::create(tag, limits) {
+                                    Ok(cgroup) => Some(cgroup),
+                                    Err(error) => {
+                                        console!(
+                                        "{col} ❗️ Failed to create cgroup for {process}: {error}\n{col} Not executing {process}.",
+                                        col = colored_tag_col,
+                                        process = colored_tag,
+                                        error = error
+                                    );
+                                        state.not_started(tag, &error);
+                                        break;
+                                    }
+                                },
+                            };
+
+                            // Removed once the process exits, so a pool sharing the directory refuses to start it meanwhile
+                            let claimed = match &pidfile {
+                                None => None,
+                                Some(pidfile) => match pidfile.claim() {
+                                    Ok(Claim::Claimed) => Some(pidfile),
+                                    Ok(Claim::Running(pid)) => {
+                                        console!(
+                                            "{col} ❗️ {process} is already running with pid {pid}, according to {file}.\n{col} Not executing {process}.",
+                                            col = colored_tag_col,
+                                            process = colored_tag,
+                                            pid = pid,
+                                            file = pidfile.path().display(),
+                                        );
+                                        state.not_started(
+                                            tag,
+                                            format_args!("it's already running with pid {}", pid),
+                                        );
+                                        break;
+                                    }
+                                    Err(error) => {
+                                        console!(
+                                            "{col} ⚠️  Failed to create {file}: {error}",
+                                            col = colored_tag_col,
+                                            file = pidfile.path().display(),
+                                            error = error
+                                        );
+                                        None
                                     }
+                                },
+                            };
+
+                            console!(
+                                "{tag} {headline}",
+                                tag = colored_tag_col,
+                                headline = crate::headline!(cmd),
+                            );
+
+                            let opts = SpawnOptions {
+                                stdin: process.stdin.stdio(),
+                                stdout: Stdio::piped(),
+                                stderr: Stdio::piped(),
+                                timeout: timeout.to_owned(),
+                                #[cfg(all(target_os = "linux", feature = "cgroup"))]
+                                cgroup: cgroup.as_ref().map(|cgroup| cgroup.path().to_owned()),
+                                ..Default::default()
+                            };
+
+                            #[cfg(feature = "otel")]
+                            let span = crate::otel::Span::start(tag)
+                                .parent(pool_span)
+                                .str("steward.tag", tag)
+                                .str("steward.exe", &cmd.exe)
+                                .str("steward.cwd", cmd.workdir().display().to_string())
+                                .int("steward.restarts", restarts.failed() as i64);
+
+                            let mut running = match cmd.spawn_with_env(
+                                opts,
+                                ColorMode::child_env().extend(base_env.clone()),
+                            ) {
+                                Ok(running) => running,
+                                Err(error) => {
+                                    console!(
+                                        "{col} ❗️ Failed to spawn {process}: {error}",
+                                        col = colored_tag_col,
+                                        process = colored_tag,
+                                        error = error
+                                    );
+                                    if let Some(pidfile) = claimed {
+                                        pidfile.remove();
+                                    }
+                                    let exit = Exit::Failed(error.to_string());
+                                    #[cfg(feature = "otel")]
+                                    span.exit(&exit);
+                                    self::notify(&webhook, tag, &exit, state.is_shutting_down());
+                                    state.exited(idx, exit);
+                                    break;
+                                }
+                            };
+                            state.started(idx, &running);
+                            if let (Some(pidfile), Some(pid)) = (claimed, running.as_child().id()) {
+                                if let Err(error) = pidfile.write(pid) {
+                                    console!(
+                                        "{col} ⚠️  Failed to write {file}: {error}",
+                                        col = colored_tag_col,
+                                        file = pidfile.path().display(),
+                                        error = error
+                                    );
+                                }
+                            }
+                            // Aborted once the process exits. A service is starting until its readiness check completes.
+                            let (readiness_check, slot) = match readiness.clone() {
+                                Some(readiness) => (
+                                    Some(task::spawn(self::check_readiness(
+                                        readiness,
+                                        state.clone(),
+                                        idx,
+                                        colored_tag_col.clone(),
+                                        colored_tag.clone(),
+                                        slot,
+                                    ))),
+                                    None,
+                                ),
+                                None => (None, slot.map(Slot::release_after_delay)),
+                            };
+                            // Held until the process exits, so it doesn't read EOF
+                            let _stdin = running.stdin();
+
+                            match running.stdout() {
+                                None => console!(
+                                    "{} Unable to read from {} stdout",
+                                    colored_tag_col,
+                                    colored_tag
+                                ),
+                                Some(stdout) => {
+                                    forward(
+                                        stdout,
+                                        colored_tag_col.clone(),
+                                        line_opts.clone(),
+                                        process.stdout.clone(),
+                                    );
+                                }
+                            }
+
+                            match running.stderr() {
+                                None => console!(
+                                    "{} Unable to read from {} stderr",
+                                    colored_tag_col,
+                                    colored_tag
+                                ),
+                                Some(stderr) => {
+                                    forward(
+                                        stderr,
+                                        colored_tag_col.clone(),
+                                        line_opts.clone(),
+                                        process.stderr.clone(),
+                                    );
+                                }
+                            }
+
+                            let pid = running.as_child().id();
+                            let recycle_at = process
+                                .restart_every
+                                .map(|interval| time::Instant::now() + interval);
+                            // Why the process is restarted by the pool, e.g. `on schedule`
+                            let mut recycled = None;
+                            let res = running
+                                .wait_until_or_kill(
+                                    async {
+                                        tokio::select! {
+                                            _ = state.shutdown_requested() => (),
+                                            _ = group::changed(&mut group_rx) => (),
+                                            _ = self::deadline(recycle_at) => recycled = Some("on schedule"),
+                                            rss = crate::memory::exceeded(pid, process.max_rss) => {
+                                                console!(
+                                                    "{col} ⚠️  Process {process} uses {rss} of memory, which exceeds its limit of {max}.",
+                                                    col = colored_tag_col,
+                                                    process = colored_tag,
+                                                    rss = crate::fmt::bytes(rss),
+                                                    max = crate::fmt::bytes(process.max_rss.unwrap_or_default()),
+                                                );
+                                                recycled = Some("due to memory usage");
+                                            }
+                                        }
+                                    },
+                                    state.forced(),
+                                )
+                                .await;
+                            if let Some(check) = readiness_check {
+                                check.abort();
+                            }
+                            if let Some(slot) = slot {
+                                slot.abort();
+                            }
+                            if let Some(pidfile) = claimed {
+                                pidfile.remove();
+                            }
+                            let exit = Exit::from_result(&res);
+                            #[cfg(feature = "otel")]
+                            span.exit(&exit);
+                            self::notify(&webhook, tag, &exit, state.is_shutting_down());
+                            state.exited(idx, exit);
+
+                            self::report_exit(&colored_tag_col, &colored_tag, &res);
+
+                            if let (Some(crash_loop), Err(_)) = (&crash_loop, &res) {
+                                match crash_loop.record(tag) {
+                                    Ok(crashes) if crash_loop.is_looping(crashes) => {
+                                        console!(
+                                            "{col} ⚠️  Process {process} is crash-looping: {n} crashes within the last {window}.",
+                                            col = colored_tag_col,
+                                            process = colored_tag,
+                                            n = crashes,
+                                            window = crate::fmt::duration(crash_loop.window),
+                                        );
+                                        if crash_loop.halt {
+                                            console!(
+                                                "{col} Not restarting {process}.",
+                                                col = colored_tag_col,
+                                                process = colored_tag
+                                            );
+                                            break;
+                                        }
+                                    }
+                                    Ok(_) => (),
+                                    Err(error) => console!(
+                                        "{col} ⚠️  Unable to record a crash of {process}: {error}",
+                                        col = colored_tag_col,
+                                        process = colored_tag,
+                                        error = error
+                                    ),
                                 }
-                            });
+                            }
+
+                            // Nothing is restarted once the pool is shutting down or draining
+                            if state.is_shutting_down() {
+                                break 'group;
+                            }
+
+                            if let Some(reason) = recycled {
+                                console!(
+                                    "{} Restarting {} {}...",
+                                    colored_tag_col,
+                                    colored_tag,
+                                    reason
+                                );
+                                continue;
+                            }
+
+                            let delay = match groups.exited(group, generation, tag, &res) {
+                                Some(GroupCmd::Restart(delay)) => delay,
+                                Some(GroupCmd::Stop) => break,
+                                Some(GroupCmd::Run) | None => {
+                                    if !process.restart.applies(&res) {
+                                        break;
+                                    }
+                                    match restarts.fail() {
+                                        Some(delay) => delay,
+                                        None => {
+                                            console!(
+                                                "{} Process {} reached the max number of restarts.",
+                                                colored_tag_col,
+                                                colored_tag
+                                            );
+                                            break;
+                                        }
+                                    }
+                                }
+                            };
+                            tokio::select! {
+                                _ = time::sleep(delay) => (),
+                                _ = state.shutting_down() => break 'group,
+                            }
+                            if state.is_shutting_down() {
+                                break 'group;
+                            }
+                            console!("{} Restarting {}...", colored_tag_col, colored_tag);
                         }
-                    }
 
-                    match process.stderr() {
-                        None => eprintln!(
-                            "{} Unable to read from {} stderr",
-                            colored_tag_col, colored_tag
-                        ),
-                        Some(stderr) => {
-                            let mut reader = BufReader::new(stderr).lines();
-                            task::spawn({
-                                let tag = colored_tag_col.clone();
-                                async move {
-                                    while let Some(line) = reader.next_line().await.unwrap() {
-                                        eprintln!("{} {}", tag, line);
+                        // The process is stopped for good, unless its group is restarted
+                        if state.is_shutting_down() {
+                            break;
+                        }
+                        groups.stopped(group, generation, tag);
+                        if !groups.restarts(group) {
+                            break;
+                        }
+                        let delay = loop {
+                            tokio::select! {
+                                cmd = group::changed(&mut group_rx) => {
+                                    if let GroupCmd::Restart(delay) = cmd {
+                                        break delay;
                                     }
                                 }
-                            });
+                                _ = state.shutting_down() => break 'group,
+                            }
+                        };
+                        tokio::select! {
+                            _ = time::sleep(delay) => (),
+                            _ = state.shutting_down() => break,
+                        }
+                        if state.is_shutting_down() {
+                            break;
                         }
+                        console!("{} Restarting {}...", colored_tag_col, colored_tag);
                     }
+                }
 
-                    let res = process.wait().await;
+                if let (true, Some(teardown)) = (setup_res, &teardown) {
+                    if let Err(error) = teardown.run().await {
+                        console!(
+                            "{col} ⚠️  Teardown of {process} failed: {error}",
+                            col = colored_tag_col,
+                            process = colored_tag,
+                            error = error
+                        );
+                    }
+                }
 
-                    match res {
-                        Ok(ExitResult::Output(_)) => eprintln!(
-                            "{} Process {} exited with code 0.",
-                            colored_tag_col, colored_tag
-                        ),
-                         Ok(ExitResult::Interrupted) => eprintln!(
-                            "{} Process {} successfully exited.",
-                            colored_tag_col, colored_tag
-                        ),
-                        Ok(ExitResult::Killed { pid }) => eprintln!(
-                            "{} Process {} with pid {pid} was killed due to timeout.",
-                            colored_tag_col,
-                            colored_tag,
-                        ),
-                        Err(Error::NonZeroExitCode { code, output: _ }) => eprintln!(
-                            "{} Process {} exited with non-zero code: {}",
-                            colored_tag_col,
-                            colored_tag,
-                            code.map(|x| format!("{}", x)).unwrap_or_else(|| "-".to_string())
-                        ),
-                        Err(Error::ProcessDoesNotExist) => eprintln!(
-                            "{} ⚠️  Process {} does not exist.",
-                            colored_tag_col, colored_tag
-                        ),
-                        Err(Error::Zombie { pid, err }) => eprintln!(
-                            "{} ⚠️  Process {} with pid {} hanged and we were unable to kill it. Error: {}",
-                            colored_tag_col, colored_tag, pid, err
-                        ),
-                        Err(Error::IoError(err)) => eprintln!(
-                            "{} Process {} exited with error: {}",
-                            colored_tag_col, colored_tag, err
-                        ),
+                if fail_fast {
+                    if let Some(exit) = state.last_exit(idx).filter(Exit::is_failure) {
+                        state.fail(tag, exit.to_string());
                     }
                 }
 
@@ -575,29 +1838,408 @@ impl ProcessPool {
             });
         }
 
-        signal::ctrl_c().await.unwrap();
-        eprintln!(); // Prints `^C` in terminal on its own line
+        tokio::select! {
+            res = signal::ctrl_c() => {
+                res?;
+                state.shutdown(Shutdown::CtrlC);
+                console!(""); // Prints `^C` in terminal on its own line
+            }
+            _ = state.shutting_down() => (),
+        }
+
+        if state.is_draining() {
+            console!(
+                "{}",
+                crate::fmt::line("Draining. Waiting for the processes to exit...")
+            );
+        }
 
-        let expire = Instant::now() + timeout;
+        let mut expire = Instant::now() + timeout;
         while exited_processes.load(Ordering::Relaxed) < pool_size {
-            if Instant::now() > expire {
-                eprintln!("⚠️  Timeout. Exiting.");
+            // A draining pool is given its own deadline by the handle
+            if state.is_draining() {
+                expire = Instant::now() + timeout;
+            } else if Instant::now() > expire {
+                console!("⚠️  Timeout. Exiting.");
                 break;
             }
             time::sleep(Duration::from_millis(500)).await;
         }
 
-        Ok(())
+        self::print_summary(&state.stats());
+        state.close_exits();
+
+        if let Some(webhook) = &opts.webhook {
+            webhook.send(&PoolEvent::Shutdown).await;
+        }
+
+        let failure = state.take_failure();
+
+        #[cfg(feature = "otel")]
+        {
+            pool_span.end(failure.as_ref().map(|error| error.to_string()));
+            crate::otel::Otlp::flush().await;
+        }
+
+        match failure {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Ensures the tags of a pool are unique and not empty, so its output and the tag-addressed
+/// [`PoolHandle`](crate::PoolHandle) methods are not ambiguous.
+fn validate_tags<'a>(tags: impl IntoIterator<Item = &'a str>) -> Result<()> {
+    let mut seen = std::collections::HashSet::new();
+    for tag in tags {
+        let reason = if tag.trim().is_empty() {
+            "a process has an empty tag".to_string()
+        } else if !seen.insert(tag) {
+            format!("several processes are tagged {}", tag)
+        } else {
+            continue;
+        };
+        return Err(Error::InvalidPool { reason });
+    }
+    Ok(())
+}
+
+/// Replaces the default timeouts of the processes with the default of the pool, or the global default.
+fn resolve_timeouts<Loc>(pool: &mut [Member<Loc>], default: Option<Duration>) -> Result<()> {
+    let mut members = pool
+        .iter_mut()
+        .filter(|x| x.process.timeout.is_default())
+        .peekable();
+    if members.peek().is_none() {
+        return Ok(());
+    }
+    let timeout = match default {
+        Some(timeout) => timeout,
+        None => crate::cmd::default_kill_timeout().map_err(|error| Error::InvalidPool {
+            reason: format!("PROCESS_TIMEOUT variable is invalid: {}", error)
+                .trim_end_matches('.')
+                .to_string(),
+        })?,
+    };
+    for member in members {
+        member.process.timeout = KillTimeout::new(timeout);
     }
+    Ok(())
+}
+
+/// Prints that a process is still waiting for its dependency every few seconds. Never resolves.
+async fn report_waiting(
+    col: &TagColumn,
+    process: &(dyn std::fmt::Display + Sync),
+    dep: &(dyn std::fmt::Display + Sync),
+) -> std::convert::Infallible {
+    const INTERVAL: Duration = Duration::from_secs(10);
+
+    let since = Instant::now();
+    loop {
+        time::sleep(INTERVAL).await;
+        console!(
+            "{col} {process} is still waiting for its {dep} dependency ({elapsed})...",
+            col = col,
+            process = process,
+            dep = dep,
+            elapsed = crate::fmt::duration(since.elapsed())
+        );
+    }
+}
+
+/// Runs a setup command of a process once, before its first start. The output goes to the console under
+/// the tag of the process, and the command is interrupted once the pool is shutting down.
+async fn run_setup<Loc>(
+    setup: &Cmd<Loc>,
+    state: &PoolState,
+    col: Arc<TagColumn>,
+    line_opts: LineOptions,
+    base_env: &Env,
+) -> Result<ExitResult>
+where
+    Loc: Location,
+{
+    console!("{} {}", col, crate::headline!(setup));
+    let opts = SpawnOptions {
+        stdout: Stdio::piped(),
+        stderr: Stdio::piped(),
+        ..Default::default()
+    };
+    let mut running =
+        setup.spawn_with_env(opts, ColorMode::child_env().extend(base_env.clone()))?;
+    if let Some(stdout) = running.stdout() {
+        forward(stdout, col.clone(), line_opts.clone(), Route::Console);
+    }
+    if let Some(stderr) = running.stderr() {
+        forward(stderr, col, line_opts, Route::Console);
+    }
+    running
+        .wait_until_or_kill(state.shutdown_requested(), state.forced())
+        .await
+}
+
+/// Waits for the readiness check of a [`Service`](crate::Service) after a start of its process.
+async fn check_readiness(
+    readiness: Arc<dyn Dependency>,
+    state: Arc<PoolState>,
+    idx: usize,
+    col: Arc<TagColumn>,
+    process: console::StyledObject<String>,
+    slot: Option<Slot>,
+) {
+    let started = Instant::now();
+    let check = crate::fmt::style(readiness.tag()).bold();
+    let res = tokio::select! {
+        res = readiness.wait() => res,
+        _ = state.shutting_down() => return,
+    };
+    drop(slot);
+    match res {
+        Ok(()) => {
+            console!(
+                "{col} {process} is ready in {elapsed}.",
+                col = col,
+                process = process,
+                elapsed = crate::fmt::duration(started.elapsed())
+            );
+            state.passed_check(idx);
+        }
+        Err(error) => {
+            console!(
+                "{col} ❗️ {check} readiness check of {process} errored: {error}",
+                col = col,
+                check = check,
+                process = process,
+                error = error
+            );
+            state.failed_check(
+                idx,
+                format_args!("its {} readiness check errored", readiness.tag()),
+            );
+        }
+    }
+}
+
+/// Prints how a process of a pool exited.
+fn report_exit(col: &TagColumn, tag: &impl std::fmt::Display, res: &Result<ExitResult>) {
+    match res {
+        Ok(ExitResult::Output(_)) => console!("{} Process {} exited with code 0.", col, tag),
+        Ok(ExitResult::Interrupted) => console!("{} Process {} successfully exited.", col, tag),
+        Ok(ExitResult::Killed { pid }) => console!(
+            "{} Process {} with pid {} was killed due to timeout.",
+            col,
+            tag,
+            pid
+        ),
+        Err(Error::NonZeroExitCode { code, output: _ }) => console!(
+            "{} Process {} exited with non-zero code: {}",
+            col,
+            tag,
+            code.map(|x| format!("{}", x))
+                .unwrap_or_else(|| "-".to_string())
+        ),
+        Err(Error::ProcessDoesNotExist) => {
+            console!("{} ⚠️  Process {} does not exist.", col, tag)
+        }
+        Err(Error::Zombie { pid, err }) => console!(
+            "{} ⚠️  Process {} with pid {} hanged and we were unable to kill it. Error: {}",
+            col,
+            tag,
+            pid,
+            err
+        ),
+        Err(err) => console!("{} Process {} exited with error: {}", col, tag, err),
+    }
+}
+
+/// Posts a crash of a process or its kill due to timeout to the webhook in the background.
+fn notify(webhook: &Option<Webhook>, tag: &str, exit: &Exit, shutting_down: bool) {
+    let event = match exit {
+        Exit::Killed => PoolEvent::Killed {
+            tag: tag.to_owned(),
+        },
+        // Processes might exit with errors when they are interrupted
+        exit if exit.is_failure() && !shutting_down => PoolEvent::Crashed {
+            tag: tag.to_owned(),
+            reason: exit.to_string(),
+        },
+        Exit::Code(_) | Exit::Interrupted | Exit::Failed(_) => return,
+    };
+    #[cfg(feature = "notify")]
+    if let PoolEvent::Crashed { tag, reason } = &event {
+        crate::desktop::notify(&format!("{} crashed", tag), reason);
+    }
+    if let Some(webhook) = webhook.clone() {
+        task::spawn(async move { webhook.send(&event).await });
+    }
+}
+
+/// Lists the processes of each group, e.g. `frontend [client, docs]`. Processes of nested groups are listed in their parents as well.
+fn groups_list<Loc>(processes: &[(Member<Loc>, Color)], groups: &Groups) -> String {
+    (0..groups.len())
+        .map(|idx| {
+            let tags = processes
+                .iter()
+                .filter(|(member, _)| groups.contains(idx, member.group))
+                .map(|(member, color)| crate::fmt::style(member.process.tag).fg(*color).to_string())
+                .collect::<Vec<_>>();
+            format!(
+                "{} [{}]",
+                crate::fmt::style(groups.name(idx)).bold(),
+                tags.join(", ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Resolves at the deadline, or never if there is no deadline.
+pub(crate) async fn deadline(deadline: Option<time::Instant>) {
+    match deadline {
+        Some(deadline) => time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Prints uptime and restarts of the processes once a pool is shut down.
+///
+/// ```sh
+/// ❯ Summary:
+///   server  uptime 1h 02m 13s  restarts 0  last: interrupted
+///   worker  uptime 42s         restarts 7  last: exited with code 1
+/// ```
+fn print_summary(stats: &[ProcessStats]) {
+    let tag_width = stats.iter().map(|x| x.tag.len()).max().unwrap_or(0);
+    let uptimes = stats
+        .iter()
+        .map(|x| crate::fmt::duration(x.uptime))
+        .collect::<Vec<_>>();
+    let uptime_width = uptimes.iter().map(|x| x.len()).max().unwrap_or(0);
+
+    console!("{}", crate::fmt::plain_headline("Summary:"));
+    for (stats, uptime) in stats.iter().zip(uptimes) {
+        let last = match &stats.last_exit {
+            Some(exit) => exit.to_string(),
+            None => "not started".to_string(),
+        };
+        let restarts = format!("restarts {}", stats.restarts);
+        let restarts = if stats.restarts > 0 {
+            crate::fmt::style(restarts).yellow().to_string()
+        } else {
+            restarts
+        };
+        console!(
+            "  {tag}{tag_pad}  uptime {uptime}{uptime_pad}  {restarts}  last: {last}",
+            tag = crate::fmt::style(stats.tag).bold(),
+            tag_pad = " ".repeat(tag_width - stats.tag.len()),
+            uptime_pad = " ".repeat(uptime_width - uptime.len()),
+        );
+    }
+}
+
+#[derive(Clone)]
+struct LineOptions {
+    filter: Option<Regex>,
+    exclude: Option<Regex>,
+    strip_ansi: bool,
+    #[cfg(all(unix, feature = "syslog"))]
+    sink: Option<crate::syslog::Sink>,
+}
+
+impl LineOptions {
+    fn allows(&self, line: &str) -> bool {
+        if self.filter.is_none() && self.exclude.is_none() {
+            return true;
+        }
+        let line = console::strip_ansi_codes(line);
+        let included = match &self.filter {
+            None => true,
+            Some(pattern) => pattern.is_match(&line),
+        };
+        let excluded = match &self.exclude {
+            None => false,
+            Some(pattern) => pattern.is_match(&line),
+        };
+        included && !excluded
+    }
+
+    fn format<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        if self.strip_ansi {
+            // Only the part after the last carriage return would be visible in a terminal
+            let line = line.trim_end_matches('\r');
+            let line = match line.rfind('\r') {
+                Some(idx) => &line[idx + 1..],
+                None => line,
+            };
+            console::strip_ansi_codes(line)
+        } else {
+            Cow::Borrowed(line)
+        }
+    }
+}
+
+/// Reads a pipe of a process until it's closed.
+async fn read_to_end(mut pipe: impl AsyncRead + Unpin) -> Vec<u8> {
+    let mut buf = vec![];
+    // Whatever is read before a failure is kept
+    let _ = pipe.read_to_end(&mut buf).await;
+    buf
+}
+
+fn forward<R>(
+    stream: R,
+    tag_col: Arc<TagColumn>,
+    line_opts: LineOptions,
+    route: Route,
+) -> task::JoinHandle<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    let mut reader = BufReader::new(stream);
+    task::spawn(async move {
+        let mut outlet = Outlet::open(&route, |warning| tag_col.print(&warning)).await;
+        let mut buf = vec![];
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf).await {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(error) => {
+                    tag_col.print(&format!("⚠️  Unable to read the output: {}", error));
+                    break;
+                }
+            }
+            if buf.ends_with(b"\n") {
+                buf.pop();
+                if buf.ends_with(b"\r") {
+                    buf.pop();
+                }
+            }
+            // Invalid UTF-8 sequences are replaced with `�`, so such output doesn't stop forwarding
+            let line = String::from_utf8_lossy(&buf).into_owned();
+            // The log gets all the output, regardless of the filters of the console
+            #[cfg(all(unix, feature = "syslog"))]
+            if let Some(sink) = &line_opts.sink {
+                sink.send(&line);
+            }
+            let formatted = line_opts.format(&line);
+            if outlet.console && line_opts.allows(&line) {
+                tag_col.print(&formatted);
+            }
+            outlet.write(&formatted).await;
+        }
+    })
 }
 
 mod colors {
     use console::Color;
     use rand::{seq::SliceRandom, thread_rng};
 
-    pub fn make(n: u8) -> Vec<Color> {
+    pub fn make(n: usize) -> Vec<Color> {
         // Preferred colors
-        let mut primaries = vec![
+        let mut palette = vec![
             // Color::Red, // Red is for errors
             Color::Green,
             Color::Yellow,
@@ -613,31 +2255,27 @@ mod colors {
         ];
 
         // Let's check first if we can get away with just primary colors
-        if n <= primaries.len() as u8 {
-            shuffle(primaries, n)
+        if n > palette.len() {
+            palette.extend(secondaries);
         }
-        // Otherwise, let's check if primary + secondary combined would work
-        else if n <= (primaries.len() + primaries.len()) as u8 {
-            primaries.extend(secondaries);
-            shuffle(primaries, n)
-        } else {
-            // TODO: Duplicate primary + secondary colors vec as many is needed, then shuffle
-            todo!()
-        }
-    }
-
-    fn shuffle<T>(mut items: Vec<T>, n: u8) -> Vec<T> {
-        items.truncate(n as usize);
-        items.shuffle(&mut thread_rng());
-        items
+        palette.shuffle(&mut thread_rng());
+        // Colors repeat in pools larger than the palette
+        palette.into_iter().cycle().take(n).collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
 
-    use crate::{Cmd, Location, Process};
+    use super::LineOptions;
+    use crate::{
+        fmt::TagColumn, Cmd, Dependency, Location, PoolEntry, PoolFormat, Process, Route,
+        TcpService,
+    };
 
     #[allow(dead_code)]
     fn process_macro_with_timeout<Loc: Location>(cmd: Cmd<Loc>) -> Process<Loc> {
@@ -655,4 +2293,190 @@ mod tests {
           cmd: cmd,
         }
     }
+
+    #[allow(dead_code)]
+    fn pool_macro_mixed_entries<Loc: Location>(
+        server: Process<Loc>,
+        client: Process<Loc>,
+        docs: Option<Process<Loc>>,
+        workers: Vec<Process<Loc>>,
+    ) -> Vec<PoolEntry<Loc, dyn Dependency>> {
+        pool![
+            server,
+            PoolEntry::ProcessWithDep {
+                process: client,
+                dependency: Box::new(
+                    TcpService::new("server", "127.0.0.1", 3000, Duration::from_secs(1), None)
+                        .unwrap()
+                ),
+            },
+            docs,
+            workers,
+        ]
+    }
+
+    #[test]
+    fn pool_tags() {
+        assert!(super::validate_tags(["server", "client"]).is_ok());
+        assert_eq!(
+            super::validate_tags(["server", "client", "server"])
+                .unwrap_err()
+                .to_string(),
+            "The pool is invalid: several processes are tagged server."
+        );
+        assert_eq!(
+            super::validate_tags(["server", " "])
+                .unwrap_err()
+                .to_string(),
+            "The pool is invalid: a process has an empty tag."
+        );
+    }
+
+    #[tokio::test]
+    async fn forward_invalid_utf8() {
+        let lines = Arc::new(Mutex::new(vec![]));
+        let route = {
+            let lines = lines.clone();
+            Route::callback(move |line| lines.lock().unwrap().push(line.to_owned()))
+        };
+        let col = Arc::new(TagColumn::new(
+            "server",
+            console::Color::Green,
+            6,
+            &PoolFormat::default(),
+        ));
+        let line_opts = LineOptions {
+            filter: None,
+            exclude: None,
+            strip_ansi: false,
+            #[cfg(all(unix, feature = "syslog"))]
+            sink: None,
+        };
+        let output: &[u8] = b"ok\n\xff\xfe\r\nstill ok";
+        super::forward(output, col, line_opts, route).await.unwrap();
+        assert_eq!(
+            *lines.lock().unwrap(),
+            ["ok", "\u{fffd}\u{fffd}", "still ok"]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn pause_tree() {
+        // Whether a process gets to the state (`T` stands for stopped) within a second
+        let reaches = |pid: u32, stopped: bool| {
+            (0..100).any(|_| {
+                let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap();
+                let state = stat.rsplit(") ").next().unwrap().starts_with('T');
+                std::thread::sleep(Duration::from_millis(10));
+                state == stopped
+            })
+        };
+        let mut shell = std::process::Command::new("sh")
+            .args(["-c", "sleep 30; true"])
+            .spawn()
+            .unwrap();
+        let pid = shell.id();
+        let sleep = loop {
+            match super::RunningProcess::descendants(pid).first() {
+                Some(sleep) => break *sleep,
+                None => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+
+        super::RunningProcess::pause(pid, false).unwrap();
+        let paused = reaches(pid, true) && reaches(sleep, true);
+        super::RunningProcess::resume(pid, false).unwrap();
+        let resumed = reaches(pid, false) && reaches(sleep, false);
+
+        crate::os::kill(sleep).unwrap();
+        shell.kill().unwrap();
+        shell.wait().unwrap();
+        assert!(paused);
+        assert!(resumed);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn shutdown_paused_tree() {
+        use std::path::PathBuf;
+
+        use crate::{Env, KillTimeout, PoolOptions, ProcessPool};
+
+        #[derive(Clone)]
+        struct Loc(PathBuf);
+
+        impl Location for Loc {
+            fn apex() -> Self {
+                Self(std::env::temp_dir())
+            }
+
+            fn as_path(&self) -> &PathBuf {
+                &self.0
+            }
+        }
+
+        // State of a process (`T` stands for stopped), `None` once it's gone
+        let state = |pid: u32| {
+            let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+            stat.rsplit(") ").next()?.chars().next()
+        };
+        let cmd = Cmd::new("sleep 4242 & wait", Env::empty(), Loc::apex(), None);
+        let process = Process::new("sleeper", cmd, KillTimeout::new(Duration::from_secs(1)));
+        let pool = ProcessPool::start([process], PoolOptions::default());
+        // The shell is looked up by its command, since other tests spawn processes as well
+        let shell = loop {
+            let procs = crate::os::processes().unwrap();
+            let shell = procs.iter().map(|x| x.0).find(|pid| {
+                std::fs::read(format!("/proc/{}/cmdline", pid))
+                    .is_ok_and(|x| String::from_utf8_lossy(&x).contains("sleep 4242 & wait"))
+                    && super::RunningProcess::descendants(*pid).len() == 1
+            });
+            match shell {
+                Some(shell) => break shell,
+                None => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let sleep = super::RunningProcess::descendants(shell)[0];
+
+        pool.process_handle("sleeper").unwrap().pause().unwrap();
+        assert_eq!(
+            pool.process("sleeper").unwrap().status,
+            crate::ProcessStatus::Paused
+        );
+        let mut paused = false;
+        for _ in 0..100 {
+            paused = state(sleep) == Some('T');
+            if paused {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        pool.shutdown().await.unwrap();
+        // The killed orphan is reaped by init at its own pace
+        let mut gone = false;
+        for _ in 0..100 {
+            gone = matches!(state(sleep), None | Some('Z'));
+            if gone {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        if !gone {
+            crate::os::kill(sleep).ok();
+        }
+        assert!(paused);
+        assert!(gone);
+    }
+
+    #[test]
+    fn colors() {
+        let colors = super::colors::make(20);
+        assert_eq!(colors.len(), 20);
+        assert_eq!(colors[..8], colors[8..16]);
+        for (idx, color) in colors[..8].iter().enumerate() {
+            assert!(!colors[..idx].contains(color));
+        }
+    }
 }
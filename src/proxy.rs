@@ -0,0 +1,169 @@
+use std::{future::Future, io, net::SocketAddr, sync::Arc};
+
+use tokio::{
+    io::copy_bidirectional,
+    net::{TcpListener, TcpStream},
+    task,
+};
+
+use crate::fmt::TagColumn;
+
+/// A TCP proxy that runs within a [`ProcessPool`](crate::ProcessPool) without any external tools.
+/// It listens on a local port and forwards connections to a target address, e.g. to expose a dockerized service
+/// under a stable local port.
+///
+/// Unlike processes, it's started right away (its target doesn't have to be available yet, connections are
+/// forwarded as they come), it's not restarted, and it's not a part of the pool statistics.
+///
+/// ```ignore
+/// ProcessPool::run_with_deps(pool![
+///     ProxyProcess::new("db-proxy", 5432, "172.17.0.2:5432").log(true),
+///     server::watch(),
+/// ])
+/// .await
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProxyProcess {
+    /// Tag used as an identificator in output of a pool.
+    pub tag: &'static str,
+    /// Local address to listen on.
+    pub listen: SocketAddr,
+    /// Target address in the `host:port` format.
+    pub target: String,
+    /// Whether to print opened and closed connections.
+    pub log: bool,
+}
+
+impl ProxyProcess {
+    /// Constructs a proxy that listens on the given port of `localhost` and forwards to the target `host:port`.
+    pub fn new(tag: &'static str, port: u16, target: impl Into<String>) -> Self {
+        Self {
+            tag,
+            listen: SocketAddr::from(([127, 0, 0, 1], port)),
+            target: target.into(),
+            log: false,
+        }
+    }
+
+    /// Sets a local address to listen on, e.g. `0.0.0.0:8080` to accept connections from other hosts.
+    pub fn listen(mut self, addr: SocketAddr) -> Self {
+        self.listen = addr;
+        self
+    }
+
+    /// Sets whether to print opened and closed connections.
+    pub fn log(mut self, log: bool) -> Self {
+        self.log = log;
+        self
+    }
+
+    /// Accepts and forwards connections until `stop` resolves.
+    pub(crate) async fn run(self, col: Arc<TagColumn>, stop: impl Future<Output = ()>) {
+        let listener = match TcpListener::bind(self.listen).await {
+            Ok(listener) => listener,
+            Err(error) => {
+                console!("{} ❗️ Failed to listen on {}: {}", col, self.listen, error);
+                return;
+            }
+        };
+        console!(
+            "{} Forwarding {} to {}",
+            col,
+            crate::fmt::style(self.listen).bold(),
+            crate::fmt::style(&self.target).bold()
+        );
+
+        let target = Arc::new(self.target);
+        tokio::pin!(stop);
+        loop {
+            let (conn, peer) = tokio::select! {
+                res = listener.accept() => match res {
+                    Ok(accepted) => accepted,
+                    Err(error) => {
+                        console!("{} ⚠️  Failed to accept a connection: {}", col, error);
+                        continue;
+                    }
+                },
+                _ = &mut stop => return,
+            };
+            let (col, target, log) = (col.clone(), target.clone(), self.log);
+            task::spawn(async move {
+                if log {
+                    console!("{} Connection from {} is opened", col, peer);
+                }
+                match self::forward(conn, &target).await {
+                    Ok((sent, received)) if log => console!(
+                        "{} Connection from {} is closed: {} sent, {} received",
+                        col,
+                        peer,
+                        crate::fmt::bytes(sent),
+                        crate::fmt::bytes(received)
+                    ),
+                    Ok(_) => (),
+                    Err(error) => console!(
+                        "{} ⚠️  Failed to forward a connection from {} to {}: {}",
+                        col,
+                        peer,
+                        target,
+                        error
+                    ),
+                }
+            });
+        }
+    }
+}
+
+/// Forwards a connection to the target. Returns the number of bytes sent to and received from the target.
+async fn forward(mut conn: TcpStream, target: &str) -> io::Result<(u64, u64)> {
+    let mut upstream = TcpStream::connect(target).await?;
+    copy_bidirectional(&mut conn, &mut upstream).await
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        sync::oneshot,
+    };
+
+    use super::*;
+    use crate::PoolFormat;
+
+    #[tokio::test]
+    async fn forwards() {
+        let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = upstream.local_addr().unwrap().to_string();
+        task::spawn(async move {
+            let (mut conn, _) = upstream.accept().await.unwrap();
+            let mut buf = [0; 4];
+            conn.read_exact(&mut buf).await.unwrap();
+            conn.write_all(&buf).await.unwrap();
+        });
+
+        let port = crate::net::free_port().unwrap();
+        let col = Arc::new(TagColumn::new(
+            "proxy",
+            console::Color::Green,
+            5,
+            &PoolFormat::default(),
+        ));
+        let (stop, stopped) = oneshot::channel::<()>();
+        let proxy = task::spawn(ProxyProcess::new("proxy", port, target).run(col, async {
+            stopped.await.ok();
+        }));
+
+        let mut conn = loop {
+            match TcpStream::connect(("127.0.0.1", port)).await {
+                Ok(conn) => break conn,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        conn.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        stop.send(()).unwrap();
+        proxy.await.unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use nix::{
+    libc,
+    sys::wait::{self, WaitPidFlag},
+    unistd::Pid,
+};
+use once_cell::sync::Lazy;
+use tokio::{
+    process::Child,
+    signal::unix::{signal, SignalKind},
+    task, time,
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Processes spawned by steward. These are waited for by their owners, so the reaper must not touch them.
+static SPAWNED: Lazy<Mutex<HashSet<i32>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Makes the current process a child subreaper and starts reaping orphans. Linux only. Must be called within a Tokio runtime.
+///
+/// When a process of a [`ProcessPool`](crate::ProcessPool) gets killed (e.g. on restart or timeout), its own children
+/// (e.g. a dev server spawned by a watcher) are orphaned. Normally, they are re-parented to `init`. As a subreaper,
+/// the current process adopts them instead, so they are reaped here and don't accumulate as zombies
+/// during long dev sessions with frequent restarts.
+///
+/// ```ignore
+/// #[tokio::main]
+/// async fn main() -> steward::Result<()> {
+///     steward::enable_subreaper()?;
+///     ProcessPool::run(vec![server::watch(), client::watch()]).await
+/// }
+/// ```
+///
+/// Only the processes spawned by steward are left to their owners. Any other exited child of the current process
+/// is reaped, so don't enable it if the current process spawns and waits for children on its own.
+pub fn enable_subreaper() -> io::Result<()> {
+    if ENABLED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    // SAFETY: `prctl` with `PR_SET_CHILD_SUBREAPER` only sets a flag of the current process
+    if unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0) } != 0 {
+        ENABLED.store(false, Ordering::SeqCst);
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut sigchld = signal(SignalKind::child())?;
+    task::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = sigchld.recv() => (),
+                _ = time::sleep(SCAN_INTERVAL) => (),
+            }
+            self::reap();
+        }
+    });
+
+    Ok(())
+}
+
+/// Spawns a process that is left to its owner by the reaper.
+/// The list of the spawned processes is locked while spawning, so the reaper can't see an unregistered process.
+pub(crate) fn spawn(f: impl FnOnce() -> io::Result<Child>) -> io::Result<Child> {
+    if !ENABLED.load(Ordering::SeqCst) {
+        return f();
+    }
+    let mut spawned = self::spawned();
+    let child = f()?;
+    if let Some(pid) = child.id() {
+        spawned.insert(pid as i32);
+    }
+    Ok(child)
+}
+
+fn reap() {
+    let mut spawned = self::spawned();
+
+    let children = match self::children() {
+        Ok(children) => children,
+        Err(_) => return,
+    };
+
+    // Spawned processes that are gone have been waited for by their owners
+    spawned.retain(|pid| children.contains(pid));
+
+    for pid in children {
+        if !spawned.contains(&pid) && self::is_zombie(pid) {
+            wait::waitpid(Pid::from_raw(pid), Some(WaitPidFlag::WNOHANG)).ok();
+        }
+    }
+}
+
+/// Direct children of the current process, including the adopted ones.
+fn children() -> io::Result<HashSet<i32>> {
+    let mut children = HashSet::new();
+    for task in fs::read_dir("/proc/self/task")? {
+        let content = match fs::read_to_string(task?.path().join("children")) {
+            Ok(content) => content,
+            // A thread might be gone by now
+            Err(_) => continue,
+        };
+        children.extend(
+            content
+                .split_whitespace()
+                .filter_map(|x| x.parse::<i32>().ok()),
+        );
+    }
+    Ok(children)
+}
+
+fn is_zombie(pid: i32) -> bool {
+    match fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        // The name of a process is in parens and might contain spaces, so the state is the first field after it
+        Ok(stat) => stat
+            .rsplit_once(')')
+            .is_some_and(|(_, rest)| rest.trim_start().starts_with('Z')),
+        Err(_) => false,
+    }
+}
+
+fn spawned() -> std::sync::MutexGuard<'static, HashSet<i32>> {
+    SPAWNED.lock().unwrap_or_else(|err| err.into_inner())
+}
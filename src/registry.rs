@@ -0,0 +1,1022 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::Poll,
+};
+
+use tokio::{sync::OnceCell, time};
+
+use crate::{
+    inputs::Inputs, Budget, Cmd, Dependency, Error, Freshness, Hook, Hooks, Location, PoolEntry,
+    PoolOptions, ProcessPool, Result, Script, ScriptDialect, Shell,
+};
+
+/// Named tasks of a project.
+///
+/// A task can be a one-off [`Cmd`](Cmd), an async function or a [`ProcessPool`](ProcessPool).
+/// Tasks are run by their names, so the registry can back a cli of a project.
+/// A task might depend on other tasks, which are run before it.
+///
+/// ```ignore
+/// let registry = Registry::new()
+///     .cmd("db:migrate", db::migrate())
+///     .task(Task::cmd("server:build", server::build()).tag("build"))
+///     .task(Task::cmd("client:build", client::build()).tag("build"))
+///     .task(
+///         Task::func("db:seed", |_args| async { db::seed().await })
+///             .about("Seeding the database")
+///             .dep("db:migrate"),
+///     )
+///     .task(
+///         Task::pool("dev", || pool![server::watch(), client::watch()])
+///             .about("Running the dev stack")
+///             .deps(["server:build", "client:build"]),
+///     );
+///
+/// registry.run("dev").await?;
+/// ```
+///
+/// A task can also be an alias for a group of other tasks that run in sequence or in parallel.
+/// Within one run, every task runs at most once, so prerequisites shared by several tasks of a group are not repeated:
+///
+/// ```ignore
+/// let registry = Registry::new()
+///     .cmd("deps", install_deps())
+///     .task(Task::cmd("build:server", server::build()).dep("deps"))
+///     .task(Task::cmd("build:client", client::build()).dep("deps"))
+///     .task(Task::par("build", ["build:server", "build:client"]))
+///     .alias("dev", ["build", "pool:watchers"]);
+/// ```
+///
+/// A command of a task might contain `{placeholder}`s, which are filled with the arguments of a task on run.
+/// Each argument is quoted for the [shell](crate::Shell) of the command, so it's passed to a program as a single word
+/// (a placeholder must not be quoted in a template):
+///
+/// ```ignore
+/// let registry = Registry::new().cmd(
+///     "build",
+///     cmd! { "cargo build --package={package}", env: Env::empty(), pwd: Loc::root(), msg: "Building a package" },
+/// );
+///
+/// registry.run_with_args("build", &TaskArgs::from([("package".to_string(), "server".to_string())])).await?;
+/// ```
+pub struct Registry<Loc> {
+    tasks: Vec<Task<Loc>>,
+    state_dir: PathBuf,
+    force: bool,
+}
+
+/// Shell of a completion script. See [`Registry::completions`](Registry::completions).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompletionShell {
+    /// Bash completion script.
+    Bash,
+    /// Zsh completion script.
+    Zsh,
+    /// Fish completion script.
+    Fish,
+}
+
+/// Arguments of a task, which fill the `{placeholder}`s of its command.
+pub type TaskArgs = HashMap<String, String>;
+
+type TaskFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+type TaskFn = Box<dyn Fn(TaskArgs) -> TaskFuture<'static> + Send + Sync>;
+type PoolFn<Loc> = Box<dyn Fn() -> Vec<PoolEntry<Loc, dyn Dependency>> + Send + Sync>;
+
+/// A task of a [`Registry`](Registry).
+pub struct Task<Loc> {
+    /// Name of a task, e.g. `db:migrate`.
+    pub name: String,
+    /// Description of a task.
+    pub about: Option<String>,
+    /// Tags of a task, which can be used to group tasks, e.g. in a list.
+    pub tags: Vec<String>,
+    /// Names of the tasks that are run before a task.
+    pub deps: Vec<String>,
+    /// Arguments of a task, in addition to the `{placeholder}`s of its command.
+    pub args: Vec<String>,
+    /// Setup and teardown hooks of a task. See [`Hooks`](Hooks).
+    pub hooks: Hooks,
+    /// Time budget of a task, excluding its dependencies. See [`Budget`](Budget).
+    /// Overrides the budget of the command of a task, if set.
+    pub budget: Budget,
+    io: Inputs,
+    kind: TaskKind<Loc>,
+}
+
+enum TaskKind<Loc> {
+    Cmd(Box<Cmd<Loc>>),
+    Fn(TaskFn),
+    Pool(PoolFn<Loc>),
+    Seq(Vec<String>),
+    Par(Vec<String>),
+}
+
+impl<Loc> Registry<Loc>
+where
+    Loc: Location + Clone + 'static,
+{
+    /// Constructs a new empty registry.
+    pub fn new() -> Self {
+        Self {
+            tasks: vec![],
+            state_dir: PathBuf::from(".steward"),
+            force: false,
+        }
+    }
+
+    /// Sets a directory where the registry keeps its state, e.g. hashes of the task inputs.
+    /// By default, it's `.steward` in the current directory.
+    pub fn state_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.state_dir = dir.into();
+        self
+    }
+
+    /// Runs tasks with declared inputs and outputs even if their outputs are up to date.
+    ///
+    /// ```ignore
+    /// registry.force(cli.force).run("build").await?;
+    /// ```
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Registers a task. If a task with the same name is already registered, it gets replaced.
+    pub fn task(mut self, task: Task<Loc>) -> Self {
+        match self.tasks.iter_mut().find(|x| x.name == task.name) {
+            Some(existing) => *existing = task,
+            None => self.tasks.push(task),
+        }
+        self
+    }
+
+    /// Registers a command under the name. A shortcut for [`Task::cmd`](Task::cmd).
+    pub fn cmd(self, name: impl Into<String>, cmd: Cmd<Loc>) -> Self {
+        self.task(Task::cmd(name, cmd))
+    }
+
+    /// Registers an alias for the tasks that run in sequence. A shortcut for [`Task::seq`](Task::seq).
+    pub fn alias<N: Into<String>>(
+        self,
+        name: impl Into<String>,
+        tasks: impl IntoIterator<Item = N>,
+    ) -> Self {
+        self.task(Task::seq(name, tasks))
+    }
+
+    /// Returns a task by its name.
+    pub fn get(&self, name: &str) -> Option<&Task<Loc>> {
+        self.tasks.iter().find(|task| task.name == name)
+    }
+
+    /// Returns an iterator over the tasks in the order of registration.
+    pub fn tasks(&self) -> impl Iterator<Item = &Task<Loc>> {
+        self.tasks.iter()
+    }
+
+    /// Returns an iterator over the tasks with the tag.
+    pub fn tagged<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a Task<Loc>> {
+        self.tasks
+            .iter()
+            .filter(move |task| task.tags.iter().any(|x| x == tag))
+    }
+
+    /// Prints the tasks with their arguments, descriptions and tags.
+    ///
+    /// ```sh
+    /// ❯ Tasks:
+    ///   db:migrate         Running migrations
+    ///   build <PACKAGE>    Building a package  [build]
+    /// ```
+    pub fn list(&self) {
+        let signatures = self
+            .tasks
+            .iter()
+            .map(|task| {
+                self.args_of(task)
+                    .iter()
+                    .fold(task.name.to_owned(), |acc, arg| {
+                        format!("{} <{}>", acc, arg.to_uppercase())
+                    })
+            })
+            .collect::<Vec<_>>();
+        let width = signatures.iter().map(|x| x.len()).max().unwrap_or(0);
+
+        console!("{}", crate::fmt::plain_headline("Tasks:"));
+        for (task, signature) in self.tasks.iter().zip(signatures) {
+            let mut details = vec![];
+            if let Some(about) = &task.about {
+                details.push(about.to_owned());
+            }
+            if !task.tags.is_empty() {
+                let tags = crate::fmt::style(format!("[{}]", task.tags.join(", "))).dim();
+                details.push(tags.to_string());
+            }
+            let signature_col = crate::fmt::style(&signature).bold();
+            if details.is_empty() {
+                console!("  {}", signature_col);
+            } else {
+                console!(
+                    "  {}{}  {}",
+                    signature_col,
+                    " ".repeat(width - signature.len()),
+                    details.join("  ")
+                );
+            }
+        }
+    }
+
+    /// Returns a completion script of task names for a cli named `bin`, which takes a task name
+    /// as its first argument. Descriptions of the tasks are included for zsh and fish.
+    ///
+    /// ```ignore
+    /// // dev completions zsh > ~/.zfunc/_dev
+    /// if let ["completions", "zsh"] = args[..] {
+    ///     print!("{}", registry.completions("dev", CompletionShell::Zsh));
+    /// }
+    /// ```
+    pub fn completions(&self, bin: &str, shell: CompletionShell) -> String {
+        let func = format!(
+            "_{}",
+            bin.replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+        );
+        match shell {
+            CompletionShell::Bash => {
+                let names = self
+                    .tasks
+                    .iter()
+                    .map(|task| task.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                BASH_COMPLETION
+                    .replace("{func}", &func)
+                    .replace("{names}", &crate::cmd::sh_quote(&names))
+                    .replace("{bin}", bin)
+            }
+            CompletionShell::Zsh => {
+                let tasks = self
+                    .tasks
+                    .iter()
+                    .map(|task| {
+                        let name = task.name.replace('\\', "\\\\").replace(':', "\\:");
+                        let item = match &task.about {
+                            Some(about) => format!("{}:{}", name, about),
+                            None => name,
+                        };
+                        format!("        {}\n", crate::cmd::sh_quote(&item))
+                    })
+                    .collect::<String>();
+                ZSH_COMPLETION
+                    .replace("{func}", &func)
+                    .replace("{tasks}", &tasks)
+                    .replace("{bin}", bin)
+            }
+            CompletionShell::Fish => self
+                .tasks
+                .iter()
+                .map(|task| {
+                    let mut line = format!(
+                        "complete -c {} -f -n __fish_use_subcommand -a {}",
+                        bin,
+                        self::fish_quote(&task.name)
+                    );
+                    if let Some(about) = &task.about {
+                        line.push_str(" -d ");
+                        line.push_str(&self::fish_quote(about));
+                    }
+                    line.push('\n');
+                    line
+                })
+                .collect(),
+        }
+    }
+
+    /// Runs a task by its name.
+    pub async fn run(&self, name: &str) -> Result<()> {
+        self.run_with_args(name, &TaskArgs::new()).await
+    }
+
+    /// Runs a task by its name with the arguments. Dependencies of a task are run first with the same arguments.
+    ///
+    /// Before running anything, it checks that all the involved tasks are registered, have the required arguments
+    /// and don't depend on each other in a cycle.
+    pub async fn run_with_args(&self, name: &str, args: &TaskArgs) -> Result<()> {
+        self.check(name, args, &mut vec![])?;
+        self.run_task(name, args, &Invocation::default()).await
+    }
+
+    /// Exports a run of a task as a shell script instead of running it. See [`Script`](Script).
+    ///
+    /// Commands are written in the order they would run, with their `{placeholder}`s filled.
+    /// Tasks that run in parallel are written one after another. Async functions, process pools and hooks
+    /// can't be expressed in a script, so they are only mentioned in comments. Inputs and outputs are not checked,
+    /// so the script runs every command.
+    ///
+    /// ```ignore
+    /// registry.export("build", &args, ScriptDialect::Bash)?.write("build.sh")?;
+    /// ```
+    pub fn export(&self, name: &str, args: &TaskArgs, dialect: ScriptDialect) -> Result<Script> {
+        self.check(name, args, &mut vec![])?;
+        let mut script = Script::new(dialect);
+        self.export_task(name, args, &mut script, &mut vec![])?;
+        Ok(script)
+    }
+
+    fn export_task<'a>(
+        &'a self,
+        name: &'a str,
+        args: &TaskArgs,
+        script: &mut Script,
+        visited: &mut Vec<&'a str>,
+    ) -> Result<()> {
+        // Same as on run, every task is exported once
+        if visited.contains(&name) {
+            return Ok(());
+        }
+        visited.push(name);
+        let task = self.get(name).ok_or_else(|| Error::UnknownTask {
+            name: name.to_owned(),
+        })?;
+
+        for dep in &task.deps {
+            self.export_task(dep, args, script, visited)?;
+        }
+        let hooks = &task.hooks;
+        if !hooks.before.is_empty() || !hooks.after.is_empty() || !hooks.after_failure.is_empty() {
+            script.push_comment(&format!("Task {} has hooks, which are not exported", name));
+        }
+        match &task.kind {
+            TaskKind::Cmd(cmd) => script.push_cmd(&task.filled(cmd, args)?),
+            TaskKind::Fn(_) => script.push_comment(&format!(
+                "Task {} runs an async function, which is not exported",
+                name
+            )),
+            TaskKind::Pool(_) => script.push_comment(&format!(
+                "Task {} runs a process pool, which is not exported",
+                name
+            )),
+            TaskKind::Seq(names) | TaskKind::Par(names) => {
+                for name in names {
+                    self.export_task(name, args, script, visited)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns names of the arguments of a task, including the arguments of its dependencies and grouped tasks.
+    pub fn args_of<'a>(&'a self, task: &'a Task<Loc>) -> Vec<&'a str> {
+        let mut args = vec![];
+        self.collect_args(task, &mut args, &mut vec![]);
+        args
+    }
+
+    fn collect_args<'a>(
+        &'a self,
+        task: &'a Task<Loc>,
+        args: &mut Vec<&'a str>,
+        visited: &mut Vec<&'a str>,
+    ) {
+        if visited.contains(&task.name.as_str()) {
+            return;
+        }
+        visited.push(&task.name);
+        for arg in task.args() {
+            if !args.contains(&arg) {
+                args.push(arg);
+            }
+        }
+        for name in task.prerequisites() {
+            if let Some(task) = self.get(name) {
+                self.collect_args(task, args, visited);
+            }
+        }
+    }
+
+    fn check(&self, name: &str, args: &TaskArgs, stack: &mut Vec<String>) -> Result<()> {
+        let task = self.get(name).ok_or_else(|| Error::UnknownTask {
+            name: name.to_owned(),
+        })?;
+
+        if let Some(idx) = stack.iter().position(|x| x == name) {
+            let mut tasks = stack[idx..].to_vec();
+            tasks.push(name.to_owned());
+            return Err(Error::TaskCycle { tasks });
+        }
+
+        task.check_args(args)?;
+
+        stack.push(name.to_owned());
+        for name in task.prerequisites() {
+            self.check(name, args, stack)?;
+        }
+        stack.pop();
+
+        Ok(())
+    }
+
+    fn run_task<'a>(
+        &'a self,
+        name: &'a str,
+        args: &'a TaskArgs,
+        run: &'a Invocation,
+    ) -> TaskFuture<'a> {
+        Box::pin(async move {
+            let task = self.get(name).ok_or_else(|| Error::UnknownTask {
+                name: name.to_owned(),
+            })?;
+
+            // If the task is already run (or running) within this invocation, the result of that run is reused
+            let mut res = Ok(());
+            let succeeded = *run
+                .cell(name)
+                .get_or_init(|| async {
+                    res = self.exec_task(task, args, run).await;
+                    res.is_ok()
+                })
+                .await;
+
+            match res {
+                Err(err) => Err(err),
+                Ok(()) if succeeded => Ok(()),
+                Ok(()) => Err(Error::TaskFailed {
+                    name: name.to_owned(),
+                }),
+            }
+        })
+    }
+
+    async fn exec_task(&self, task: &Task<Loc>, args: &TaskArgs, run: &Invocation) -> Result<()> {
+        task.hooks
+            .around(self.exec_task_body(task, args, run))
+            .await
+    }
+
+    async fn exec_task_body(
+        &self,
+        task: &Task<Loc>,
+        args: &TaskArgs,
+        run: &Invocation,
+    ) -> Result<()> {
+        for dep in &task.deps {
+            self.run_task(dep, args, run).await?;
+        }
+
+        // Inputs are checked after the dependencies, since they might produce them
+        let stamp = self
+            .state_dir
+            .join("inputs")
+            .join(crate::checkpoint::sanitize(&task.name));
+        if task.io.is_declared() && !self.force && task.io.is_fresh(&stamp)? {
+            crate::print(format!("Skipping {}: up to date", task.name));
+            return Ok(());
+        }
+
+        let res = match &task.kind {
+            TaskKind::Seq(names) => {
+                task.budget
+                    .enforce(&task.name, async {
+                        for name in names {
+                            self.run_task(name, args, run).await?;
+                        }
+                        Ok(())
+                    })
+                    .await
+            }
+            TaskKind::Par(names) => {
+                let runs = names
+                    .iter()
+                    .map(|name| self.run_task(name, args, run))
+                    .collect();
+                task.budget
+                    .enforce(&task.name, async {
+                        Error::collect(self::join_all(runs).await).map(drop)
+                    })
+                    .await
+            }
+            TaskKind::Cmd(_) | TaskKind::Fn(_) | TaskKind::Pool(_) => task.exec(args).await,
+        };
+
+        if res.is_ok() && task.io.is_declared() {
+            task.io.commit(&stamp)?;
+        }
+        res
+    }
+}
+
+/// State of a single [`Registry::run_with_args`](Registry::run_with_args) call.
+/// Holds an outcome of every task, so shared prerequisites are run only once.
+#[derive(Default)]
+struct Invocation {
+    runs: Mutex<HashMap<String, Arc<OnceCell<bool>>>>,
+}
+
+impl Invocation {
+    fn cell(&self, name: &str) -> Arc<OnceCell<bool>> {
+        let mut runs = self.runs.lock().unwrap();
+        runs.entry(name.to_owned()).or_default().clone()
+    }
+}
+
+/// Polls the futures concurrently until all of them complete and returns their results in order.
+async fn join_all(mut futures: Vec<TaskFuture<'_>>) -> Vec<Result<()>> {
+    let mut results: Vec<Option<Result<()>>> = futures.iter().map(|_| None).collect();
+    std::future::poll_fn(|cx| {
+        let mut pending = false;
+        for (future, res) in futures.iter_mut().zip(results.iter_mut()) {
+            if res.is_none() {
+                match future.as_mut().poll(cx) {
+                    Poll::Ready(x) => *res = Some(x),
+                    Poll::Pending => pending = true,
+                }
+            }
+        }
+        if pending {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    })
+    .await;
+    results.into_iter().flatten().collect()
+}
+
+impl<Loc> Default for Registry<Loc>
+where
+    Loc: Location + Clone + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Loc> Task<Loc>
+where
+    Loc: Location + Clone + 'static,
+{
+    /// Constructs a task that runs a command via [`Cmd::run`](Cmd::run).
+    /// The message of the command is used as a description of the task.
+    pub fn cmd(name: impl Into<String>, cmd: Cmd<Loc>) -> Self {
+        let about = cmd.msg().cloned();
+        Self::new(name, about, TaskKind::Cmd(Box::new(cmd)))
+    }
+
+    /// Constructs a task that runs an async function with the arguments of the task.
+    pub fn func<F, Fut>(name: impl Into<String>, f: F) -> Self
+    where
+        F: Fn(TaskArgs) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        Self::new(
+            name,
+            None,
+            TaskKind::Fn(Box::new(move |args| Box::pin(f(args)))),
+        )
+    }
+
+    /// Constructs a task that runs a [`ProcessPool`](ProcessPool) of the entries returned by the function.
+    /// See also [`pool!`](crate::pool!) macro.
+    pub fn pool<F>(name: impl Into<String>, f: F) -> Self
+    where
+        F: Fn() -> Vec<PoolEntry<Loc, dyn Dependency>> + Send + Sync + 'static,
+    {
+        Self::new(name, None, TaskKind::Pool(Box::new(f)))
+    }
+
+    /// Constructs a task that runs the tasks in sequence. Stops on the first failure.
+    pub fn seq<N: Into<String>>(
+        name: impl Into<String>,
+        tasks: impl IntoIterator<Item = N>,
+    ) -> Self {
+        let tasks = tasks.into_iter().map(Into::into).collect();
+        Self::new(name, None, TaskKind::Seq(tasks))
+    }
+
+    /// Constructs a task that runs the tasks in parallel. Waits for all of them to complete
+    /// and returns their errors, if any. See [`Error::Errors`](crate::Error::Errors).
+    pub fn par<N: Into<String>>(
+        name: impl Into<String>,
+        tasks: impl IntoIterator<Item = N>,
+    ) -> Self {
+        let tasks = tasks.into_iter().map(Into::into).collect();
+        Self::new(name, None, TaskKind::Par(tasks))
+    }
+
+    fn new(name: impl Into<String>, about: Option<String>, kind: TaskKind<Loc>) -> Self {
+        Self {
+            name: name.into(),
+            about,
+            tags: vec![],
+            deps: vec![],
+            args: vec![],
+            hooks: Hooks::default(),
+            budget: Budget::default(),
+            io: Inputs::default(),
+            kind,
+        }
+    }
+
+    /// Sets a description of the task.
+    pub fn about(mut self, about: impl Into<String>) -> Self {
+        self.about = Some(about.into());
+        self
+    }
+
+    /// Adds a tag to the task.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Adds a task that is run before the task.
+    pub fn dep(mut self, name: impl Into<String>) -> Self {
+        self.deps.push(name.into());
+        self
+    }
+
+    /// Adds tasks that are run before the task, in order.
+    pub fn deps<N: Into<String>>(mut self, names: impl IntoIterator<Item = N>) -> Self {
+        self.deps.extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds an argument to the task, e.g. for a function task. `{placeholder}`s of a command are added automatically.
+    pub fn arg(mut self, name: impl Into<String>) -> Self {
+        self.args.push(name.into());
+        self
+    }
+
+    /// Adds a hook that runs before the task and its dependencies. See [`Hooks`](Hooks).
+    pub fn before(mut self, hook: Hook) -> Self {
+        self.hooks = self.hooks.before(hook);
+        self
+    }
+
+    /// Adds a hook that runs after the task, whether it succeeded or failed. See [`Hooks`](Hooks).
+    pub fn after(mut self, hook: Hook) -> Self {
+        self.hooks = self.hooks.after(hook);
+        self
+    }
+
+    /// Adds a hook that runs after the task failed. See [`Hooks`](Hooks).
+    pub fn after_failure(mut self, hook: Hook) -> Self {
+        self.hooks = self.hooks.after_failure(hook);
+        self
+    }
+
+    /// Adds a glob of the files the task depends on, e.g. `server/src/**/*.rs`. Relative globs are resolved
+    /// against the current directory. A task with declared inputs and outputs is skipped when its outputs are up to date.
+    /// See [`Freshness`](Freshness).
+    ///
+    /// ```ignore
+    /// Task::cmd("client:build", client::build())
+    ///     .input(Loc::client().as_path().join("src/**/*.res"))
+    ///     .input(Loc::client().as_path().join("bsconfig.json"))
+    ///     .output(Loc::client().as_path().join("lib/bs"))
+    /// ```
+    pub fn input(mut self, glob: impl AsRef<Path>) -> Self {
+        self.io
+            .inputs
+            .push(glob.as_ref().to_string_lossy().into_owned());
+        self
+    }
+
+    /// Adds a file or a directory the task produces. See [`Task::input`](Task::input).
+    pub fn output(mut self, path: impl Into<PathBuf>) -> Self {
+        self.io.outputs.push(path.into());
+        self
+    }
+
+    /// Sets a time budget of the task, excluding its dependencies. See [`Budget`](Budget).
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Sets how the task decides whether its outputs are up to date. See [`Freshness`](Freshness).
+    pub fn freshness(mut self, freshness: Freshness) -> Self {
+        self.io.freshness = freshness;
+        self
+    }
+
+    /// Returns a name of a task.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns names of the tasks that a task runs: its dependencies followed by the grouped tasks.
+    fn prerequisites(&self) -> impl Iterator<Item = &String> {
+        let grouped = match &self.kind {
+            TaskKind::Seq(names) | TaskKind::Par(names) => names.as_slice(),
+            TaskKind::Cmd(_) | TaskKind::Fn(_) | TaskKind::Pool(_) => &[],
+        };
+        self.deps.iter().chain(grouped)
+    }
+
+    /// Returns names of the arguments of a task: `{placeholder}`s of its command, in order of appearance,
+    /// followed by the arguments added via [`Task::arg`](Task::arg).
+    pub fn args(&self) -> Vec<&str> {
+        let mut args = match &self.kind {
+            TaskKind::Cmd(cmd) => self::placeholders(cmd.exe()),
+            TaskKind::Fn(_) | TaskKind::Pool(_) | TaskKind::Seq(_) | TaskKind::Par(_) => vec![],
+        };
+        for arg in &self.args {
+            if !args.contains(&arg.as_str()) {
+                args.push(arg);
+            }
+        }
+        args
+    }
+
+    fn check_args(&self, args: &TaskArgs) -> Result<()> {
+        match self.args().into_iter().find(|arg| !args.contains_key(*arg)) {
+            Some(arg) => Err(Error::MissingTaskArg {
+                task: self.name.to_owned(),
+                arg: arg.to_owned(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// The command with the `{placeholder}`s filled with the arguments.
+    fn filled(&self, cmd: &Cmd<Loc>, args: &TaskArgs) -> Result<Cmd<Loc>> {
+        let exe = self::fill(cmd.exe(), args, &cmd.shell).map_err(|arg| Error::MissingTaskArg {
+            task: self.name.to_owned(),
+            arg,
+        })?;
+        let budget = if self.budget.is_none() {
+            cmd.budget
+        } else {
+            self.budget
+        };
+        Ok(Cmd {
+            exe,
+            budget,
+            ..cmd.clone()
+        })
+    }
+
+    async fn exec(&self, args: &TaskArgs) -> Result<()> {
+        match &self.kind {
+            // The command enforces the budget on its own
+            TaskKind::Cmd(cmd) => self.filled(cmd, args)?.run().await,
+            TaskKind::Fn(f) => self.budget.enforce(&self.name, f(args.to_owned())).await,
+            TaskKind::Pool(f) => {
+                let deadline = self.budget.hard.map(|x| time::Instant::now() + x);
+                let handle = ProcessPool::start(f(), PoolOptions::default());
+                self.budget
+                    .watch(
+                        &self.name,
+                        handle.wait_until(crate::process::deadline(deadline)),
+                    )
+                    .await
+            }
+            // Groups are run by the registry
+            TaskKind::Seq(_) | TaskKind::Par(_) => Ok(()),
+        }
+    }
+}
+
+/// Parses `{placeholder}`s of a command template. Shell expansions, like `${VAR}`, are not placeholders.
+fn placeholders(template: &str) -> Vec<&str> {
+    let mut res: Vec<&str> = vec![];
+    for (_, name, _) in self::scan(template) {
+        if !res.contains(&name) {
+            res.push(name);
+        }
+    }
+    res
+}
+
+/// Fills `{placeholder}`s of a command template with the arguments quoted for the shell.
+/// Returns a name of a missing argument on error.
+fn fill(template: &str, args: &TaskArgs, shell: &Shell) -> std::result::Result<String, String> {
+    let mut res = String::with_capacity(template.len());
+    let mut last = 0;
+    for (start, name, end) in self::scan(template) {
+        let val = args.get(name).ok_or_else(|| name.to_owned())?;
+        res.push_str(&template[last..start]);
+        res.push_str(&shell.quote(val));
+        last = end;
+    }
+    res.push_str(&template[last..]);
+    Ok(res)
+}
+
+/// Returns start, name and end of each placeholder in a template.
+fn scan(template: &str) -> Vec<(usize, &str, usize)> {
+    let bytes = template.as_bytes();
+    let mut res = vec![];
+    let mut idx = 0;
+    while let Some(offset) = template[idx..].find('{') {
+        let start = idx + offset;
+        idx = start + 1;
+        if start > 0 && bytes[start - 1] == b'$' {
+            continue;
+        }
+        let len = match template[idx..].find('}') {
+            Some(len) => len,
+            None => break,
+        };
+        let name = &template[idx..idx + len];
+        let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if valid {
+            idx += len + 1;
+            res.push((start, name, idx));
+        }
+    }
+    res
+}
+
+/// Completes a task name as the first argument. Bash treats `:` as a word break, so the current word
+/// is taken from the line, and the part of a task name before the last `:` is removed from the candidates.
+const BASH_COMPLETION: &str = r#"{func}() {
+    local line="${COMP_LINE:0:$COMP_POINT}"
+    local words=($line)
+    local cur=""
+    if [[ "$line" != *" " ]]; then
+        cur="${words[${#words[@]}-1]}"
+        words=("${words[@]:0:${#words[@]}-1}")
+    fi
+    if [ "${#words[@]}" -eq 1 ]; then
+        local prefix="${cur%"${cur##*:}"}"
+        COMPREPLY=($(compgen -W {names} -- "$cur"))
+        COMPREPLY=("${COMPREPLY[@]#"$prefix"}")
+    fi
+}
+complete -F {func} {bin}
+"#;
+
+const ZSH_COMPLETION: &str = r#"#compdef {bin}
+
+{func}() {
+    local -a tasks
+    tasks=(
+{tasks}    )
+    if (( CURRENT == 2 )); then
+        _describe 'task' tasks
+    fi
+}
+
+compdef {func} {bin}
+"#;
+
+/// Single quotes a value for fish, which, unlike `sh`, supports escapes within single quotes.
+fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+#[cfg(feature = "clap")]
+impl<Loc> Registry<Loc>
+where
+    Loc: Location + Clone + 'static,
+{
+    /// Returns clap subcommands for the tasks of the registry: a name of a subcommand is the name of a task,
+    /// its description is the description of a task and its positional arguments are the arguments of a task.
+    /// Requires `clap` feature.
+    ///
+    /// ```ignore
+    /// let cli = clap::Command::new("dev")
+    ///     .subcommand_required(true)
+    ///     .subcommands(registry.clap_subcommands());
+    ///
+    /// registry.run_matches(&cli.get_matches()).await?;
+    /// ```
+    pub fn clap_subcommands(&self) -> Vec<clap::Command> {
+        self.tasks
+            .iter()
+            .map(|task| {
+                let cmd = clap::Command::new(task.name.to_owned());
+                let cmd = match &task.about {
+                    Some(about) => cmd.about(about.to_owned()),
+                    None => cmd,
+                };
+                self.args_of(task).into_iter().fold(cmd, |cmd, arg| {
+                    cmd.arg(
+                        clap::Arg::new(arg.to_owned())
+                            .value_name(arg.to_uppercase())
+                            .required(true),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Runs a task that matches the subcommand of the clap matches. Requires `clap` feature.
+    /// Returns `false` if there is no subcommand or it is not a task of the registry,
+    /// so other subcommands of a cli can be handled by the caller.
+    pub async fn run_matches(&self, matches: &clap::ArgMatches) -> Result<bool> {
+        let (name, matches) = match matches.subcommand() {
+            Some((name, matches)) => (name, matches),
+            None => return Ok(false),
+        };
+        let task = match self.get(name) {
+            Some(task) => task,
+            None => return Ok(false),
+        };
+        let args = self
+            .args_of(task)
+            .into_iter()
+            .filter_map(|arg| {
+                matches
+                    .get_one::<String>(arg)
+                    .map(|val| (arg.to_owned(), val.to_owned()))
+            })
+            .collect();
+        self.run_with_args(name, &args).await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::TaskArgs;
+    use crate::{Error, Location, Registry, Shell, Task};
+
+    #[derive(Clone)]
+    struct Loc(PathBuf);
+
+    impl Location for Loc {
+        fn apex() -> Self {
+            Self(std::env::temp_dir())
+        }
+
+        fn as_path(&self) -> &PathBuf {
+            &self.0
+        }
+    }
+
+    fn fail(name: &'static str) -> Task<Loc> {
+        Task::func(name, move |_| async move {
+            Err(Error::IoError(std::io::Error::other(format!(
+                "{} is broken",
+                name
+            ))))
+        })
+    }
+
+    #[tokio::test]
+    async fn par_errors() {
+        let registry = Registry::<Loc>::new()
+            .task(fail("a"))
+            .task(Task::func("b", |_| async { Ok(()) }))
+            .task(fail("c"))
+            .task(Task::par("all", ["a", "b", "c"]));
+        let error = registry.run("all").await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "2 of 3 failed:\n- IO error: a is broken\n- IO error: c is broken"
+        );
+    }
+
+    #[test]
+    fn fish_quote() {
+        assert_eq!(super::fish_quote("db:migrate"), "'db:migrate'");
+        assert_eq!(super::fish_quote(r"it's a\b"), r"'it\'s a\\b'");
+    }
+
+    #[test]
+    fn placeholders_skip_shell_expansions() {
+        assert_eq!(
+            super::placeholders("cargo run --bin {bin} -- {port} ${HOME} {bin} '{print $1}'"),
+            vec!["bin", "port"]
+        );
+    }
+
+    #[test]
+    fn fill_placeholders() {
+        let sh = Shell::new("sh");
+        let args = TaskArgs::from([("bin".to_string(), "server".to_string())]);
+        assert_eq!(
+            super::fill("cargo run --bin {bin} ${HOME}", &args, &sh),
+            Ok("cargo run --bin server ${HOME}".to_string())
+        );
+        assert_eq!(
+            super::fill("{bin} {port}", &args, &sh),
+            Err("port".to_string())
+        );
+    }
+
+    #[test]
+    fn fill_quotes_arguments() {
+        let sh = Shell::new("sh");
+        let args = TaskArgs::from([
+            ("bin".to_string(), "my app".to_string()),
+            ("msg".to_string(), "it's; rm -rf ~".to_string()),
+        ]);
+        assert_eq!(
+            super::fill("run {bin} {msg}", &args, &sh),
+            Ok(r"run 'my app' 'it'\''s; rm -rf ~'".to_string())
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{io, process, string};
+use std::{io, path::PathBuf, process, string, time::Duration};
 
 /// Result type of this crate.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -11,8 +11,9 @@ pub type KillError = nix::Error;
 #[cfg(windows)]
 pub type KillError = winapi::shared::minwindef::DWORD;
 
-/// Error type of this crate.
+/// Error type of this crate. More variants might be added, so a `match` on it needs a wildcard arm.
 #[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// IO error that might happen during command / process execution.
     #[error("IO error: {0}")]
@@ -30,6 +31,135 @@ pub enum Error {
     /// which is unexpected in the context of this program.
     #[error("Process does not exist.")]
     ProcessDoesNotExist,
+    /// Error raised before spawning a process when its working directory does not exist or is not a directory.
+    #[error("Working directory {} does not exist or is not a directory.", .path.display())]
+    InvalidWorkingDir {
+        /// Working directory of the command.
+        path: PathBuf,
+    },
+    /// Error raised when values of an [`Env`](crate::Env) reference each other in a cycle.
+    #[error("Environment variables reference each other in a cycle: {}.", .keys.join(" -> "))]
+    EnvCycle {
+        /// Keys that form the cycle. The first key is repeated at the end.
+        keys: Vec<String>,
+    },
+    /// Error raised when a value of an [`Env`](crate::Env) references a variable that is not defined
+    /// either in the environment or in the environment of the current process.
+    #[error("Environment variable {key} references undefined variable {var}.", key = .key, var = .var)]
+    EnvUndefined {
+        /// Key which value contains the reference.
+        key: String,
+        /// Referenced variable.
+        var: String,
+    },
+    /// Error raised when a task is not found in a [`Registry`](crate::Registry).
+    #[error("Task {name} is not registered.", name = .name)]
+    UnknownTask {
+        /// Name of the task.
+        name: String,
+    },
+    /// Error raised when tasks of a [`Registry`](crate::Registry) depend on each other in a cycle.
+    #[error("Tasks depend on each other in a cycle: {}.", .tasks.join(" -> "))]
+    TaskCycle {
+        /// Names of the tasks that form the cycle. The first task is repeated at the end.
+        tasks: Vec<String>,
+    },
+    /// Error raised when a task of a [`Registry`](crate::Registry) is required by several tasks within one run
+    /// and its first run failed.
+    #[error("Task {name} failed earlier in this run.", name = .name)]
+    TaskFailed {
+        /// Name of the task.
+        name: String,
+    },
+    /// Error raised when a task of a [`Registry`](crate::Registry) is run without one of its arguments.
+    #[error("Task {task} requires argument {arg}.", task = .task, arg = .arg)]
+    MissingTaskArg {
+        /// Name of the task.
+        task: String,
+        /// Name of the missing argument.
+        arg: String,
+    },
+    /// Error raised when a process of a [`ProcessPool`](crate::ProcessPool) fails and the pool is shut down
+    /// because of [`PoolOptions::fail_fast`](crate::PoolOptions::fail_fast).
+    #[error("Process {tag} {reason}.", tag = .tag, reason = .reason)]
+    ProcessFailed {
+        /// Tag of the process.
+        tag: String,
+        /// Why the process failed, e.g. `exited with code 1`.
+        reason: String,
+    },
+    /// Error raised when a duration can't be parsed. See [`parse_duration`](crate::parse_duration).
+    #[error("{value:?} is not a valid duration: {reason}.", value = .value, reason = .reason)]
+    InvalidDuration {
+        /// The value that can't be parsed.
+        value: String,
+        /// Why the value can't be parsed.
+        reason: String,
+    },
+    /// Error raised when a [`ProcessPool`](crate::ProcessPool) can't be started, e.g. two of its processes share a tag.
+    #[error("The pool is invalid: {reason}.", reason = .reason)]
+    InvalidPool {
+        /// Why the pool is invalid.
+        reason: String,
+    },
+    /// Error raised when a [`ProcessPool`](crate::ProcessPool) is not going to get ready.
+    /// See [`PoolHandle::ready`](crate::PoolHandle::ready).
+    #[error("The pool is not ready: {reason}.", reason = .reason)]
+    PoolNotReady {
+        /// Why the pool is not ready, e.g. a process is not started because its dependency errored.
+        reason: String,
+    },
+    /// Error raised when a process of a [`ProcessPool`](crate::ProcessPool) can't be paused or resumed,
+    /// since it's not running at the moment. See [`ProcessHandle`](crate::ProcessHandle).
+    #[error("Process {tag} is not running.", tag = .tag)]
+    ProcessNotRunning {
+        /// Tag of the process.
+        tag: String,
+    },
+    /// Error raised by [`os::terminate`](crate::os::terminate) when there is no running process with the pid.
+    #[error("Process with pid {pid} is not running.", pid = .pid)]
+    NoSuchProcess {
+        /// Process id.
+        pid: u32,
+    },
+    /// Error raised by [`os::terminate`](crate::os::terminate) when a process can't be terminated,
+    /// e.g. it belongs to another user. The error of the OS is the same on every platform.
+    #[error("Failed to terminate the process with pid {pid}: {error}", pid = .pid, error = .error)]
+    TerminateFailed {
+        /// Process id.
+        pid: u32,
+        /// Error of the OS.
+        error: io::Error,
+    },
+    /// Error raised when a command or a task exceeds its hard time budget. See [`Budget`](crate::Budget).
+    #[error("{name} exceeded its hard time budget of {budget}.", name = .name, budget = crate::fmt::duration(*.budget))]
+    BudgetExceeded {
+        /// Message of the command, or name of the task.
+        name: String,
+        /// The hard limit.
+        budget: Duration,
+    },
+    /// Error raised when output of a command can't be parsed, e.g. by [`Cmd::output_json`](crate::Cmd::output_json).
+    #[error("Failed to parse output of {cmd}: {reason}. Stderr: {stderr:?}", cmd = .cmd, reason = .reason, stderr = .stderr)]
+    InvalidOutput {
+        /// Message of the command, or the command itself.
+        cmd: String,
+        /// Why the output can't be parsed, or `interrupted` when the command was interrupted.
+        reason: String,
+        /// Stdout of the command.
+        stdout: Vec<u8>,
+        /// Stderr of the command, lossily converted to UTF-8.
+        stderr: String,
+    },
+    /// Error raised when several operations that run together failed, e.g. tasks of [`Task::par`](crate::Task::par).
+    /// Holds every failure, so none of the causes is hidden.
+    #[error("{} of {} failed:{}", .errors.len(), .total, self::list(.errors))]
+    Errors {
+        /// Errors of the failed operations, in order.
+        errors: Vec<Error>,
+        /// Number of operations, including the succeeded ones.
+        total: usize,
+    },
     /// When a process manager failed to kill hanged child process, there is a zombie process left hanging around.
     /// This error provides details, such as process id and an error, so user could handle cleaning manually.
     #[cfg(unix)]
@@ -52,6 +182,35 @@ pub enum Error {
     },
 }
 
+impl Error {
+    /// Collects the results of operations that ran together. A single failure is returned as is,
+    /// while several failures are returned as [`Error::Errors`](Error::Errors).
+    pub(crate) fn collect<T>(results: impl IntoIterator<Item = Result<T>>) -> Result<Vec<T>> {
+        let mut values = vec![];
+        let mut errors = vec![];
+        for res in results {
+            match res {
+                Ok(value) => values.push(value),
+                Err(error) => errors.push(error),
+            }
+        }
+        let total = values.len() + errors.len();
+        match errors.len() {
+            0 => Ok(values),
+            1 => Err(errors.remove(0)),
+            _ => Err(Self::Errors { errors, total }),
+        }
+    }
+}
+
+/// Formats errors as a list, one per line. Lines of multiline errors are indented.
+fn list(errors: &[Error]) -> String {
+    errors
+        .iter()
+        .map(|error| format!("\n- {}", error.to_string().replace('\n', "\n  ")))
+        .collect()
+}
+
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Self {
         Self::IoError(err)
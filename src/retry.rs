@@ -0,0 +1,208 @@
+use std::time::{Duration, Instant};
+
+use tokio::time;
+
+/// Delays between attempts of a [`RetryPolicy`](RetryPolicy).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backoff {
+    /// The same delay between all attempts.
+    Fixed(Duration),
+    /// The delay starts with `initial` and doubles after every failed attempt, up to `max`.
+    Exponential {
+        /// Delay after the first failed attempt.
+        initial: Duration,
+        /// Max delay between attempts.
+        max: Duration,
+    },
+}
+
+impl Backoff {
+    /// Returns a delay after the given number of failed attempts (starting with 1).
+    pub fn delay(&self, failed: u32) -> Duration {
+        match self {
+            Self::Fixed(delay) => *delay,
+            Self::Exponential { initial, max } => {
+                let factor = 2u32.saturating_pow(failed.saturating_sub(1));
+                initial.saturating_mul(factor).min(*max)
+            }
+        }
+    }
+}
+
+/// Defines how an operation is retried: how long to wait between attempts and when to give up.
+///
+/// The same policy type is used to wait for dependencies (see [`dep::Retry`](crate::dep::Retry)),
+/// to retry commands (see [`Cmd::run_with_retry`](crate::Cmd::run_with_retry)) and to restart processes
+/// of a [`ProcessPool`](crate::ProcessPool) (see [`Process::backoff`](crate::Process::backoff)).
+///
+/// ```ignore
+/// let policy = RetryPolicy::exponential(Duration::from_millis(100), Duration::from_secs(5))
+///     .max_attempts(10)
+///     .timeout(Duration::from_secs(60));
+///
+/// db::migrate().run_with_retry(&policy).await?;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Delays between attempts.
+    pub backoff: Backoff,
+    /// Max number of attempts, including the first one. Unlimited if `None`.
+    pub max_attempts: Option<u32>,
+    /// Overall time limit of all attempts. Unlimited if `None`.
+    pub timeout: Option<Duration>,
+}
+
+impl RetryPolicy {
+    /// Interval used by the built-in dependencies to poll for availability.
+    pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+    /// Constructs an unlimited policy with the fixed delay between attempts.
+    pub fn fixed(delay: Duration) -> Self {
+        Self {
+            backoff: Backoff::Fixed(delay),
+            max_attempts: None,
+            timeout: None,
+        }
+    }
+
+    /// Constructs an unlimited policy with the exponential backoff. See [`Backoff::Exponential`](Backoff::Exponential).
+    pub fn exponential(initial: Duration, max: Duration) -> Self {
+        Self {
+            backoff: Backoff::Exponential { initial, max },
+            max_attempts: None,
+            timeout: None,
+        }
+    }
+
+    /// Limits the number of attempts, including the first one.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Limits the overall time of all attempts.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Policy of the built-in dependencies: polling with the fixed interval until the timeout.
+    pub(crate) fn poll(timeout: Duration) -> Self {
+        Self::fixed(Self::POLL_INTERVAL).timeout(timeout)
+    }
+
+    pub(crate) fn start(&self) -> Attempts {
+        Attempts {
+            policy: *self,
+            started: Instant::now(),
+            deadline: None,
+            failed: 0,
+        }
+    }
+}
+
+/// State of an operation retried according to a [`RetryPolicy`](RetryPolicy).
+pub(crate) struct Attempts {
+    policy: RetryPolicy,
+    started: Instant,
+    /// A deadline imposed from outside of the policy, e.g. shared by all the dependencies of a pool.
+    deadline: Option<Instant>,
+    failed: u32,
+}
+
+impl Attempts {
+    /// Gives up at the deadline, if it comes before the timeout of the policy.
+    pub(crate) fn until(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Registers a failed attempt and sleeps until the next one.
+    /// Returns `false` right away if the policy doesn't allow another attempt.
+    pub(crate) async fn next(&mut self) -> bool {
+        match self.fail() {
+            Some(delay) => {
+                time::sleep(delay).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Registers a failed attempt and returns a delay before the next one,
+    /// or `None` if the policy doesn't allow another attempt.
+    /// The last delay is shortened to fit the timeout, so the last attempt happens right at the deadline.
+    pub(crate) fn fail(&mut self) -> Option<Duration> {
+        self.failed += 1;
+        if let Some(max) = self.policy.max_attempts {
+            if self.failed >= max {
+                return None;
+            }
+        }
+        let delay = self.policy.backoff.delay(self.failed);
+        match self.remaining() {
+            Some(remaining) if remaining.is_zero() => None,
+            Some(remaining) => Some(delay.min(remaining)),
+            None => Some(delay),
+        }
+    }
+
+    /// Time left until the timeout of the policy or the deadline, whichever comes first.
+    /// `None` if there is neither.
+    pub(crate) fn remaining(&self) -> Option<Duration> {
+        let timeout = self
+            .policy
+            .timeout
+            .map(|timeout| timeout.saturating_sub(self.started.elapsed()));
+        let deadline = self
+            .deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        match (timeout, deadline) {
+            (Some(timeout), Some(deadline)) => Some(timeout.min(deadline)),
+            (timeout, deadline) => timeout.or(deadline),
+        }
+    }
+
+    /// Number of failed attempts so far.
+    pub(crate) fn failed(&self) -> u32 {
+        self.failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponential_backoff() {
+        let backoff = Backoff::Exponential {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(1),
+        };
+        let delays = (1..=6)
+            .map(|x| backoff.delay(x).as_millis())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, [100, 200, 400, 800, 1000, 1000]);
+    }
+
+    #[tokio::test]
+    async fn max_attempts() {
+        let mut attempts = RetryPolicy::fixed(Duration::ZERO).max_attempts(3).start();
+        assert!(attempts.next().await);
+        assert!(attempts.next().await);
+        assert!(!attempts.next().await);
+        assert_eq!(attempts.failed(), 3);
+    }
+
+    #[test]
+    fn deadline() {
+        let policy = RetryPolicy::fixed(Duration::from_secs(1)).timeout(Duration::from_secs(60));
+        let mut attempts = policy
+            .start()
+            .until(Some(Instant::now() + Duration::from_millis(500)));
+        assert!(attempts.remaining().unwrap() <= Duration::from_millis(500));
+        assert!(attempts.fail().unwrap() <= Duration::from_millis(500));
+        let mut attempts = policy.start().until(Some(Instant::now()));
+        assert_eq!(attempts.fail(), None);
+    }
+}
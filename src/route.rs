@@ -0,0 +1,147 @@
+use std::{fmt, path::PathBuf, sync::Arc};
+
+use tokio::{fs, io::AsyncWriteExt};
+
+/// Where a stream (stdout or stderr) of a process that runs as a part of a [`ProcessPool`](crate::ProcessPool) goes.
+/// See [`Process::stdout`](crate::Process::stdout) and [`Process::stderr`](crate::Process::stderr).
+///
+/// Routes can be combined via [`Route::and`](Route::and), e.g. to keep a noisy process quiet in the console
+/// while its full output is still available in a file:
+///
+/// ```ignore
+/// process! { tag: "client", cmd: cmd! { ... } }
+///     .stdout(Route::file("log/client.log"))
+///     .stderr(Route::Console.and(Route::file("log/client.log")))
+/// ```
+#[derive(Clone, Default)]
+pub enum Route {
+    /// Lines are printed to the console, tagged with the process tag.
+    /// [`Process::filter`](crate::Process::filter) and [`Process::exclude`](crate::Process::exclude) apply only here.
+    #[default]
+    Console,
+    /// Lines are discarded.
+    Null,
+    /// Lines are appended to a file, which is created if it doesn't exist.
+    File(PathBuf),
+    /// Lines are passed to a function, e.g. to watch for a specific message.
+    Callback(LineCallback),
+    /// Lines go to every route.
+    All(Vec<Route>),
+}
+
+/// A function that receives lines of a [`Route::Callback`](Route::Callback).
+pub type LineCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+impl Route {
+    /// Constructs a route to a file.
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        Self::File(path.into())
+    }
+
+    /// Constructs a route to a function.
+    pub fn callback(f: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        Self::Callback(Arc::new(f))
+    }
+
+    /// Combines the route with another one, so lines go to both.
+    pub fn and(self, other: Route) -> Self {
+        match self {
+            Self::All(mut routes) => {
+                routes.push(other);
+                Self::All(routes)
+            }
+            route => Self::All(vec![route, other]),
+        }
+    }
+
+    fn visit<'a>(&'a self, leaves: &mut Vec<&'a Route>) {
+        match self {
+            Self::All(routes) => routes.iter().for_each(|route| route.visit(leaves)),
+            route => leaves.push(route),
+        }
+    }
+}
+
+impl fmt::Debug for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Console => write!(f, "Console"),
+            Self::Null => write!(f, "Null"),
+            Self::File(path) => f.debug_tuple("File").field(path).finish(),
+            Self::Callback(_) => write!(f, "Callback"),
+            Self::All(routes) => f.debug_tuple("All").field(routes).finish(),
+        }
+    }
+}
+
+/// Opened destinations of a [`Route`](Route) of a running process.
+pub(crate) struct Outlet {
+    pub(crate) console: bool,
+    files: Vec<fs::File>,
+    callbacks: Vec<LineCallback>,
+}
+
+impl Outlet {
+    /// Opens the files of a route. A file that can't be opened is reported via `warn` and skipped.
+    pub(crate) async fn open(route: &Route, warn: impl Fn(String)) -> Self {
+        let mut leaves = vec![];
+        route.visit(&mut leaves);
+        let mut outlet = Self {
+            console: false,
+            files: vec![],
+            callbacks: vec![],
+        };
+        for leaf in leaves {
+            match leaf {
+                Route::Console => outlet.console = true,
+                Route::Null | Route::All(_) => (),
+                Route::File(path) => {
+                    let file = fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)
+                        .await;
+                    match file {
+                        Ok(file) => outlet.files.push(file),
+                        Err(error) => warn(format!(
+                            "⚠️  Failed to open {}: {}. Output is not written to it.",
+                            path.display(),
+                            error
+                        )),
+                    }
+                }
+                Route::Callback(f) => outlet.callbacks.push(f.clone()),
+            }
+        }
+        outlet
+    }
+
+    /// Writes a line to the files and passes it to the functions of the route. The console is handled by the caller.
+    pub(crate) async fn write(&mut self, line: &str) {
+        for file in &mut self.files {
+            // Output is best effort: a failed write doesn't stop the process
+            let _ = file.write_all(format!("{}\n", line).as_bytes()).await;
+        }
+        for f in &self.callbacks {
+            f(line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combined_routes() {
+        let route = Route::Console
+            .and(Route::file("client.log"))
+            .and(Route::Null.and(Route::callback(|_| ())));
+        let mut leaves = vec![];
+        route.visit(&mut leaves);
+        assert_eq!(
+            format!("{:?}", leaves),
+            r#"[Console, File("client.log"), Null, Callback]"#
+        );
+    }
+}
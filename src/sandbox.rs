@@ -0,0 +1,135 @@
+use std::{io, os::unix::ffi::OsStrExt, path::PathBuf};
+
+use nix::{
+    fcntl::{self, OFlag},
+    mount::{self, MsFlags},
+    sched::{self, CloneFlags},
+    sys::stat::Mode,
+    unistd, NixPath,
+};
+use tokio::process::Command;
+
+/// Sandboxing options of a spawned process. Linux only.
+///
+/// Useful for containing untrusted code generators or third-party build steps.
+/// When the current process is not running as root, the sandbox is created within a new user namespace,
+/// where the current user is mapped to itself, so no extra privileges are required
+/// (as long as unprivileged user namespaces are enabled in the system).
+///
+/// ```ignore
+/// let opts = SpawnOptions::default().sandbox(Sandbox {
+///     read_only: vec![Loc::root().as_path().to_owned()],
+///     no_network: true,
+///     ..Default::default()
+/// });
+///
+/// cmd! { "npx codegen", env: Env::empty(), pwd: Loc::generated() }.spawn(opts)?;
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Sandbox {
+    /// Runs a process in a new mount namespace, so its mounts don't affect the rest of the system.
+    pub mount_ns: bool,
+    /// Runs children of a process in a new PID namespace.
+    /// Note that the shell itself stays in the current namespace, only processes it spawns are moved.
+    pub pid_ns: bool,
+    /// Paths that are made read-only for a process. Implies [`mount_ns`](Sandbox::mount_ns).
+    /// Use [`Cmd::create_pwd`](crate::Cmd::create_pwd) or other dirs to let a process write its output.
+    pub read_only: Vec<PathBuf>,
+    /// Runs a process in a new network namespace without network access.
+    pub no_network: bool,
+}
+
+impl Sandbox {
+    fn flags(&self) -> CloneFlags {
+        let mut flags = CloneFlags::empty();
+        if self.mount_ns || !self.read_only.is_empty() {
+            flags.insert(CloneFlags::CLONE_NEWNS);
+        }
+        if self.pid_ns {
+            flags.insert(CloneFlags::CLONE_NEWPID);
+        }
+        if self.no_network {
+            flags.insert(CloneFlags::CLONE_NEWNET);
+        }
+        flags
+    }
+
+    /// Configures the command to enter the sandbox right before `exec`.
+    pub(crate) fn apply(self, command: &mut Command) {
+        let mut flags = self.flags();
+        if flags.is_empty() {
+            return;
+        }
+
+        // Everything that allocates is prepared before fork
+        let uid = unistd::geteuid();
+        let gid = unistd::getegid();
+        let user_ns = !uid.is_root();
+        if user_ns {
+            flags.insert(CloneFlags::CLONE_NEWUSER);
+        }
+        let uid_map = format!("{uid} {uid} 1");
+        let gid_map = format!("{gid} {gid} 1");
+        let read_only = self.read_only;
+
+        let enter = move || -> nix::Result<()> {
+            sched::unshare(flags)?;
+
+            if user_ns {
+                write("/proc/self/uid_map", uid_map.as_bytes())?;
+                write("/proc/self/setgroups", b"deny")?;
+                write("/proc/self/gid_map", gid_map.as_bytes())?;
+            }
+
+            if flags.contains(CloneFlags::CLONE_NEWNS) {
+                // Prevents propagation of the sandbox mounts to the parent namespace
+                mount::mount::<str, str, str, str>(
+                    None,
+                    "/",
+                    None,
+                    MsFlags::MS_REC | MsFlags::MS_PRIVATE,
+                    None,
+                )?;
+            }
+
+            for path in &read_only {
+                let path = path.as_os_str().as_bytes();
+                mount::mount::<[u8], [u8], str, str>(
+                    Some(path),
+                    path,
+                    None,
+                    MsFlags::MS_BIND | MsFlags::MS_REC,
+                    None,
+                )?;
+                mount::mount::<str, [u8], str, str>(
+                    None,
+                    path,
+                    None,
+                    MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY | MsFlags::MS_REC,
+                    None,
+                )?;
+            }
+
+            Ok(())
+        };
+
+        // SAFETY: The closure only performs system calls, all the data is allocated before fork.
+        unsafe {
+            command.pre_exec(move || enter().map_err(to_io_error));
+        }
+    }
+}
+
+pub(crate) fn write<P: ?Sized + NixPath>(path: &P, data: &[u8]) -> nix::Result<()> {
+    let fd = fcntl::open(path, OFlag::O_WRONLY, Mode::empty())?;
+    let res = unistd::write(fd, data);
+    unistd::close(fd)?;
+    res.map(|_| ())
+}
+
+pub(crate) fn to_io_error(err: nix::Error) -> io::Error {
+    match err.as_errno() {
+        Some(errno) => io::Error::from_raw_os_error(errno as i32),
+        None => io::Error::from(io::ErrorKind::Other),
+    }
+}
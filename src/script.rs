@@ -0,0 +1,255 @@
+use std::{fmt, fs, path::Path};
+
+use crate::{Backend, Cmd, Location, Result, Shell};
+
+/// Dialect of an exported [`Script`](Script).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptDialect {
+    /// Bash script.
+    Bash,
+    /// PowerShell script.
+    PowerShell,
+}
+
+/// A shell script equivalent to a sequence of [`Cmd`](Cmd)s. Instead of running the commands, it explains what
+/// would be run: every command is written with its message, working directory and environment.
+/// Handy for debugging and for environments where only a shell script can run.
+///
+/// ```ignore
+/// let script = Script::bash()
+///     .cmd(&server::build())
+///     .cmd(&client::build());
+///
+/// print!("{}", script);
+/// script.write("build.sh")?;
+/// ```
+///
+/// Every command runs in its own scope, so the working directory doesn't leak to the following commands.
+/// In a Bash script, the same applies to the environment, but a PowerShell script sets the environment
+/// of the session. The script stops on the first failed command.
+///
+/// See also [`Registry::export`](crate::Registry::export).
+pub struct Script {
+    dialect: ScriptDialect,
+    body: String,
+}
+
+impl Script {
+    /// Constructs a new empty script.
+    pub fn new(dialect: ScriptDialect) -> Self {
+        Self {
+            dialect,
+            body: String::new(),
+        }
+    }
+
+    /// Constructs a new empty Bash script.
+    pub fn bash() -> Self {
+        Self::new(ScriptDialect::Bash)
+    }
+
+    /// Constructs a new empty PowerShell script.
+    pub fn powershell() -> Self {
+        Self::new(ScriptDialect::PowerShell)
+    }
+
+    /// Dialect of the script.
+    pub fn dialect(&self) -> ScriptDialect {
+        self.dialect
+    }
+
+    /// Adds a comment. Multiline comments are supported.
+    pub fn comment(mut self, text: impl AsRef<str>) -> Self {
+        self.push_comment(text.as_ref());
+        self
+    }
+
+    /// Adds a command.
+    pub fn cmd<Loc>(mut self, cmd: &Cmd<Loc>) -> Self
+    where
+        Loc: Location,
+    {
+        self.push_cmd(cmd);
+        self
+    }
+
+    /// Writes the script to a file. On Unix, the file is made executable.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_string())?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn push_comment(&mut self, text: &str) {
+        if !self.body.is_empty() {
+            self.body.push('\n');
+        }
+        for line in text.lines() {
+            self.body.push_str(format!("# {}", line).trim_end());
+            self.body.push('\n');
+        }
+    }
+
+    pub(crate) fn push_cmd<Loc>(&mut self, cmd: &Cmd<Loc>)
+    where
+        Loc: Location,
+    {
+        match cmd.msg() {
+            Some(msg) => self.push_comment(msg),
+            None if !self.body.is_empty() => self.body.push('\n'),
+            None => (),
+        }
+
+        let workdir = cmd.workdir();
+        let pwd = workdir.to_string_lossy();
+        let local = cmd.backend.is_local();
+        let env = cmd.env().sorted();
+        let argv = match cmd.backend.argv(cmd.exe(), &cmd.shell, cmd.env()) {
+            Ok(argv) => argv,
+            Err(err) => {
+                self.push_comment(&format!("Unable to export the command: {}", err));
+                return;
+            }
+        };
+        match self.dialect {
+            ScriptDialect::Bash => {
+                let quote = crate::cmd::sh_quote;
+                self.body.push_str("(\n");
+                // Remote backends don't use the working directory
+                if local {
+                    if cmd.create_pwd {
+                        self.body.push_str(&format!("  mkdir -p {}\n", quote(&pwd)));
+                    }
+                    self.body.push_str(&format!("  cd {}\n", quote(&pwd)));
+                }
+                for (key, val) in env {
+                    self.body
+                        .push_str(&format!("  export {}={}\n", key, quote(val)));
+                }
+                // Commands for the default shell are written as is, so the script is easier to read
+                let exe = if cmd.backend == Backend::Local && cmd.shell == Shell::default() {
+                    cmd.exe().to_owned()
+                } else {
+                    argv.iter().map(|x| quote(x)).collect::<Vec<_>>().join(" ")
+                };
+                // Multiline commands are not indented, since it might change their meaning (e.g. of a heredoc)
+                if exe.contains('\n') {
+                    self.body.push_str(&format!("{}\n", exe));
+                } else {
+                    self.body.push_str(&format!("  {}\n", exe));
+                }
+                self.body.push_str(")\n");
+            }
+            ScriptDialect::PowerShell => {
+                let quote = self::ps_quote;
+                self.body.push_str("& {\n");
+                if local && cmd.create_pwd {
+                    self.body.push_str(&format!(
+                        "  New-Item -ItemType Directory -Force -Path {} | Out-Null\n",
+                        quote(&pwd)
+                    ));
+                }
+                if local {
+                    self.body
+                        .push_str(&format!("  Push-Location -LiteralPath {}\n", quote(&pwd)));
+                }
+                self.body.push_str("  try {\n");
+                for (key, val) in env {
+                    self.body
+                        .push_str(&format!("    ${{env:{}}} = {}\n", key, quote(val)));
+                }
+                let exe = argv.iter().map(|x| quote(x)).collect::<Vec<_>>().join(" ");
+                self.body.push_str(&format!("    & {}\n", exe));
+                self.body
+                    .push_str("    if ($LASTEXITCODE -ne 0) { exit $LASTEXITCODE }\n");
+                self.body.push_str("  } finally {\n");
+                if local {
+                    self.body.push_str("    Pop-Location\n");
+                }
+                self.body.push_str("  }\n");
+                self.body.push_str("}\n");
+            }
+        }
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.dialect {
+            ScriptDialect::Bash => writeln!(f, "#!/usr/bin/env bash\nset -e\n")?,
+            ScriptDialect::PowerShell => writeln!(f, "$ErrorActionPreference = 'Stop'\n")?,
+        }
+        write!(f, "{}", self.body)
+    }
+}
+
+/// Single quotes a value for PowerShell, where a single quote within a value is escaped by doubling it.
+fn ps_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::Env;
+
+    #[derive(Clone)]
+    struct Loc(PathBuf);
+
+    impl Location for Loc {
+        fn apex() -> Self {
+            Self(PathBuf::from("/app"))
+        }
+
+        fn as_path(&self) -> &PathBuf {
+            &self.0
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn bash_script() {
+        let cmd = Cmd::new(
+            "cargo build",
+            Env::one("RUST_LOG", "debug info"),
+            Loc(PathBuf::from("/app/my server")),
+            Some("Building a server".to_string()),
+        );
+        let script = Script::bash()
+            .comment("Build")
+            .cmd(&cmd)
+            .cmd(&cmd.clone().shell(Shell::new("bash").flag("-l")));
+        assert_eq!(
+            script.to_string(),
+            [
+                "#!/usr/bin/env bash",
+                "set -e",
+                "",
+                "# Build",
+                "",
+                "# Building a server",
+                "(",
+                "  cd '/app/my server'",
+                "  export RUST_LOG='debug info'",
+                "  cargo build",
+                ")",
+                "",
+                "# Building a server",
+                "(",
+                "  cd '/app/my server'",
+                "  export RUST_LOG='debug info'",
+                "  bash -l -c 'cargo build'",
+                ")",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{Cmd, Dependency, Process};
+
+/// A long-running process of a [`ProcessPool`](crate::ProcessPool) along with the commands that prepare and clean up
+/// after it, and a check that tells when it's ready. The pool manages all of them as a single entry:
+///
+/// 1. Once its [`dependency`](Service::dependency) is ready, the [`setup`](Service::setup) command runs.
+///    If it fails, the process is not started.
+/// 2. The process is started (and restarted) as usual.
+/// 3. On every start, the [`readiness`](Service::readiness) check is awaited. The service counts as started
+///    for [`PoolHandle::ready`](crate::PoolHandle::ready) only once the check passes.
+/// 4. Once the process is stopped for good (including shutdown of the pool), the [`teardown`](Service::teardown)
+///    command runs, if the setup succeeded.
+///
+/// ```ignore
+/// ProcessPool::run_with_deps(pool![
+///     Service::new(process! { tag: "db", cmd: docker::run_postgres() })
+///         .setup(docker::pull_postgres())
+///         .readiness(TcpService::new("postgres", 5432))
+///         .teardown(docker::rm_postgres()),
+///     server::watch(),
+/// ])
+/// .await
+/// ```
+pub struct Service<Loc> {
+    /// The long-running process.
+    pub process: Process<Loc>,
+    /// A dependency that must be ready before the setup command runs. See [`Dependency`](Dependency).
+    pub dependency: Option<Box<dyn Dependency>>,
+    /// A command that runs once before the first start of the process, e.g. to build it.
+    pub setup: Option<Cmd<Loc>>,
+    /// A check that tells the process is ready after a start, e.g. its health endpoint.
+    pub readiness: Option<Box<dyn Dependency>>,
+    /// A command that runs once the process is stopped for good, e.g. to remove its container.
+    pub teardown: Option<Cmd<Loc>>,
+}
+
+impl<Loc> Service<Loc> {
+    /// Constructs a service of a process without any commands or checks around it.
+    pub fn new(process: Process<Loc>) -> Self {
+        Self {
+            process,
+            dependency: None,
+            setup: None,
+            readiness: None,
+            teardown: None,
+        }
+    }
+
+    /// Sets a dependency that must be ready before the setup command runs.
+    pub fn dependency(mut self, dependency: impl Dependency + 'static) -> Self {
+        self.dependency = Some(Box::new(dependency));
+        self
+    }
+
+    /// Sets a command that runs once before the first start of the process.
+    pub fn setup(mut self, cmd: Cmd<Loc>) -> Self {
+        self.setup = Some(cmd);
+        self
+    }
+
+    /// Sets a check that tells the process is ready after a start.
+    pub fn readiness(mut self, check: impl Dependency + 'static) -> Self {
+        self.readiness = Some(Box::new(check));
+        self
+    }
+
+    /// Sets a command that runs once the process is stopped for good.
+    pub fn teardown(mut self, cmd: Cmd<Loc>) -> Self {
+        self.teardown = Some(cmd);
+        self
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use super::*;
+    use crate::{Env, FsEntry, KillTimeout, Location, PoolOptions, ProcessPool, ProcessStatus};
+
+    #[derive(Clone)]
+    struct Loc(PathBuf);
+
+    impl Location for Loc {
+        fn apex() -> Self {
+            Self(std::env::temp_dir().join(format!("steward-service-{}", std::process::id())))
+        }
+
+        fn as_path(&self) -> &PathBuf {
+            &self.0
+        }
+    }
+
+    #[tokio::test]
+    async fn lifecycle() {
+        let dir = Loc::apex();
+        std::fs::create_dir_all(dir.as_path()).unwrap();
+        let cmd = |exe: &str| Cmd::new(exe, Env::empty(), Loc::apex(), None);
+        let service = Service::new(Process::new(
+            "server",
+            cmd("touch ready && exec sleep 30"),
+            KillTimeout::new(Duration::from_secs(5)),
+        ))
+        .setup(cmd("touch setup"))
+        .readiness(FsEntry::new(
+            "ready",
+            Loc(dir.as_path().join("ready")),
+            Duration::from_secs(5),
+        ))
+        .teardown(cmd("rm setup ready"));
+
+        let pool = ProcessPool::start([service], PoolOptions::default());
+        pool.ready().await.unwrap();
+        let setup = dir.as_path().join("setup").exists();
+        let status = pool.process("server").unwrap().status;
+        pool.shutdown().await.unwrap();
+        let teardown = !dir.as_path().join("setup").exists();
+
+        std::fs::remove_dir_all(dir.as_path()).unwrap();
+        assert!(setup);
+        assert_eq!(status, ProcessStatus::Running);
+        assert!(teardown);
+    }
+}
@@ -0,0 +1,122 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    task::{self, JoinHandle},
+    time,
+};
+
+/// Staggered startup of the processes of a [`ProcessPool`](crate::ProcessPool), so a large pool doesn't
+/// spawn all of its processes (e.g. a dozen of compilers) at once. See [`PoolOptions::stagger`](crate::PoolOptions::stagger).
+///
+/// Only a limited number of processes are starting at a time, the rest wait for a slot right before spawning.
+/// A process waits for its dependency first, so the dependency order is respected, while independent processes
+/// are started in parallel up to the limit. Restarts are staggered as well.
+///
+/// ```ignore
+/// let opts = PoolOptions {
+///     // Starts the processes one by one, every 2 seconds
+///     stagger: Some(Stagger {
+///         delay: Duration::from_secs(2),
+///         ..Default::default()
+///     }),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stagger {
+    /// Max number of processes that are starting at once. Default: 1.
+    pub max_starting: usize,
+    /// How long a process is considered starting after it's spawned. A [`Service`](crate::Service) with a readiness
+    /// check is starting until the check completes instead. A process that exits is not starting anymore.
+    /// Default: 1 second.
+    pub delay: Duration,
+}
+
+impl Default for Stagger {
+    fn default() -> Self {
+        Self {
+            max_starting: 1,
+            delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Slots of the starting processes of a running pool.
+#[derive(Clone)]
+pub(crate) struct Starts {
+    slots: Arc<Semaphore>,
+    delay: Duration,
+}
+
+impl Starts {
+    pub(crate) fn new(stagger: Stagger) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(stagger.max_starting.max(1))),
+            delay: stagger.delay,
+        }
+    }
+
+    /// Waits for a slot to start a process.
+    pub(crate) async fn acquire(&self) -> Slot {
+        let permit = self
+            .slots
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("Semaphore of starts is never closed");
+        Slot {
+            _permit: permit,
+            delay: self.delay,
+        }
+    }
+}
+
+/// Slot of a starting process. Freed once dropped.
+pub(crate) struct Slot {
+    _permit: OwnedSemaphorePermit,
+    delay: Duration,
+}
+
+impl Slot {
+    /// Frees the slot once the delay passes. Aborting the returned task frees it right away.
+    pub(crate) fn release_after_delay(self) -> JoinHandle<()> {
+        task::spawn(async move {
+            time::sleep(self.delay).await;
+            drop(self);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn slots() {
+        let starts = Starts::new(Stagger {
+            max_starting: 1,
+            delay: Duration::from_millis(100),
+        });
+        let slot = starts.acquire().await;
+        // The only slot is taken
+        assert!(time::timeout(Duration::from_millis(50), starts.acquire())
+            .await
+            .is_err());
+
+        // Aborting the delay frees the slot right away
+        slot.release_after_delay().abort();
+        let slot = time::timeout(Duration::from_millis(50), starts.acquire())
+            .await
+            .unwrap();
+
+        // Otherwise, it's freed once the delay passes
+        let _release = slot.release_after_delay();
+        assert!(time::timeout(Duration::from_millis(50), starts.acquire())
+            .await
+            .is_err());
+        assert!(time::timeout(Duration::from_secs(1), starts.acquire())
+            .await
+            .is_ok());
+    }
+}
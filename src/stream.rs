@@ -0,0 +1,176 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Child,
+    sync::mpsc,
+    task,
+};
+
+use crate::RunningProcess;
+
+/// An item of [`Cmd::stream`](crate::Cmd::stream).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OutputLine {
+    /// A line of stdout, without a line ending. Invalid UTF-8 sequences are replaced with `�`.
+    Stdout(String),
+    /// A line of stderr, without a line ending. Invalid UTF-8 sequences are replaced with `�`.
+    Stderr(String),
+    /// The last item: an exit code of a process. Might be absent on Unix systems when a process was terminated by a signal.
+    Exit(Option<i32>),
+    /// The last item, when the output couldn't be read or the process couldn't be waited for.
+    Failed(String),
+}
+
+/// A stream of output lines of a process. See [`Cmd::stream`](crate::Cmd::stream).
+///
+/// Lines are read from the process only as fast as they are consumed, so a slow consumer
+/// eventually blocks the process on write. Dropping the stream kills the process.
+pub struct OutputStream {
+    rx: mpsc::Receiver<OutputLine>,
+}
+
+impl OutputStream {
+    /// Number of lines read ahead of the consumer.
+    const BUFFER: usize = 64;
+
+    pub(crate) fn new(mut running: RunningProcess) -> Self {
+        let (tx, rx) = mpsc::channel(Self::BUFFER);
+        let stdout = running.stdout();
+        let stderr = running.stderr();
+        let child = running.into_child();
+        task::spawn(self::drive(child, stdout, stderr, tx));
+        Self { rx }
+    }
+}
+
+impl Stream for OutputStream {
+    type Item = OutputLine;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+async fn drive<O, E>(
+    mut child: Child,
+    stdout: Option<O>,
+    stderr: Option<E>,
+    tx: mpsc::Sender<OutputLine>,
+) where
+    O: AsyncRead + Unpin,
+    E: AsyncRead + Unpin,
+{
+    let mut stdout = stdout.map(BufReader::new);
+    let mut stderr = stderr.map(BufReader::new);
+    let mut stdout_buf = vec![];
+    let mut stderr_buf = vec![];
+
+    while stdout.is_some() || stderr.is_some() {
+        let line = tokio::select! {
+            // The consumer is gone while the process is silent
+            _ = tx.closed() => {
+                let _ = child.kill().await;
+                return;
+            }
+            res = self::read_line(&mut stdout, &mut stdout_buf), if stdout.is_some() => {
+                match res {
+                    Ok(Some(line)) => OutputLine::Stdout(line),
+                    Ok(None) => { stdout = None; continue; }
+                    Err(err) => OutputLine::Failed(format!("Unable to read stdout: {}", err)),
+                }
+            }
+            res = self::read_line(&mut stderr, &mut stderr_buf), if stderr.is_some() => {
+                match res {
+                    Ok(Some(line)) => OutputLine::Stderr(line),
+                    Ok(None) => { stderr = None; continue; }
+                    Err(err) => OutputLine::Failed(format!("Unable to read stderr: {}", err)),
+                }
+            }
+        };
+        let failed = matches!(line, OutputLine::Failed(_));
+        // The consumer is gone (or the output is broken), so there is no point in keeping the process running
+        if tx.send(line).await.is_err() || failed {
+            let _ = child.kill().await;
+            return;
+        }
+    }
+
+    let last = tokio::select! {
+        res = child.wait() => match res {
+            Ok(status) => OutputLine::Exit(status.code()),
+            Err(err) => OutputLine::Failed(format!("Unable to wait for the process: {}", err)),
+        },
+        _ = tx.closed() => {
+            let _ = child.kill().await;
+            return;
+        }
+    };
+    let _ = tx.send(last).await;
+}
+
+/// Reads a line without a line ending. Returns `None` at the end of the stream.
+///
+/// Reading is cancelled when the other stream yields a line first, but the partially read bytes
+/// are kept in the buffer, so the buffer is cleared only once a line is complete.
+async fn read_line<R>(
+    reader: &mut Option<BufReader<R>>,
+    buf: &mut Vec<u8>,
+) -> std::io::Result<Option<String>>
+where
+    R: AsyncRead + Unpin,
+{
+    let reader = match reader {
+        Some(reader) => reader,
+        None => return Ok(None),
+    };
+    if reader.read_until(b'\n', buf).await? == 0 && buf.is_empty() {
+        return Ok(None);
+    }
+    if buf.ends_with(b"\n") {
+        buf.pop();
+        if buf.ends_with(b"\r") {
+            buf.pop();
+        }
+    }
+    let line = String::from_utf8_lossy(buf).into_owned();
+    buf.clear();
+    Ok(Some(line))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::process::Stdio;
+
+    use tokio::{process::Command, sync::mpsc};
+
+    use super::OutputLine;
+
+    #[tokio::test]
+    async fn kill_on_drop() {
+        let mut child = Command::new("sh")
+            .args(["-c", "echo $$; exec sleep 30"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdout = child.stdout.take();
+        let (tx, mut rx) = mpsc::channel(1);
+        let drive = tokio::spawn(super::drive(child, stdout, None::<tokio::io::Empty>, tx));
+
+        let pid = match rx.recv().await {
+            Some(OutputLine::Stdout(pid)) => pid.parse().unwrap(),
+            line => panic!("Unexpected line: {:?}", line),
+        };
+        drop(rx);
+        // The process is killed right away instead of sleeping till the end
+        tokio::time::timeout(std::time::Duration::from_secs(5), drive)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!crate::os::is_running(pid));
+    }
+}
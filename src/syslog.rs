@@ -0,0 +1,88 @@
+use std::{io, os::unix::net::UnixDatagram, sync::Arc};
+
+/// A system log that output of the processes of a [`ProcessPool`](crate::ProcessPool) is forwarded to,
+/// in addition to the console. See [`PoolOptions::log_sink`](crate::PoolOptions::log_sink).
+///
+/// Every line is sent with the `info` priority and the tag of a process as an identifier,
+/// so the output of a single process can be viewed via e.g. `journalctl -t server`. ANSI escape codes are stripped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogSink {
+    /// The local syslog daemon (`/dev/log`, or `/var/run/syslog` on macOS). Lines are sent with the `user` facility.
+    Syslog,
+    /// `systemd-journald` via its native protocol. Linux only.
+    Journald,
+}
+
+impl LogSink {
+    fn socket(&self) -> &'static str {
+        match self {
+            #[cfg(target_os = "macos")]
+            Self::Syslog => "/var/run/syslog",
+            #[cfg(not(target_os = "macos"))]
+            Self::Syslog => "/dev/log",
+            Self::Journald => "/run/systemd/journal/socket",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Syslog => "syslog",
+            Self::Journald => "journald",
+        }
+    }
+}
+
+/// Connection to a [`LogSink`](LogSink) of a process.
+#[derive(Clone)]
+pub(crate) struct Sink {
+    kind: LogSink,
+    tag: &'static str,
+    socket: Arc<UnixDatagram>,
+}
+
+impl Sink {
+    /// Connects to a log sink. Prints a warning and returns `None` if it's not available.
+    pub(crate) fn connect(kind: LogSink, tag: &'static str) -> Option<Self> {
+        let connect = || -> io::Result<UnixDatagram> {
+            let socket = UnixDatagram::unbound()?;
+            socket.connect(kind.socket())?;
+            // A line is dropped rather than blocking the output of a process when the log is congested
+            socket.set_nonblocking(true)?;
+            Ok(socket)
+        };
+        match connect() {
+            Ok(socket) => Some(Self {
+                kind,
+                tag,
+                socket: Arc::new(socket),
+            }),
+            Err(error) => {
+                console!(
+                    "⚠️  Failed to connect to {}: {}. Output of {} is not forwarded.",
+                    kind.name(),
+                    error,
+                    crate::fmt::style(tag).bold()
+                );
+                None
+            }
+        }
+    }
+
+    /// Sends a line of output of the process.
+    pub(crate) fn send(&self, line: &str) {
+        let line = console::strip_ansi_codes(line);
+        self.socket.send(self.message(&line).as_bytes()).ok();
+    }
+
+    fn message(&self, line: &str) -> String {
+        match self.kind {
+            // `<PRI>TAG: MSG` where `PRI` is `user` facility (1) * 8 + `info` severity (6)
+            LogSink::Syslog => format!("<14>{}: {}", self.tag, line),
+            // Lines never contain newlines, so the simple `KEY=value` form suffices
+            LogSink::Journald => format!(
+                "PRIORITY=6\nSYSLOG_IDENTIFIER={}\nMESSAGE={}\n",
+                self.tag, line
+            ),
+        }
+    }
+}